@@ -0,0 +1,320 @@
+// Internal crates.
+use crate::{
+    error::SystemError,
+    system::{self, VirtualFile},
+};
+
+// External crates.
+use flate2::read::GzDecoder;
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::{Component, Path},
+};
+use tar::{Archive, Entry};
+use zip::{ZipArchive, read::ZipFile};
+
+/// This function is responsible for checking if a path points to a supported archive file.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path to check.
+///
+/// # Returns
+///
+/// True if the path is a regular file with a supported archive extension (see ARCHIVE_FORMATS), false otherwise.
+///
+pub fn is_supported_archive(path: &str) -> bool {
+    Path::new(path).is_file() && archive_format(path).is_some()
+}
+
+/// The supported archive formats, with their extensions.
+pub const ARCHIVE_FORMATS: [(&str, &[&str]); 2] = [("zip", &[".zip"]), ("tar", &[".tar", ".tar.gz", ".tgz"])];
+
+/// This function is responsible for finding the archive format of a path from its extension.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path to check.
+///
+/// # Returns
+///
+/// The name of the format ("zip" or "tar"), None if the extension isn't a supported archive one.
+///
+fn archive_format(path: &str) -> Option<&'static str> {
+    let lowercase_path: String = path.to_lowercase();
+    ARCHIVE_FORMATS
+        .iter()
+        .find(|(_, extensions)| extensions.iter().any(|extension| lowercase_path.ends_with(extension)))
+        .map(|(format, _)| *format)
+}
+
+/// This function is responsible for building virtual files from the entries of an archive.
+/// Each entry is streamed and hashed in memory, the archive is never extracted to the disk.
+///
+/// # Arguments
+///
+/// * `archive_path` - A string slice that holds the path of the archive.
+/// * `include_hidden_files` - A boolean that indicates if hidden entries should be included or not.
+/// * `hash_algorithms` - The hash algorithms to apply on each entry, empty for a search by name only.
+///
+/// # Returns
+///
+/// A vector of VirtualFile structs, one per file entry of the archive, SystemError otherwise.
+///
+pub fn build_archive_virtual_files(archive_path: &str, include_hidden_files: bool, hash_algorithms: &[String]) -> Result<Vec<VirtualFile>, SystemError> {
+    if archive_format(archive_path) == Some("tar") {
+        build_tar_virtual_files(archive_path, include_hidden_files, hash_algorithms)
+    } else {
+        build_zip_virtual_files(archive_path, include_hidden_files, hash_algorithms)
+    }
+}
+
+/// This function is responsible for building virtual files from the entries of a zip archive.
+///
+/// # Arguments
+///
+/// * `archive_path` - A string slice that holds the path of the archive.
+/// * `include_hidden_files` - A boolean that indicates if hidden entries should be included or not.
+/// * `hash_algorithms` - The hash algorithms to apply on each entry, empty for a search by name only.
+///
+/// # Returns
+///
+/// A vector of VirtualFile structs, one per file entry of the archive, SystemError otherwise.
+///
+fn build_zip_virtual_files(archive_path: &str, include_hidden_files: bool, hash_algorithms: &[String]) -> Result<Vec<VirtualFile>, SystemError> {
+    let input: File = File::open(archive_path).map_err(|e| SystemError::UnableToReadArchive(archive_path.to_string(), e))?;
+    let mut archive: ZipArchive<BufReader<File>> = ZipArchive::new(BufReader::new(input))
+        .map_err(|e| SystemError::UnableToReadArchive(archive_path.to_string(), e.into()))?;
+    let mut virtual_files: Vec<VirtualFile> = Vec::new();
+
+    for index in 0..archive.len() {
+        let entry: ZipFile = archive.by_index(index).map_err(|e| SystemError::UnableToReadArchive(archive_path.to_string(), e.into()))?;
+
+        // Skip directories and entries trying to escape the archive (e.g. "../file").
+        if entry.is_dir() || entry.enclosed_name().is_none() {
+            continue;
+        }
+
+        let entry_name: String = entry.name().to_string();
+        if !include_hidden_files && entry_name.split('/').any(|component| component.starts_with('.')) {
+            continue;
+        }
+
+        let Some(name) = Path::new(&entry_name).file_name().and_then(|n| n.to_str()) else { continue };
+        let mut file: VirtualFile = VirtualFile {
+            name: name.to_string(),
+            size: entry.size(),
+            full_path: format!("{archive_path}/{entry_name}"),
+            checksums: None,
+            ..Default::default()
+        };
+        drop(entry);
+
+        // The entry is decompressed again for each algorithm, to keep the memory usage bounded.
+        for hash_algorithm in hash_algorithms {
+            let entry: ZipFile = archive.by_index(index).map_err(|e| SystemError::UnableToReadArchive(archive_path.to_string(), e.into()))?;
+            let checksum: Option<String> = system::manage_hash_reader(entry, hash_algorithm)
+                .map_err(|e| SystemError::UnableToReadArchive(archive_path.to_string(), e))?;
+            if let Some(checksum) = checksum {
+                file.update_checksum(hash_algorithm, checksum);
+            }
+        }
+
+        virtual_files.push(file);
+    }
+
+    Ok(virtual_files)
+}
+
+/// This function is responsible for building virtual files from the entries of a tar archive, compressed with gzip or not.
+/// A tar archive can only be read sequentially, so it is read once per hash algorithm to keep the memory usage bounded.
+///
+/// # Arguments
+///
+/// * `archive_path` - A string slice that holds the path of the archive.
+/// * `include_hidden_files` - A boolean that indicates if hidden entries should be included or not.
+/// * `hash_algorithms` - The hash algorithms to apply on each entry, empty for a search by name only.
+///
+/// # Returns
+///
+/// A vector of VirtualFile structs, one per regular file entry of the archive, SystemError otherwise.
+///
+fn build_tar_virtual_files(archive_path: &str, include_hidden_files: bool, hash_algorithms: &[String]) -> Result<Vec<VirtualFile>, SystemError> {
+    let mut virtual_files: Vec<VirtualFile> = Vec::new();
+    for_each_tar_entry(archive_path, include_hidden_files, |entry_name, entry| {
+        let Some(name) = Path::new(&entry_name).file_name().and_then(|n| n.to_str()) else { return Ok(()) };
+        let mut file: VirtualFile = VirtualFile {
+            name: name.to_string(),
+            size: entry.header().size().unwrap_or_default(),
+            full_path: format!("{archive_path}/{entry_name}"),
+            checksums: None,
+            ..Default::default()
+        };
+
+        if let Some(hash_algorithm) = hash_algorithms.first() {
+            hash_tar_entry(archive_path, entry, hash_algorithm, &mut file)?;
+        }
+
+        virtual_files.push(file);
+        Ok(())
+    })?;
+
+    // The entries are visited in the same order on each pass, so they match the virtual files by index.
+    for hash_algorithm in hash_algorithms.iter().skip(1) {
+        let mut index: usize = 0;
+        for_each_tar_entry(archive_path, include_hidden_files, |_, entry| {
+            hash_tar_entry(archive_path, entry, hash_algorithm, &mut virtual_files[index])?;
+            index += 1;
+            Ok(())
+        })?;
+    }
+
+    Ok(virtual_files)
+}
+
+/// This function is responsible for visiting the regular file entries of a tar archive.
+/// Directories, links and entries trying to escape the archive (e.g. "../file") are skipped.
+///
+/// # Arguments
+///
+/// * `archive_path` - A string slice that holds the path of the archive.
+/// * `include_hidden_files` - A boolean that indicates if hidden entries should be included or not.
+/// * `visit` - The closure called with the normalized name and the stream of each entry.
+///
+/// # Returns
+///
+/// Ok if the whole archive has been read, SystemError otherwise.
+///
+fn for_each_tar_entry<F>(archive_path: &str, include_hidden_files: bool, mut visit: F) -> Result<(), SystemError>
+where
+    F: FnMut(String, Entry<'_, Box<dyn Read>>) -> Result<(), SystemError>,
+{
+    let archive_error = |e: std::io::Error| SystemError::UnableToReadArchive(archive_path.to_string(), e);
+    let input: BufReader<File> = BufReader::new(File::open(archive_path).map_err(archive_error)?);
+    let reader: Box<dyn Read> = if archive_path.to_lowercase().ends_with(".tar") { Box::new(input) } else { Box::new(GzDecoder::new(input)) };
+    let mut archive: Archive<Box<dyn Read>> = Archive::new(reader);
+
+    for entry in archive.entries().map_err(archive_error)? {
+        let entry: Entry<'_, Box<dyn Read>> = entry.map_err(archive_error)?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let mut components: Vec<String> = Vec::new();
+        let mut escaping: bool = false;
+        for component in entry.path().map_err(archive_error)?.components() {
+            match component {
+                Component::Normal(component) => components.push(component.to_string_lossy().to_string()),
+                Component::CurDir => {}
+                _ => escaping = true,
+            }
+        }
+
+        if escaping || components.is_empty() || (!include_hidden_files && components.iter().any(|component| component.starts_with('.'))) {
+            continue;
+        }
+
+        visit(components.join("/"), entry)?;
+    }
+
+    Ok(())
+}
+
+/// This function is responsible for hashing the stream of a tar entry and storing the checksum in its virtual file.
+///
+/// # Arguments
+///
+/// * `archive_path` - A string slice that holds the path of the archive.
+/// * `entry` - The entry to hash.
+/// * `hash_algorithm` - A string slice that holds the hash algorithm to apply.
+/// * `file` - The virtual file of the entry.
+///
+/// # Returns
+///
+/// Ok if the entry has been hashed, SystemError otherwise.
+///
+fn hash_tar_entry(archive_path: &str, entry: Entry<'_, Box<dyn Read>>, hash_algorithm: &str, file: &mut VirtualFile) -> Result<(), SystemError> {
+    let checksum: Option<String> =
+        system::manage_hash_reader(entry, hash_algorithm).map_err(|e| SystemError::UnableToReadArchive(archive_path.to_string(), e))?;
+    if let Some(checksum) = checksum {
+        file.update_checksum(hash_algorithm, checksum);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, io::Write, path::PathBuf};
+    use flate2::{Compression, write::GzEncoder};
+    use tar::{Builder, EntryType, Header};
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    #[test]
+    fn test_build_archive_virtual_files() {
+        let archive_path: PathBuf = env::temp_dir().join("deepfinder_test_archive.zip");
+        let mut writer: ZipWriter<File> = ZipWriter::new(File::create(&archive_path).unwrap());
+        writer.add_directory("nested/dir/", SimpleFileOptions::default()).unwrap();
+        writer.start_file("original.txt", SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"same content").unwrap();
+        writer.start_file("nested/dir/copy.txt", SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"same content").unwrap();
+        writer.start_file("other.txt", SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"other content").unwrap();
+        writer.finish().unwrap();
+
+        let archive: &str = archive_path.to_str().unwrap();
+        assert!(is_supported_archive(archive));
+
+        let virtual_files: Vec<VirtualFile> = build_archive_virtual_files(archive, false, &["md5".to_string()]).unwrap();
+        assert_eq!(virtual_files.len(), 3);
+        assert_eq!(virtual_files[1].name, "copy.txt");
+        assert_eq!(virtual_files[1].full_path, format!("{archive}/nested/dir/copy.txt"));
+        assert_eq!(virtual_files[0].size, 12);
+        assert_eq!(virtual_files[0].checksums, virtual_files[1].checksums);
+        assert_ne!(virtual_files[0].checksums, virtual_files[2].checksums);
+
+        let _ = fs::remove_file(archive_path);
+    }
+
+    #[test]
+    fn test_build_tar_virtual_files() {
+        let archive_path: PathBuf = env::temp_dir().join("deepfinder_test_archive.tar.gz");
+        let mut builder: Builder<GzEncoder<File>> = Builder::new(GzEncoder::new(File::create(&archive_path).unwrap(), Compression::default()));
+        let mut append = |path: &str, entry_type: EntryType, content: &[u8]| {
+            let mut header: Header = Header::new_gnu();
+            header.set_entry_type(entry_type);
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            if entry_type == EntryType::Symlink {
+                header.set_link_name("original.txt").unwrap();
+            }
+            builder.append_data(&mut header, path, content).unwrap();
+        };
+        append("./nested/dir/", EntryType::Directory, b"");
+        append("./original.txt", EntryType::Regular, b"same content");
+        append("./nested/dir/copy.txt", EntryType::Regular, b"same content");
+        append("./nested/link.txt", EntryType::Symlink, b"");
+        append("./.hidden/copy.txt", EntryType::Regular, b"same content");
+        append("./other.txt", EntryType::Regular, b"other content");
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let archive: &str = archive_path.to_str().unwrap();
+        assert!(is_supported_archive(archive));
+
+        let hash_algorithms: Vec<String> = vec!["md5".to_string(), "sha256".to_string()];
+        let virtual_files: Vec<VirtualFile> = build_archive_virtual_files(archive, false, &hash_algorithms).unwrap();
+        assert_eq!(virtual_files.len(), 3);
+        assert_eq!(virtual_files[1].name, "copy.txt");
+        assert_eq!(virtual_files[1].full_path, format!("{archive}/nested/dir/copy.txt"));
+        assert_eq!(virtual_files[0].size, 12);
+        assert_eq!(virtual_files[0].checksums.as_ref().unwrap().len(), 2);
+        assert_eq!(virtual_files[0].checksums, virtual_files[1].checksums);
+        assert_ne!(virtual_files[0].checksums, virtual_files[2].checksums);
+        assert_eq!(build_archive_virtual_files(archive, true, &hash_algorithms).unwrap().len(), 4);
+
+        let _ = fs::remove_file(archive_path);
+    }
+}
@@ -1,30 +1,233 @@
 // Internal crates.
 use crate::{
-    error::{ArgError, DeepFinderError},
-    system,
+    archive,
+    export::{self, FORMAT_PLACEHOLDERS},
+    error::{ArgError, DeepFinderError, SystemError},
+    search_engine::{EmptyDirCollector, WarningCollector, record_warning},
+    system::{self, Transform},
 };
 
 // External crates.
-use clap::{Arg, ArgAction, ArgMatches, Command};
-use std::process::exit;
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command, parser::ValueSource};
+use regex::Regex;
+use serde::Serialize;
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    path::Path,
+    process::exit,
+};
+
+/// The hash algorithms that can be chosen by the user.
+const HASH_ALGORITHMS: [&str; 15] = [
+    "md5",
+    "sha1",
+    "sha224",
+    "sha256",
+    "sha384",
+    "sha512",
+    "sha3-224",
+    "sha3-256",
+    "sha3-384",
+    "sha3-512",
+    "blake2b-512",
+    "blake2s-256",
+    "whirlpool",
+    "blake3",
+    "xxh3",
+];
+
+/// The checksums of "abc" for each hash algorithm, to check that the hashing works ('--doctor').
+const HASH_TEST_VECTORS: [(&str, &str); 15] = [
+    ("md5", "900150983cd24fb0d6963f7d28e17f72"),
+    ("sha1", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+    ("sha224", "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7"),
+    ("sha256", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+    ("sha384", "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7"),
+    ("sha512", "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"),
+    ("sha3-224", "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf"),
+    ("sha3-256", "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"),
+    ("sha3-384", "ec01498288516fc926459f58e2c6ad8df9b473cb0fc08c2596da7cf0e49be4b298d88cea927ac7f539f1edf228376d25"),
+    ("sha3-512", "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0"),
+    ("blake2b-512", "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"),
+    ("blake2s-256", "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982"),
+    ("whirlpool", "4e2448a4c6f486bb16b6562c73b4020bf3043e3a731bce721ae1b303d97e6d4c7181eebdb6c57e277d0e34957114cbd6c797fc9d95d8b582d225292076d4eef5"),
+    ("blake3", "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"),
+    ("xxh3", "78af5f94892f3950"),
+];
+
+/// The ignore file loaded from the scan root, unless another one is given with '--ignore-file'.
+const IGNORE_FILE_NAME: &str = ".deepfinderignore";
+
+/// The arguments that can't be used when the search path is an archive, with their name on the command line.
+const ARCHIVE_UNSUPPORTED_ARGS: [(&str, &str); 3] =
+    [("content_type", "--content-type"), ("verify_sidecars", "--verify-sidecars"), ("image_ahash", "--image-ahash")];
+
+/// The optional capabilities of DeepFinder provided by an argument, with its identifier.
+/// A capability is only listed in the features if its argument is defined (see the 'features' function).
+const FEATURE_ARGS: [(&str, &str); 4] =
+    [("zstd-output", "compress"), ("image-ahash", "image_ahash"), ("parallel-blake3", "parallel_hash_within_file"), ("xxh3-prefilter", "prefilter")];
+
+/// This struct is the machine-readable version of DeepFinder ('--version-json').
+///
+#[derive(Serialize)]
+struct VersionInfo {
+    name: &'static str,
+    version: &'static str,
+    features: Vec<String>,
+}
 
 /// This struct is built from the values/choices of the user.
 ///
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Eq, PartialEq, Debug, Default)]
 pub struct FindingConfig {
     pub search_path: String,
     pub enable_search_by_name: bool,
     pub include_hidden_files: bool,
+    pub exclude: Vec<String>,
+    pub exclude_regex: Vec<PathRegex>,
+    pub content_type: Option<String>,
+    pub skip_dirs: Vec<String>,
+    pub skip_top: Vec<String>,
+    pub exclude_stubs: bool,
+    pub follow_reparse_points: bool,
+    pub include_symlinks_as_entries: bool,
     pub include_hashes_in_output: bool,
+    pub combined_hash: bool,
+    pub hash_truncate: Option<usize>,
     pub hash: Option<Vec<String>>,
+    pub prefilter: Option<String>,
+    pub algo_for_large: Option<(String, u64)>,
     pub output: CliOutput,
+    pub temp_dir: Option<String>,
+    pub compress: bool,
+    pub read_only: bool,
+    pub filter_report: Option<String>,
+    pub record_filelist: Option<String>,
+    pub replay_filelist: Option<String>,
+    pub from_stdin0: bool,
+    pub merge_reports: Option<Vec<String>>,
+    pub compare: Option<(String, String)>,
+    pub verify_sidecars: bool,
+    pub hash_stdin: bool,
+    pub min_size: Option<u64>,
+    pub min_dir_size: Option<u64>,
+    pub min_wasted: Option<u64>,
+    pub min_occurrences: Option<usize>,
+    pub only: Vec<String>,
+    pub csv_style: CsvStyle,
+    pub xml_style: XmlStyle,
+    pub image_ahash: Option<u32>,
+    pub size_tolerance: Option<u32>,
+    pub ignore_extension: bool,
+    pub normalize_unicode: bool,
+    pub detect_confusables: bool,
+    pub name_then_hash: bool,
+    pub distinct_empty: bool,
+    pub unique_content: bool,
+    pub largest_first: bool,
+    pub list_hashes: bool,
+    pub byte_budget: Option<u64>,
+    pub checkpoint_interval: Option<u64>,
+    pub io_throttle: Option<u64>,
+    pub slow_file_threshold: Option<u64>,
+    pub keep_policy: KeepPolicy,
+    pub prefer_dirs: Vec<String>,
+    pub scope: Scope,
+    pub list_deletable: bool,
+    pub emit_script: Option<String>,
+    pub on_group: Option<String>,
+    pub strict: bool,
+    pub color: bool,
+    pub relative_times: bool,
+    pub quiet_if_empty: bool,
+    pub fail_on_duplicates: bool,
+    pub quiet: bool,
+    pub progress_json: bool,
+    pub benchmark: bool,
+    pub max_paths_per_group: Option<usize>,
+    pub format_template: Option<String>,
+    pub summary_report: bool,
+    pub by_dir_pairs: bool,
+    pub report_empty_dirs: bool,
+    pub json_tree: bool,
+    pub all_files: bool,
+    pub parallel_hash_within_file: bool,
+    pub transform: Transform,
+    pub content_case_insensitive: bool,
+    pub ignore_metadata: bool,
+    pub warnings: WarningCollector,
+    pub empty_dirs: EmptyDirCollector,
+}
+
+/// This struct holds the style of the XML output (indentation and line endings).
+/// The default style is compact, on a single line.
+///
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub struct XmlStyle {
+    pub indent: bool,
+    pub crlf: bool,
+}
+
+/// This struct holds the style of the CSV output (delimiter, quoting and checksums layout).
+/// The default style uses ';' as delimiter, quotes the fields only when necessary and writes the checksums in a single cell.
+///
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub struct CsvStyle {
+    pub delimiter: u8,
+    pub always_quote: bool,
+    pub hash_columns: bool,
+}
+
+impl Default for CsvStyle {
+    fn default() -> Self {
+        Self { delimiter: b';', always_quote: false, hash_columns: false }
+    }
+}
+
+/// This struct holds a compiled regular expression matched against the full paths.
+/// Two regular expressions are equal if they have the same pattern.
+///
+#[derive(Debug, Clone)]
+pub struct PathRegex(pub Regex);
+
+impl PartialEq for PathRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for PathRegex {}
+
+/// This enum is used to determine which file of a duplicate group is kept, the others being deletable.
+/// The ties are always broken by the alphabetical order of the paths.
+///
+#[derive(Eq, PartialEq, Debug, Default, Clone, Copy)]
+pub enum KeepPolicy {
+    #[default]
+    First,
+    Last,
+    Shortest,
+    Longest,
+    Oldest,
+    Newest,
+}
+
+/// This enum is used to determine which duplicate groups are reported, according to the folders of their paths.
+///
+#[derive(Eq, PartialEq, Debug, Default, Clone, Copy)]
+pub enum Scope {
+    #[default]
+    Global,
+    CrossDir,
 }
 
 /// This enum is used to determine the output format of the program.
 /// If no output format is specified, the program will display the results in STDIN, in a raw format.
 /// 
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Eq, PartialEq, Debug, Default)]
 pub enum CliOutput {
+    #[default]
     Standard,
     CsvStdin,
     CsvFile(String),
@@ -32,6 +235,26 @@ pub enum CliOutput {
     JsonFile(String),
     XmlStdin,
     XmlFile(String),
+    CsvStdinAndFile(String),
+    JsonStdinAndFile(String),
+    XmlStdinAndFile(String),
+}
+
+impl CliOutput {
+    /// This function is responsible for getting the path of the output file, if the results are written to a file.
+    ///
+    /// # Returns
+    ///
+    /// The path of the output file, None if the results are only displayed.
+    ///
+    pub fn file_path(&self) -> Option<&str> {
+        match self {
+            Self::CsvFile(path) | Self::JsonFile(path) | Self::XmlFile(path) | Self::CsvStdinAndFile(path) | Self::JsonStdinAndFile(path) | Self::XmlStdinAndFile(path) => {
+                Some(path)
+            }
+            Self::Standard | Self::CsvStdin | Self::JsonStdin | Self::XmlStdin => None,
+        }
+    }
 }
 
 /// This function is responsible for building the command context for the CLI with the clap framework.
@@ -46,12 +269,15 @@ fn build_command_context() -> Command {
         .arg_required_else_help(true)
         .disable_help_flag(true) // Keep the help handling in the run() function
         .disable_version_flag(true) // Keep the version handling in the run() function
+        .group(ArgGroup::new("output_file").args(["csv_output", "json_output", "xml_output"]).multiple(true))
+        .group(ArgGroup::new("xml").args(["xml_display", "xml_output"]).multiple(true))
         .arg(
             Arg::new("path")
                 .index(1)
                 .value_name("path")
                 .value_parser(clap::builder::NonEmptyStringValueParser::new())
-                .help("The path to the directory to search for duplicates"),
+                .help("The path to the directory to search for duplicates")
+                .conflicts_with_all(["filter_report", "merge_reports", "compare", "hash_stdin", "replay_filelist", "from_stdin0"]),
         )
         .arg(
             Arg::new("name")
@@ -60,30 +286,163 @@ fn build_command_context() -> Command {
                 .help("Allow duplicate finding by the filename")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("ignore_extension")
+                .long("ignore-extension")
+                .help("Ignore the extension of the files when finding duplicates by their name")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("normalize_unicode")
+                .long("normalize-unicode")
+                .help("Normalize the names (NFC) before comparing them, so the NFC and NFD forms of a name match")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("detect_confusables")
+                .long("detect-confusables")
+                .help("Group the names that look identical (invisible characters, homoglyphs) and flag them")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["hash_algorithm", "image_ahash"]),
+        )
+        .arg(
+            Arg::new("name_then_hash")
+                .long("name-then-hash")
+                .help("Only hash the files whose names collide, then group the same-named files by content")
+                .action(ArgAction::SetTrue)
+                .requires("hash_algorithm")
+                .conflicts_with_all(["name", "image_ahash", "size_tolerance", "list_hashes", "all_files"]),
+        )
+        .arg(
+            Arg::new("algo_for_large")
+                .long("algo-for-large")
+                .help("Hash the files of at least --large-threshold bytes with this algorithm instead of the primary ones")
+                .value_parser(HASH_ALGORITHMS)
+                .value_name("hash")
+                .requires_all(["hash_algorithm", "large_threshold"])
+                .conflicts_with_all(["prefilter", "image_ahash", "size_tolerance"]),
+        )
+        .arg(
+            Arg::new("large_threshold")
+                .long("large-threshold")
+                .help("The size from which the files are hashed with --algo-for-large")
+                .value_parser(parse_size)
+                .value_name("size")
+                .requires("algo_for_large"),
+        )
+        .arg(
+            Arg::new("unique_content")
+                .long("unique-content")
+                .help("Count each physical file once, the hard links and bind mounts of a scanned file being ignored")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("distinct_empty")
+                .long("distinct-empty")
+                .help("Never group the empty files together when comparing the contents")
+                .action(ArgAction::SetTrue)
+                .requires("hash_algorithm")
+                .conflicts_with_all(["name", "image_ahash"]),
+        )
+        .arg(
+            Arg::new("largest_first")
+                .long("largest-first")
+                .help("Hash the largest files first, and report the groups from the largest to the smallest")
+                .action(ArgAction::SetTrue)
+                .requires("hash_algorithm")
+                .conflicts_with_all(["name", "image_ahash", "size_tolerance"]),
+        )
         .arg(
             Arg::new("hash_algorithm")
                 .short('a')
                 .long("hash-algorithm")
                 .value_delimiter(',')
-                .value_parser(vec![
-                    "md5",
-                    "sha1",
-                    "sha224",
-                    "sha256",
-                    "sha384",
-                    "sha512",
-                    "sha3-224",
-                    "sha3-256",
-                    "sha3-384",
-                    "sha3-512",
-                    "blake2b-512",
-                    "blake2s-256",
-                    "whirlpool",
-                ])
+                .value_parser(HASH_ALGORITHMS)
+                .visible_alias("confirm")
                 .help("Allow duplicate finding by one or multiple hash algorithms")
                 .value_name("hash")
                 .num_args(1..),
         )
+        .arg(
+            Arg::new("prefilter")
+                .long("prefilter")
+                .help("Hash every file with a fast algorithm first, the -a algorithms only confirm the candidates")
+                .value_parser(HASH_ALGORITHMS)
+                .value_name("hash")
+                .requires("hash_algorithm")
+                .conflicts_with_all(["list_hashes", "image_ahash", "name"]),
+        )
+        .arg(
+            Arg::new("list_hashes")
+                .long("list-hashes")
+                .help("List the checksums of every file instead of finding duplicates")
+                .action(ArgAction::SetTrue)
+                .requires("hash_algorithm")
+                .conflicts_with_all(["filter_report", "merge_reports", "image_ahash"]),
+        )
+        .arg(
+            Arg::new("parallel_hash_within_file")
+                .long("parallel-hash-within-file")
+                .help("Hash each large file with several threads (blake3 only)")
+                .action(ArgAction::SetTrue)
+                .requires("hash_algorithm"),
+        )
+        .arg(
+            Arg::new("transform")
+                .long("transform")
+                .help("Apply a transformation on the content of the files before hashing them")
+                .value_parser(system::TRANSFORMS)
+                .value_name("transform")
+                .requires("hash_algorithm")
+                .conflicts_with("image_ahash"),
+        )
+        .arg(
+            Arg::new("content_case_insensitive")
+                .long("content-case-insensitive")
+                .help("Hash the text files lowercased, so the texts differing only by their case match")
+                .action(ArgAction::SetTrue)
+                .requires("hash_algorithm")
+                .conflicts_with_all(["transform", "image_ahash", "prefilter"]),
+        )
+        .arg(
+            Arg::new("ignore_metadata")
+                .long("ignore-metadata")
+                .help("Hash the decoded pixels of the images, so the images differing only by their metadata (EXIF...) match")
+                .action(ArgAction::SetTrue)
+                .requires("hash_algorithm")
+                .conflicts_with_all(["image_ahash", "transform", "prefilter"]),
+        )
+        .arg(
+            Arg::new("byte_budget")
+                .long("byte-budget")
+                .help("Stop hashing new files once the given number of bytes has been hashed")
+                .value_parser(parse_size)
+                .value_name("size"),
+        )
+        .arg(
+            Arg::new("checkpoint_interval")
+                .long("checkpoint-interval")
+                .help("Write the duplicates confirmed so far to the output file every N seconds while hashing")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .value_name("secs")
+                .requires_all(["hash_algorithm", "output_file"])
+                .conflicts_with_all(["prefilter", "image_ahash", "byte_budget", "list_hashes", "list_deletable", "json_tree", "report"]),
+        )
+        .arg(
+            Arg::new("io_throttle")
+                .long("io-throttle")
+                .help("Limit the read bandwidth while hashing, in MB/s")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .value_name("MB/s"),
+        )
+        .arg(
+            Arg::new("slow_file_threshold")
+                .long("slow-file-threshold")
+                .help("Warn about the files whose hashing takes longer than the given number of milliseconds")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .value_name("ms")
+                .requires("hash_algorithm"),
+        )
         .arg(
             Arg::new("hidden_files")
                 .short('f')
@@ -91,6 +450,72 @@ fn build_command_context() -> Command {
                 .help("Allow duplicate finding for hidden files")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("exclude")
+                .long("exclude")
+                .help("Exclude the files and folders whose name matches the glob pattern")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .value_name("pattern")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("ignore_file")
+                .long("ignore-file")
+                .help("Read the exclude patterns from the given file instead of the .deepfinderignore file of the scan root")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .value_name("path"),
+        )
+        .arg(
+            Arg::new("exclude_regex")
+                .long("exclude-regex")
+                .help("Exclude the files and folders whose full path matches the regular expression")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .value_name("pattern")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("skip_dir")
+                .long("skip-dir")
+                .help("Skip the given directory and its whole subtree")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .value_name("path")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("skip_top")
+                .long("skip-top")
+                .help("Skip the folders with the given names, only when they are direct children of the scanned folder")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .value_name("name")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("exclude_stubs")
+                .long("exclude-stubs")
+                .help("Skip the cloud placeholders (OneDrive, Dropbox...), to avoid downloading them")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("follow_reparse_points")
+                .long("follow-reparse-points")
+                .help("Follow the junctions and the other reparse points (Windows only), they are skipped by default")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("include_symlinks_as_entries")
+                .long("include-symlinks-as-entries")
+                .help("List the symbolic links in the manifest, with their target, instead of following them")
+                .action(ArgAction::SetTrue)
+                .requires("list_hashes"),
+        )
+        .arg(
+            Arg::new("content_type")
+                .long("content-type")
+                .help("Only search the files whose content (magic bytes) has the given type")
+                .value_parser(["app", "archive", "audio", "book", "doc", "font", "image", "text", "video"])
+                .value_name("type")
+                .conflicts_with_all(["filter_report", "merge_reports"]),
+        )
         .arg(
             Arg::new("csv_display")
                 .short('c')
@@ -102,7 +527,6 @@ fn build_command_context() -> Command {
                     "xml_output",
                     "json_display",
                     "json_output",
-                    "csv_output",
                 ]),
         )
         .arg(
@@ -117,7 +541,6 @@ fn build_command_context() -> Command {
                     "xml_output",
                     "json_display",
                     "json_output",
-                    "csv_display",
                 ]),
         )
         .arg(
@@ -129,7 +552,6 @@ fn build_command_context() -> Command {
                 .conflicts_with_all([
                     "xml_display",
                     "xml_output",
-                    "json_output",
                     "csv_display",
                     "csv_output",
                 ]),
@@ -144,7 +566,6 @@ fn build_command_context() -> Command {
                 .conflicts_with_all([
                     "xml_display",
                     "xml_output",
-                    "json_display",
                     "csv_display",
                     "csv_output",
                 ]),
@@ -156,7 +577,6 @@ fn build_command_context() -> Command {
                 .help("Export the results to stdin in XML format")
                 .action(ArgAction::SetTrue)
                 .conflicts_with_all([
-                    "xml_output",
                     "json_display",
                     "json_output",
                     "csv_display",
@@ -171,13 +591,55 @@ fn build_command_context() -> Command {
                 .value_parser(clap::builder::NonEmptyStringValueParser::new())
                 .value_name("path")
                 .conflicts_with_all([
-                    "xml_display",
                     "json_display",
                     "json_output",
                     "csv_display",
                     "csv_output",
                 ]),
         )
+        .arg(
+            Arg::new("json_tree")
+                .long("json-tree")
+                .help("Export the results in JSON as a tree of nested folders, the files referencing their group")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["csv_display", "csv_output", "xml_display", "xml_output", "format_template", "report", "list_deletable", "list_hashes"]),
+        )
+        .arg(
+            Arg::new("combined_hash")
+                .long("combined-hash")
+                .help("Include a single combined checksum of the duplicates in the output")
+                .action(ArgAction::SetTrue)
+                .requires("hash_algorithm"),
+        )
+        .arg(
+            Arg::new("hash_truncate")
+                .long("hash-truncate")
+                .help("Only display the first N characters of each checksum")
+                .value_parser(clap::value_parser!(u16).range(1..))
+                .value_name("n")
+                .requires("hash_algorithm"),
+        )
+        .arg(
+            Arg::new("compress")
+                .long("compress")
+                .help("Compress the output file with zstd")
+                .action(ArgAction::SetTrue)
+                .requires("output_file"),
+        )
+        .arg(
+            Arg::new("temp_dir")
+                .long("temp-dir")
+                .help("Directory of the temporary files written before being moved to the output file")
+                .value_name("path"),
+        )
+        .arg(
+            Arg::new("read_only")
+                .long("read-only")
+                .visible_alias("no-write")
+                .help("Never write on the scanned filesystem, the results are written to stdout or to another volume")
+                .conflicts_with_all(["checkpoint_interval", "emit_script", "on_group"])
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("include_hashes")
                 .short('i')
@@ -186,149 +648,1140 @@ fn build_command_context() -> Command {
                 .action(ArgAction::SetTrue),
         )
         .arg(
-            Arg::new("version")
-                .short('v')
-                .long("version")
+            Arg::new("image_ahash")
+                .long("image-ahash")
+                .help("Find the similar images with their perceptual average hash")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["name", "hash_algorithm"]),
+        )
+        .arg(
+            Arg::new("ahash_distance")
+                .long("ahash-distance")
+                .help("The maximum Hamming distance between the average hashes of two similar images")
+                .value_parser(clap::value_parser!(u32).range(0..=64))
+                .value_name("n")
+                .requires("image_ahash"),
+        )
+        .arg(
+            Arg::new("size_tolerance")
+                .long("size-tolerance")
+                .help("Group the files with the same name and sizes within <percent> of each other (approximate)")
+                .value_parser(parse_percent)
+                .value_name("percent")
+                .conflicts_with_all(["hash_algorithm", "image_ahash", "filter_report", "merge_reports", "list_hashes"]),
+        )
+        .arg(
+            Arg::new("csv_delimiter")
+                .long("csv-delimiter")
+                .help("The delimiter to use in the CSV output")
+                .value_parser(parse_csv_delimiter)
+                .value_name("char"),
+        )
+        .arg(
+            Arg::new("csv_hash_columns")
+                .long("csv-hash-columns")
+                .help("Write each checksum in its own column (MD5, SHA256...) of the CSV output instead of a single cell")
+                .requires("include_hashes")
                 .action(ArgAction::SetTrue),
         )
         .arg(
-            Arg::new("help")
-                .short('h')
-                .long("help")
+            Arg::new("xml_pretty")
+                .long("xml-pretty")
+                .help("Indent the XML output, one element per line")
+                .requires("xml")
                 .action(ArgAction::SetTrue),
         )
-}
-
-/// This function is resonsible for displaying the help menu with all the features of DeepFinder and their options.
-///
-fn display_help() {
-    println!("Usage: deepfinder <path> [options]");
-    println!("Options:");
-    println!("  -n, --name\t\t\t\tFind the duplicates by their name (faster option).\n\t\t\t\t\tSelected by default if both -n and -a arguments are not specified.\n");
-    println!("  -a, --hash-algorithm\t\t\tFind the duplicates based on file content (slower option).\n\t\t\t\t\tIt can be used to compare the content of the files.\n\t\t\t\t\tYou can choose between: md5, sha1, sha224, sha256, sha384, sha512,\n\t\t\t\t\tsha3-224, sha3-256, sha3-384, sha3-512, blake2b-512, blake2s-256 and whirlpool.\n");
-    println!("  -f, --hidden-files\t\t\tEnable search for hidden files.\n");
-    println!("  -c <path>, --csv-display\t\tExport the results to stdin in a CSV format.\n");
-    println!("  -C <path>, --csv-output <path>\tExport the results in a CSV file.\n");
-    println!("  -j <path>, --json-display\t\tExport the results to stdin in a JSON format.\n");
-    println!("  -J <path>, --json-output <path>\tExport the results in a JSON file.\n");
-    println!("  -x <path>, --xml-display\t\tExport the results to stdin in a XML format.\n");
-    println!("  -X <path>, --xml-output <path>\tExport the results in a XML file.\n ");
-    println!("  -i, --include-hashes\t\t\tInclude the checksums of the duplicates in the output.\n\t\t\t\t\tThis argument requires -a to be specified.\n");
-    println!("  -v, --version\t\t\t\tDisplay the version of DeepFinder.\n");
-    println!("  -h, --help\t\t\t\tDisplay this help message.\n\n");
-}
-
-/// This function is responsible for scheduling the execution of the different features of the program according to the user's choices.
-///
-/// # Returns
-///
-/// Ok if the program has been executed, DeepFinderError otherwise.
-///
-pub fn run() -> Result<FindingConfig, DeepFinderError> {
-    let command_context: Command = build_command_context();
-    let matches: ArgMatches = command_context
-        .try_get_matches()
-        .map_err(|_| DeepFinderError::ArgError(ArgError::SyntaxError))?;
-
-    // Call display_help() instead of clap help with the -h or --help arguments (better control of the help message).
-    if matches.get_flag("help") {
-        display_help();
-        exit(0);
-    }
-    // Call println!() instead of clap version with the -v or --version arguments (better control of the version message).
-    if matches.get_flag("version") {
-        println!("DeepFinder v{}", env!("CARGO_PKG_VERSION"));
-        exit(0);
-    }
-
-    parse_user_choices(&matches)
-}
-
-/// This function is responsible for parsing the user's choices and building the FindingConfig struct.
-///
-/// # Arguments
-///
-/// * `matches` - The ArgMatches struct containing the user's choices.
-///
-/// # Returns
-///
-/// Ok(FindingConfig) if the user's choices are valid, DeepFinderError otherwise.
-///
-fn parse_user_choices(matches: &ArgMatches) -> Result<FindingConfig, DeepFinderError> {
-    let search_path: String = matches
-        .get_one::<String>("path")
-        .ok_or(DeepFinderError::ArgError(ArgError::NoPathSpecified))
-        .and_then(|path| system::is_valid_folder_path(path).map_err(DeepFinderError::SystemError))?
-        .trim_end_matches(['/', '\\'])
-        .to_string();
-
-    let hash: Option<Vec<String>> = matches
-        .get_many::<String>("hash_algorithm")
-        .map(|values| values.cloned().collect());
-
-    let output: CliOutput = match (
-        matches.get_flag("csv_display"),
-        matches.get_one::<String>("csv_output"),
-        matches.get_flag("json_display"),
-        matches.get_one::<String>("json_output"),
-        matches.get_flag("xml_display"),
-        matches.get_one::<String>("xml_output"),
-    ) {
-        (true, _, _, _, _, _) => CliOutput::CsvStdin,
-        (_, Some(path), _, _, _, _) => CliOutput::CsvFile(check_output_arg(path)?),
-        (_, _, true, _, _, _) => CliOutput::JsonStdin,
-        (_, _, _, Some(path), _, _) => CliOutput::JsonFile(check_output_arg(path)?),
-        (_, _, _, _, true, _) => CliOutput::XmlStdin,
-        (_, _, _, _, _, Some(path)) => CliOutput::XmlFile(check_output_arg(path)?),
-        _ => CliOutput::Standard,
-    };
-
-    Ok(FindingConfig {
-        search_path,
-        enable_search_by_name: matches.get_flag("name") || !matches.contains_id("hash_algorithm"),
-        include_hidden_files: matches.get_flag("hidden_files"),
-        include_hashes_in_output: matches.contains_id("hash_algorithm") && matches.get_flag("include_hashes"),
-        hash,
-        output,
-    })
-}
-
-/// This function is responsible for checking the path for the 'output' arguments, if it's a valid path on the filesystem.
-///
-/// # Arguments
-///
-/// * `path` - The path to check.
-///
-/// # Returns
-///
-/// Ok(String) if the path is valid, DeepFinderError otherwise.
-///
-fn check_output_arg(path: &str) -> Result<String, DeepFinderError> {
-    match system::is_valid_file_path(path) {
-        Ok(full_path) => Ok(full_path),
-        Err(e) => Err(DeepFinderError::SystemError(e)),
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_parse_user_choices() {
-        let command_context: Command = build_command_context();
-
-        // Set different settings for Unix and Windows.
-        let search_path: String;
-        let matches: ArgMatches;
-        let output: CliOutput = if cfg!(target_family = "unix") {
-            search_path = "/tmp".to_string();
-            matches = command_context.clone().get_matches_from(vec!["deepfinder", "/tmp", "-a", "md5,sha256", "-f", "-n", "-C", "/tmp/output.csv"]);
-            CliOutput::CsvFile("/tmp/output.csv".to_string())
-        } else {
-            search_path = "C:\\Windows".to_string();
-            matches = command_context.clone().get_matches_from(vec!["deepfinder", "C:\\Windows\\", "-a", "md5,sha256", "-f", "-n", "-C", "C:\\output.csv"]);
-            CliOutput::CsvFile("C:\\output.csv".to_string())
+        .arg(
+            Arg::new("crlf")
+                .long("crlf")
+                .help("End the lines of the indented XML output with CRLF")
+                .requires("xml_pretty")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("csv_always_quote")
+                .long("csv-always-quote")
+                .help("Quote every field in the CSV output")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("filter_report")
+                .long("filter-report")
+                .help("Filter an existing JSON report instead of scanning a directory")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .value_name("path"),
+        )
+        .arg(
+            Arg::new("merge_reports")
+                .long("merge-reports")
+                .help("Merge several existing JSON reports instead of scanning a directory")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .value_name("path")
+                .num_args(1..)
+                .conflicts_with("filter_report"),
+        )
+        .arg(
+            Arg::new("compare")
+                .long("compare")
+                .help("Compare two files directly instead of scanning a directory")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .value_names(["fileA", "fileB"])
+                .num_args(2)
+                .conflicts_with_all(["filter_report", "merge_reports", "list_hashes", "image_ahash"]),
+        )
+        .arg(
+            Arg::new("hash_stdin")
+                .long("hash-stdin")
+                .help("Print the checksum of the content piped on stdin instead of scanning a directory")
+                .action(ArgAction::SetTrue)
+                .requires("hash_algorithm")
+                .conflicts_with_all(["filter_report", "merge_reports", "compare", "list_hashes", "verify_sidecars"]),
+        )
+        .arg(
+            Arg::new("verify_sidecars")
+                .long("verify-sidecars")
+                .help("Verify the files against their sidecar checksum files instead of finding the duplicates")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["filter_report", "merge_reports", "compare", "list_hashes", "hash_algorithm", "image_ahash"]),
+        )
+        .arg(
+            Arg::new("record_filelist")
+                .long("record-filelist")
+                .help("Save the list of the files found by the scan, to replay it later")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .value_name("path")
+                .conflicts_with_all(["filter_report", "merge_reports", "compare", "hash_stdin", "verify_sidecars"]),
+        )
+        .arg(
+            Arg::new("replay_filelist")
+                .long("replay-filelist")
+                .help("Search the duplicates in a recorded list of files instead of scanning a directory")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .value_name("path")
+                .conflicts_with_all(["filter_report", "merge_reports", "compare", "hash_stdin", "verify_sidecars"]),
+        )
+        .arg(
+            Arg::new("from_stdin0")
+                .long("from-stdin0")
+                .help("Search the duplicates in a NUL-delimited list of files read on stdin (e.g. find -print0) instead of scanning a directory")
+                .conflicts_with_all(["filter_report", "merge_reports", "compare", "hash_stdin", "verify_sidecars", "replay_filelist"])
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("min_size")
+                .long("min-size")
+                .help("Only report the duplicates whose size is at least the given size")
+                .value_parser(parse_size)
+                .value_name("size"),
+        )
+        .arg(
+            Arg::new("min_dir_size")
+                .long("min-dir-size")
+                .help("Skip the whole subtree of the folders whose total size is below the given size")
+                .value_parser(parse_size)
+                .value_name("size")
+                .conflicts_with_all(["filter_report", "merge_reports"]),
+        )
+        .arg(
+            Arg::new("min_wasted")
+                .long("min-wasted")
+                .help("Only report the duplicates whose reclaimable space is at least the given size")
+                .value_parser(parse_size)
+                .value_name("size"),
+        )
+        .arg(
+            Arg::new("min_occurrences")
+                .long("min-occurrences")
+                .help("Only report the duplicates found at least the given number of times")
+                .value_parser(clap::value_parser!(usize))
+                .value_name("n"),
+        )
+        .arg(
+            Arg::new("only")
+                .long("only")
+                .help("Only report the duplicates whose name matches the glob pattern")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .value_name("pattern")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("scope")
+                .long("scope")
+                .help("Only report the duplicates spanning at least two folders with 'cross-dir'")
+                .value_parser(["global", "cross-dir"])
+                .value_name("scope"),
+        )
+        .arg(
+            Arg::new("list_deletable")
+                .long("list-deletable")
+                .help("Export only the paths that can be deleted, all but the kept one of each group")
+                .action(ArgAction::SetTrue)
+                // Only the groups of identical contents can be deleted, never the files sharing a name or a similar content.
+                .requires("hash_algorithm")
+                .conflicts_with_all([
+                    "list_hashes",
+                    "name",
+                    "size_tolerance",
+                    "ignore_metadata",
+                    "content_case_insensitive",
+                    "transform",
+                ]),
+        )
+        .arg(
+            Arg::new("emit_script")
+                .long("emit-script")
+                .help("Write a script deleting all but the kept file of each group, to review it before running it")
+                .value_name("path")
+                // Only the groups of identical contents can be deleted, never the files sharing a name or a similar content.
+                .requires("hash_algorithm")
+                .conflicts_with_all([
+                    "list_deletable",
+                    "list_hashes",
+                    "json_tree",
+                    "report",
+                    "name",
+                    "size_tolerance",
+                    "ignore_metadata",
+                    "content_case_insensitive",
+                    "transform",
+                ]),
+        )
+        .arg(
+            Arg::new("on_group")
+                .long("on-group")
+                .help("Run a command once per group, with the group details in DF_GROUP_NAME, DF_GROUP_SIZE, DF_GROUP_PATHS and its JSON on stdin")
+                .value_name("command")
+                .conflicts_with("list_hashes"),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .help("Abort the search if the command of --on-group fails")
+                .requires("on_group")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("keep")
+                .long("keep")
+                .help("The policy used to choose the file to keep in each group")
+                .value_parser(["first", "last", "shortest", "longest", "oldest", "newest"])
+                .value_name("policy"),
+        )
+        .arg(
+            Arg::new("prefer_dir")
+                .long("prefer-dir")
+                .help("Keep the file of the given directory in each group, before applying the keep policy")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .value_name("path")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("max_paths_per_group")
+                .long("max-paths-per-group")
+                .help("Only display the first N paths (sorted) of each group")
+                .value_parser(clap::value_parser!(usize))
+                .value_name("n"),
+        )
+        .arg(
+            Arg::new("format_template")
+                .long("format-template")
+                .help("Display the results with a custom template, e.g. '{count} {name}'")
+                .value_parser(parse_format_template)
+                .value_name("template")
+                .conflicts_with_all(["csv_display", "csv_output", "json_display", "json_output", "xml_display", "xml_output", "list_deletable"]),
+        )
+        .arg(
+            Arg::new("all_files")
+                .long("all-files")
+                .help("Report every file, the unique ones being groups with a single occurrence")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["filter_report", "merge_reports", "image_ahash", "prefilter", "list_hashes"]),
+        )
+        .arg(
+            Arg::new("report")
+                .long("report")
+                .help("Display an analytical report instead of the duplicates ('summary')")
+                .value_parser(["summary"])
+                .value_name("report")
+                .conflicts_with_all(["csv_display", "csv_output", "json_display", "json_output", "xml_display", "xml_output", "list_deletable", "list_hashes", "format_template"]),
+        )
+        .arg(
+            Arg::new("by_dir_pairs")
+                .long("by-dir-pairs")
+                .help("Display the pairs of directories sharing duplicates instead of the duplicates")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "csv_display",
+                    "csv_output",
+                    "json_display",
+                    "json_output",
+                    "xml_display",
+                    "xml_output",
+                    "list_deletable",
+                    "list_hashes",
+                    "format_template",
+                    "report",
+                    "json_tree",
+                ]),
+        )
+        .arg(
+            Arg::new("report_empty_dirs")
+                .long("report-empty-dirs")
+                .help("Also list the empty directories found by the traversal, immediately or recursively empty")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "csv_display",
+                    "csv_output",
+                    "json_display",
+                    "json_output",
+                    "xml_display",
+                    "xml_output",
+                    "list_deletable",
+                    "list_hashes",
+                    "emit_script",
+                    "json_tree",
+                    "filter_report",
+                    "merge_reports",
+                    "replay_filelist",
+                    "from_stdin0",
+                ]),
+        )
+        .arg(
+            Arg::new("quiet_if_empty")
+                .long("quiet-if-empty")
+                .help("Suppress all output when no duplicate is found")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("fail_on_duplicates")
+                .long("fail-on-duplicates")
+                .help("Exit with the code 2 if any duplicate is found, e.g. to fail a CI check")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["list_hashes", "compare", "hash_stdin", "verify_sidecars"]),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help("Don't display the informational messages on stderr")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("progress_json")
+                .long("progress-json")
+                .help("Report the progress on stderr as newline-delimited JSON events")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("benchmark")
+                .long("benchmark")
+                .help("Display the duration and throughput of each phase of the search on stderr")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no_color")
+                .long("no-color")
+                .help("Disable the colors of the standard output")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("relative_times")
+                .long("relative-times")
+                .help("Display the modification time of each path relative to now (e.g. '3 hours ago')")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("format_template"),
+        )
+        .arg(
+            Arg::new("version")
+                .short('v')
+                .long("version")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("version_json")
+                .long("version-json")
+                .help("Display the version and the features of DeepFinder in JSON")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("doctor")
+                .long("doctor")
+                .help("Display a diagnostic report of the environment and check the hash algorithms")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("export_schema")
+                .long("export-schema")
+                .help("Print the JSON Schema or the XSD of the JSON or XML output")
+                .value_parser(["json", "xml"])
+                .value_name("format"),
+        )
+        .arg(
+            Arg::new("list_algorithms")
+                .long("list-algorithms")
+                .help("List the supported hash algorithms, in text or JSON (e.g. --list-algorithms=json)")
+                .value_parser(["text", "json"])
+                .value_name("format")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("text"),
+        )
+        .arg(
+            Arg::new("help")
+                .short('h')
+                .long("help")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+/// This function is resonsible for displaying the help menu with all the features of DeepFinder and their options.
+///
+fn display_help() {
+    println!("Usage: deepfinder <path> [options]");
+    println!("Options:");
+    println!("  -n, --name\t\t\t\tFind the duplicates by their name (faster option).\n\t\t\t\t\tSelected by default if both -n and -a arguments are not specified.\n");
+    println!("  -a, --hash-algorithm\t\t\tFind the duplicates based on file content (slower option).\n\t\t\t\t\tIt can be used to compare the content of the files.\n\t\t\t\t\tYou can choose between: md5, sha1, sha224, sha256, sha384, sha512,\n\t\t\t\t\tsha3-224, sha3-256, sha3-384, sha3-512, blake2b-512, blake2s-256, whirlpool, blake3 and xxh3.\n\t\t\t\t\tThe xxh3 algorithm is very fast but isn't cryptographic.\n");
+    println!("  --prefilter <hash> --confirm <hash>\tHash every file with a fast algorithm (e.g. xxh3), then confirm the candidate duplicates\n\t\t\t\t\twith a strong algorithm (e.g. sha256), never computed on the unique files.\n\t\t\t\t\tOnly the strong checksums are reported. --confirm is an alias of -a.\n");
+    println!("  --ignore-extension\t\t\tIgnore the extension of the files when finding the duplicates by their name\n\t\t\t\t\t(e.g. 'song.mp3' and 'song.flac' are duplicates).\n");
+    println!("  --normalize-unicode\t\t\tNormalize the names (NFC) when finding the duplicates by their name, so a name written\n\t\t\t\t\tin the NFD form (e.g. by macOS) matches the same name in the NFC form.\n");
+    println!("  --detect-confusables\t\t\tGroup the names that look identical but differ, e.g. with a zero-width character or a Cyrillic 'а'\n\t\t\t\t\tinstead of a Latin 'a' (spoofing). Such a group is flagged with a warning on stderr, listing its names\n\t\t\t\t\twith their non-ASCII characters escaped. This argument can't be used with -a.\n");
+    println!("  --name-then-hash\t\t\tOnly hash the files whose names collide, each group holds same-named files with the same content.\n\t\t\t\t\tThe same-named files whose contents differ are listed on stderr (requires -a).\n");
+    println!("  --algo-for-large <hash>\t\tHash the files of at least --large-threshold bytes with <hash> only (e.g. a faster blake3),\n\t\t\t\t\tthe smaller files being hashed with the algorithms of -a. The files hashed with different algorithms\n\t\t\t\t\tare never compared, since they have different sizes.\n");
+    println!("  --large-threshold <size>\t\tThe size from which the files are hashed with --algo-for-large (e.g. 100M).\n");
+    println!("  --unique-content\t\t\tCount each physical file once: the paths leading to a file already scanned (hard links,\n\t\t\t\t\tbind mounts) are ignored, so the results reflect the physical storage. Unix only.\n");
+    println!("  --distinct-empty\t\t\tNever group the empty files together, each of them is considered unique (requires -a).\n\t\t\t\t\tBy default, the empty files share the same content and are grouped together.\n");
+    println!("  --largest-first\t\t\tHash the largest files first, and report the groups from the largest to the smallest (requires -a).\n\t\t\t\t\tWith --checkpoint-interval, the partial results hold the largest duplicates first.\n");
+    println!("  --list-hashes\t\t\t\tList the checksums of every file (manifest) instead of finding the duplicates.\n\t\t\t\t\tThis argument requires -a to be specified.\n");
+    println!("  --parallel-hash-within-file\t\tHash each large file (64 MiB or more) with all the cores, useful for a few huge files.\n\t\t\t\t\tOnly blake3 can be computed in parallel, the other algorithms are computed sequentially.\n");
+    println!("  --transform <transform>\t\tMatch a file with its transformed copy, e.g. its byte-swapped copy for raw data.\n\t\t\t\t\tYou can choose between: none (default), byteswap16 and byteswap32.\n\t\t\t\t\tThe files are read twice. This argument requires -a to be specified.\n");
+    println!("  --content-case-insensitive\t\tHash the text files (UTF-8 without NUL bytes) lowercased, so the texts differing only by their case\n\t\t\t\t\t(and having the same size) match. The binary files and the files over 64 MiB are hashed as is.\n\t\t\t\t\tThis argument requires -a to be specified.\n");
+    println!("  --ignore-metadata\t\t\tHash the decoded pixels of the images instead of their bytes, so the images with the same pixels\n\t\t\t\t\tbut different metadata (EXIF, comments...) are duplicates, whatever their size. It's an exact equality,\n\t\t\t\t\tunlike --image-ahash. The other files are hashed as usual. This argument requires -a to be specified.\n");
+    println!("  --byte-budget <size>\t\t\tStop hashing new files once <size> bytes have been hashed (e.g. 50G).\n\t\t\t\t\tThe results are partial if the budget is exceeded.\n");
+    println!("  --checkpoint-interval <secs>\t\tWrite the duplicates confirmed so far to the output file every <secs>\n\t\t\t\t\tseconds while hashing (requires -a and -C, -J or -X).\n");
+    println!("  --io-throttle <MB/s>\t\t\tLimit the read bandwidth while hashing (all threads together), e.g. to run in the background.\n");
+    println!("  --slow-file-threshold <ms>\t\tWarn (on stderr) about each file whose hashing takes longer than <ms> milliseconds,\n\t\t\t\t\twith its throughput, to spot a failing disk or a stalled network share.\n");
+    println!("  -f, --hidden-files\t\t\tEnable search for hidden files.\n");
+    println!("  --exclude <pattern>\t\t\tExclude the files and folders whose name matches <pattern> ('*' and '?' wildcards).\n\t\t\t\t\tAn excluded folder is never descended. This argument can be repeated (e.g. --exclude node_modules --exclude '*.tmp').\n");
+    println!("  --ignore-file <path>\t\t\tRead the exclude patterns from <path> instead of the .deepfinderignore file of the scan root.\n\t\t\t\t\tThe patterns are separated by commas or new lines and can hold spaces, the lines starting with '#' are comments.\n\t\t\t\t\tThey are merged with the --exclude patterns.\n");
+    println!("  --exclude-regex <pattern>\t\tExclude the files and folders whose full path matches the regular expression <pattern>\n\t\t\t\t\t(e.g. --exclude-regex '.*/\\.git/.*'). A folder is only pruned if its own path matches,\n\t\t\t\t\tso '.*/\\.git/.*' excludes the files of the .git folders, but still descends them. This argument can be repeated.\n");
+    println!("  --skip-dir <path>\t\t\tSkip the directory <path> and its whole subtree (e.g. --skip-dir /data/backups).\n\t\t\t\t\tThe paths are resolved (symbolic links included) before being compared. This argument can be repeated.\n");
+    println!("  --skip-top <name>\t\t\tSkip the folders named <name> only at the top level of the scanned folder,\n\t\t\t\t\tthe deeper folders with the same name are still scanned. This argument can be repeated\n\t\t\t\t\t(e.g. --skip-top archive --skip-top vendor).\n");
+    println!("  --exclude-stubs\t\t\tSkip the cloud placeholders (OneDrive, Dropbox...), reading them would download their content.\n\t\t\t\t\tOn Windows, the files with the offline or recall attributes are skipped.\n\t\t\t\t\tOn Unix, the non-empty files without any block on the disk are skipped.\n");
+    println!("  --follow-reparse-points\t\tFollow the folders that are junctions or other reparse points (Windows only).\n\t\t\t\t\tThey are skipped by default. When followed, a folder reached twice is traversed once (no loop).\n\t\t\t\t\tOn the other platforms, it is ignored with a warning.\n");
+    println!("  --include-symlinks-as-entries\t\tList the symbolic links in the manifest as entries of kind \"symlink\", with their target,\n\t\t\t\t\tinstead of following them. The links are never hashed. This argument requires --list-hashes.\n");
+    println!("  --content-type <type>\t\t\tOnly search the files whose content has the given type, whatever their extension.\n\t\t\t\t\tThe type is detected from the magic bytes of the files, the files of unknown type are excluded.\n\t\t\t\t\tThe \"text\" type holds the files starting with UTF-8 text. It can't be used on an archive.\n\t\t\t\t\tYou can choose between: app, archive, audio, book, doc, font, image, text and video.\n");
+    println!("  -c <path>, --csv-display\t\tExport the results to stdin in a CSV format.\n");
+    println!("  -C <path>, --csv-output <path>\tExport the results in a CSV file.\n");
+    println!("  -j <path>, --json-display\t\tExport the results to stdin in a JSON format.\n");
+    println!("  -J <path>, --json-output <path>\tExport the results in a JSON file.\n\t\t\t\t\tThe JSON object holds the \"duplicates\" groups and the \"warnings\" raised by the search (skipped files...).\n");
+    println!("  --json-tree\t\t\t\tExport the results in JSON as nested folders, each file referencing the id of its group, with the warnings\n\t\t\t\t\t(e.g. {{\"tree\": {{\"home\": {{\"a.txt\": \"<group id>\"}}}}, \"warnings\": []}}). Written to stdin, or to the -J file.\n\t\t\t\t\tA duplicate archive holding duplicate files is a folder whose \"\" key references its group.\n");
+    println!("  -x <path>, --xml-display\t\tExport the results to stdin in a XML format.\n");
+    println!("  -X <path>, --xml-output <path>\tExport the results in a XML file.\n\t\t\t\t\tA display argument can be combined with the output argument of the same format\n\t\t\t\t\tto write the results in both stdin and a file (e.g. -j -J <path>).\n ");
+    println!("  --compress\t\t\t\tCompress the output file with zstd, the '.zst' extension is appended to its path.\n\t\t\t\t\tThe results displayed in stdin are never compressed. This argument requires -C, -J or -X.\n");
+    println!("  --temp-dir <path>\t\t\tWrite the temporary files in this directory instead of next to the output file,\n\t\t\t\t\tthe output file is then replaced at once. Its permissions are kept, and a symbolic link is written through.\n");
+    println!("  --read-only, --no-write\t\tNever write on the scanned filesystem (e.g. to preserve the evidence of a forensic analysis).\n\t\t\t\t\tThe results are displayed or written to another volume: a path written on the scanned filesystem\n\t\t\t\t\t(output file, --record-filelist, --temp-dir) is an error.\n\t\t\t\t\tThe options writing files or running commands (--checkpoint-interval, --emit-script, --on-group)\n\t\t\t\t\tcan't be used.\n");
+    println!("  -i, --include-hashes\t\t\tInclude the checksums of the duplicates in the output.\n\t\t\t\t\tThis argument requires -a to be specified (or a report, whose checksums are emitted again).\n");
+    println!("  --combined-hash\t\t\tInclude a single combined checksum of the duplicates in the output (e.g. md5+sha256),\n\t\t\t\t\tthe checksums are concatenated in the alphabetical order of the algorithms.\n\t\t\t\t\tThis argument requires -a to be specified.\n");
+    println!("  --hash-truncate <n>\t\t\tOnly display the first <n> characters of each checksum, in every output (e.g. --hash-truncate 12).\n\t\t\t\t\tThe files are still grouped with the full checksums, but the truncated ones can't verify the files.\n\t\t\t\t\tThis argument requires -a to be specified.\n");
+    println!("  --image-ahash\t\t\t\tFind the similar images (resized, re-encoded...) with their perceptual average hash.\n\t\t\t\t\tOnly the png, jpg, jpeg, gif, bmp and webp files are compared, the others are ignored. It can't be used on an archive.\n");
+    println!("  --ahash-distance <n>\t\t\tThe maximum number of different bits (0 to 64) between two similar images.\n\t\t\t\t\tThe default value is 5. This argument requires --image-ahash to be specified.\n");
+    println!("  --size-tolerance <percent>\t\tFind the approximate duplicates (e.g. re-encoded videos): the files with the same name whose sizes\n\t\t\t\t\tare within <percent> (e.g. 1 or 0.5) of the smallest file of their group. It's a heuristic to triage,\n\t\t\t\t\tthe contents aren't compared. This argument can't be used with -a.\n");
+    println!("  --csv-delimiter <char>\t\tUse <char> as delimiter in the CSV output (';' by default).\n\t\t\t\t\tIt must be a single ASCII character other than a quote or a line break, use '\\t' for a tab.\n");
+    println!("  --csv-always-quote\t\t\tQuote every field in the CSV output, not only the ones that need it.\n");
+    println!("  --csv-hash-columns\t\t\tWrite each checksum in its own column of the CSV output, named after its algorithm (e.g. MD5, SHA256),\n\t\t\t\t\tinstead of a single multiline cell. This argument requires -i to be specified.\n");
+    println!("  --xml-pretty\t\t\t\tIndent the XML output (-x or -X), one element per line.\n");
+    println!("  --crlf\t\t\t\tEnd the lines of the indented XML output with CRLF instead of LF, for the Windows tools.\n\t\t\t\t\tThis argument requires --xml-pretty to be specified.\n");
+    println!("  --filter-report <path>\t\tFilter an existing JSON report instead of scanning a directory.\n\t\t\t\t\tThe filters are applied in memory and the report is exported again in the chosen format.\n");
+    println!("  --merge-reports <path>...\t\tMerge several existing JSON reports (e.g. generated on different machines).\n\t\t\t\t\tThe groups with the same checksums are merged and their paths are unioned.\n\t\t\t\t\tThe groups of a report written without -i have no checksums, they are kept without being merged.\n");
+    println!("  --compare <fileA> <fileB>\t\tCheck if two files are identical, without scanning a directory.\n\t\t\t\t\tThe files are compared byte by byte, or with their checksums if -a is specified.\n\t\t\t\t\tThe exit status is 0 if the files are identical, 2 if they differ, 1 if a file can't be read.\n");
+    println!("  --hash-stdin\t\t\t\tPrint the checksum of the content piped on stdin (e.g. cat file | deepfinder --hash-stdin -a sha256).\n\t\t\t\t\tThis argument requires -a to be specified.\n");
+    println!("  --verify-sidecars\t\t\tVerify each file against its sidecar checksum files (e.g. 'file.iso.sha256'),\n\t\t\t\t\tthe supported sidecars are .md5, .sha1, .sha256 and .sha512. The verified, failed, missing\n\t\t\t\t\tand unreadable counts are reported. The exit status is 2 if a file is corrupted or unreadable, 0 otherwise.\n\t\t\t\t\tIt can't be used on an archive.\n");
+    println!("  --record-filelist <path>\t\tSave the list of the files found by the scan in a JSON file, to replay the same scan later.\n");
+    println!("  --replay-filelist <path>\t\tSearch the duplicates in a list recorded with --record-filelist instead of scanning a directory.\n\t\t\t\t\tThe files are still read to be hashed, only the traversal is skipped (e.g. to benchmark the hashing).\n");
+    println!("  --from-stdin0\t\t\t\tSearch the duplicates in a list of files read on stdin instead of scanning a directory.\n\t\t\t\t\tThe paths are separated by NUL bytes, so they can contain newlines (e.g. find . -type f -print0 | deepfinder --from-stdin0).\n\t\t\t\t\tThe paths that aren't regular files are skipped with a warning.\n");
+    println!("  --min-size <size>\t\t\tOnly report the duplicates whose size is at least <size>.\n\t\t\t\t\tThe size can use a unit suffix: K, M, G or T (e.g. 100M).\n");
+    println!("  --min-dir-size <size>\t\t\tSkip the folders whose total size (sub-folders included) is below <size>, with their whole subtree:\n\t\t\t\t\tnone of their files is hashed nor reported. The search path itself is never skipped (e.g. 10M).\n");
+    println!("  --min-wasted <size>\t\t\tOnly report the duplicates wasting at least <size>, i.e. size * (occurrences - 1).\n\t\t\t\t\tThe size can use a unit suffix: K, M, G or T (e.g. 50M).\n");
+    println!("  --min-occurrences <n>\t\t\tOnly report the duplicates found at least <n> times.\n");
+    println!("  --only <pattern>\t\t\tOnly report the duplicates whose name matches the glob pattern (e.g. '*.iso').\n\t\t\t\t\tThis argument can be repeated, a group matching any pattern is reported.\n");
+    println!("  --scope <scope>\t\t\tChoose the duplicates to report: 'global' (default) or 'cross-dir'.\n\t\t\t\t\tWith 'cross-dir', only the duplicates scattered in at least two folders are reported\n\t\t\t\t\t(e.g. 'song.mp3' and 'song.flac' in the same folder are ignored with --ignore-extension).\n");
+    println!("  --list-deletable\t\t\tExport only the paths that can be deleted (all but the kept one of each group),\n\t\t\t\t\tone entry each with the kept path of its group.\n\t\t\t\t\tThis argument requires -a to be specified, the groups of files sharing a name or a similar content\n\t\t\t\t\t(-n, --size-tolerance, --ignore-metadata, --content-case-insensitive, --transform) are refused.\n");
+    println!("  --emit-script <path>\t\t\tWrite a script deleting all but the kept file of each group (see --keep), instead of the results.\n\t\t\t\t\tIt's a shell script (PowerShell on Windows) to review before running it, nothing is deleted by DeepFinder.\n\t\t\t\t\tThis argument requires -a to be specified, the groups of files sharing a name or a similar content\n\t\t\t\t\t(-n, --size-tolerance, --ignore-metadata, --content-case-insensitive, --transform) are refused.\n");
+    println!("  --on-group <command>\t\t\tRun <command> with the shell once per group, before the results are displayed or written.\n\t\t\t\t\tThe group is described by DF_GROUP_NAME, DF_GROUP_SIZE and DF_GROUP_PATHS (one path per line)\n\t\t\t\t\tand its JSON is written on the standard input. A failing command is reported as a warning.\n\t\t\t\t\tThe output of the command is written on stderr, so the results on stdout stay intact.\n");
+    println!("  --strict\t\t\t\tAbort the search if the command of --on-group fails.\n");
+    println!("  --keep <policy>\t\t\tThe policy used to choose the file to keep in each group.\n\t\t\t\t\tYou can choose between: first (default) and last in the alphabetical order, shortest and longest path,\n\t\t\t\t\toldest and newest modification time.\n");
+    println!("  --prefer-dir <path>\t\t\tKeep the file under <path> in each group, the keep policy only breaks the ties.\n\t\t\t\t\tThis argument can be repeated, the first directories have the highest priority\n\t\t\t\t\t(e.g. --prefer-dir ~/Archive --prefer-dir ~/Documents).\n");
+    println!("  --max-paths-per-group <n>\t\tOnly display the first <n> paths (sorted) of each group, followed by '... and M more'.\n\t\t\t\t\tThe occurrences still count every path of the group.\n");
+    println!("  --format-template <template>\t\tDisplay a line per group rendered from <template> instead of the default format.\n\t\t\t\t\tThe placeholders are {{name}}, {{count}}, {{size}} and {{wasted}} (in bytes), and {{path}}\n\t\t\t\t\tto render a line per path (e.g. --format-template '{{wasted}} {{count}} {{path}}'). A literal brace\n\t\t\t\t\tis written '{{{{' or '}}}}'. The paths hidden by --max-paths-per-group are counted on a last line.\n");
+    println!("  --all-files\t\t\t\tReport every file (inventory), the unique files being groups with a single occurrence.\n");
+    println!("  --report summary\t\t\tDisplay a summary instead of the duplicates: the numbers of groups and files,\n\t\t\t\t\tthe duplicated and reclaimable sizes, the dedupe ratio and the top 10 groups by reclaimable space.\n");
+    println!("  --by-dir-pairs\t\t\tDisplay the pairs of directories sharing duplicates instead of the duplicates,\n\t\t\t\t\twith their numbers of shared files and sizes, sorted by shared size.\n");
+    println!("  --report-empty-dirs\t\t\tAlso list the directories without any file after the results, each one being immediately empty\n\t\t\t\t\t(no entry at all) or recursively empty (only empty directories). A folder holding a hidden\n\t\t\t\t\tor excluded entry isn't empty.\n");
+    println!("  --quiet-if-empty\t\t\tSuppress all output (stdin and files) when no duplicate is found, in every format.\n");
+    println!("  --fail-on-duplicates\t\t\tExit with the code 2 if any duplicate group is found (after the filters, e.g. --min-size),\n\t\t\t\t\tthe results being still displayed or written. Useful to fail a CI or pre-commit check.\n");
+    println!("  -q, --quiet\t\t\t\tDon't display the informational messages on stderr (e.g. the summary of a file output).\n");
+    println!("  --progress-json\t\t\tReport the progress of the traversal and the hashing on stderr, as newline-delimited JSON events\n\t\t\t\t\t(e.g. {{\"phase\":\"hashing\",\"done\":123,\"total\":456,\"bytes\":7890}}), for the programs wrapping DeepFinder.\n");
+    println!("  --benchmark\t\t\t\tDisplay the duration and throughput (files/s, MB/s hashed) of each phase of the search on stderr:\n\t\t\t\t\ttraversal, hashing and grouping, followed by the number of cores used.\n");
+    println!("  --no-color\t\t\t\tDisable the colors of the standard output.\n\t\t\t\t\tThe colors are also disabled if stdout isn't a terminal or if NO_COLOR is set.\n");
+    println!("  --relative-times\t\t\tDisplay the modification time of each path relative to now (e.g. 'modified 3 hours ago')\n\t\t\t\t\tin the standard output. The other formats are unchanged.\n");
+    println!("  -v, --version\t\t\t\tDisplay the version of DeepFinder.\n");
+    println!("  --version-json\t\t\tDisplay the version of DeepFinder and its features in JSON, for the scripts.\n");
+    println!("  --doctor\t\t\t\tDisplay a diagnostic report to troubleshoot the environment (platform, number of cores,\n\t\t\t\t\twritable current and temporary directories, long paths) and check each hash algorithm\n\t\t\t\t\twith a known test vector. The exit code is 1 if a check fails.\n");
+    println!("  --export-schema <format>\t\tPrint the schema of the output in the given format, to validate the reports or generate typed clients:\n\t\t\t\t\tthe JSON Schema of the JSON output ('json') or the XSD of the XML output ('xml').\n");
+    println!("  --list-algorithms[=format]\t\tList the supported hash algorithms, one per line ('text', default) or as a JSON array\n\t\t\t\t\t('--list-algorithms=json').\n");
+    println!("  -h, --help\t\t\t\tDisplay this help message.\n\n");
+}
+
+/// This function is responsible for scheduling the execution of the different features of the program according to the user's choices.
+///
+/// # Returns
+///
+/// Ok if the program has been executed, DeepFinderError otherwise.
+///
+pub fn run() -> Result<FindingConfig, DeepFinderError> {
+    let command_context: Command = build_command_context();
+    let matches: ArgMatches = command_context
+        .try_get_matches()
+        .map_err(|_| DeepFinderError::ArgError(ArgError::SyntaxError))?;
+
+    // Call display_help() instead of clap help with the -h or --help arguments (better control of the help message).
+    if matches.get_flag("help") {
+        display_help();
+        exit(0);
+    }
+    // Call println!() instead of clap version with the -v or --version arguments (better control of the version message).
+    if matches.get_flag("version") {
+        println!("DeepFinder v{}", env!("CARGO_PKG_VERSION"));
+        exit(0);
+    }
+    if matches.get_flag("version_json") {
+        println!("{}", format_version_json());
+        exit(0);
+    }
+    if let Some(format) = matches.get_one::<String>("list_algorithms") {
+        println!("{}", format_algorithms(format == "json"));
+        exit(0);
+    }
+    if let Some(format) = matches.get_one::<String>("export_schema") {
+        println!("{}", export::output_schema(format));
+        exit(0);
+    }
+    if matches.get_flag("doctor") {
+        let (report, passed): (String, bool) = format_doctor_report(&hash_self_check());
+        print!("{report}");
+        exit(if passed { 0 } else { 1 });
+    }
+
+    parse_user_choices(&matches)
+}
+
+/// This function is responsible for formatting the version of DeepFinder in JSON, with its name and its features.
+///
+/// # Returns
+///
+/// The version as a single JSON object, e.g. {"name":"deepfinder","version":"X.Y.Z","features":[...]}.
+///
+fn format_version_json() -> String {
+    let version: VersionInfo = VersionInfo { name: "deepfinder", version: env!("CARGO_PKG_VERSION"), features: features() };
+    serde_json::to_string(&version).unwrap_or_default()
+}
+
+/// This function is responsible for listing the optional capabilities of DeepFinder, listed in the machine-readable version so the tools can check them.
+/// They are derived from the supported archive formats and from the arguments of the command, so they can't drift from them.
+///
+/// # Returns
+///
+/// The features, e.g. "archive-zip" or "zstd-output".
+///
+fn features() -> Vec<String> {
+    let command_context: Command = build_command_context();
+    let archive_features = archive::ARCHIVE_FORMATS.iter().map(|(format, _)| format!("archive-{format}"));
+    let arg_features = FEATURE_ARGS
+        .iter()
+        .filter(|(_, id)| command_context.get_arguments().any(|arg| arg.get_id() == id))
+        .map(|(feature, _)| feature.to_string());
+    archive_features.chain(arg_features).collect()
+}
+
+/// This function is responsible for hashing "abc" with each hash algorithm and comparing the checksums with the test vectors.
+///
+/// # Returns
+///
+/// The hash algorithms (in the order of HASH_TEST_VECTORS), each one with true if its checksum is the expected one.
+///
+fn hash_self_check() -> Vec<(&'static str, bool)> {
+    HASH_TEST_VECTORS
+        .iter()
+        .map(|(algorithm, expected)| {
+            let checksum: Option<String> = system::manage_hash_reader(b"abc".as_slice(), algorithm).ok().flatten();
+            (*algorithm, checksum.as_deref() == Some(*expected))
+        })
+        .collect()
+}
+
+/// This function is responsible for building the diagnostic report of the environment ('--doctor').
+///
+/// # Arguments
+///
+/// * `self_check` - The result of the hash self-check, for each hash algorithm.
+///
+/// # Returns
+///
+/// The report, and true if every check has passed.
+///
+fn format_doctor_report(self_check: &[(&str, bool)]) -> (String, bool) {
+    let writable = |path: &Path| {
+        let path: String = path.to_string_lossy().to_string();
+        let status: &str = if system::check_writable_folder(&path).is_ok() { "writable" } else { "NOT writable" };
+        format!("{path} ({status})")
+    };
+
+    let mut report: String = format!("DeepFinder v{}\n", env!("CARGO_PKG_VERSION"));
+    report += &format!("Platform: {} ({})\n", env::consts::OS, env::consts::ARCH);
+    report += &format!("Logical cores: {}\n", num_cpus::get());
+    report += &format!("Algorithms: {}\n", HASH_ALGORITHMS.join(", "));
+    report += &format!("Features: {}\n", features().join(", "));
+    report += &format!("Current directory: {}\n", env::current_dir().map_or_else(|e| format!("unknown ({e})"), |dir| writable(&dir)));
+    report += &format!("Temporary directory: {}\n", writable(&env::temp_dir()));
+    let long_paths: &str = if cfg!(target_family = "windows") { "the output paths are limited to 260 characters" } else { "supported" };
+    report += &format!("Long paths: {long_paths}\n");
+
+    report += "Hash self-check:\n";
+    for (algorithm, passed) in self_check {
+        report += &format!("  {algorithm:<12} {}\n", if *passed { "ok" } else { "FAILED" });
+    }
+
+    let passed: bool = self_check.iter().all(|(_, passed)| *passed);
+    report += if passed { "Every check has passed.\n" } else { "Some checks have failed, please report this issue with this output.\n" };
+    (report, passed)
+}
+
+/// This function is responsible for formatting the supported hash algorithms, the same ones accepted by the '-a' argument.
+///
+/// # Arguments
+///
+/// * `json` - A boolean that indicates if the algorithms are formatted as a JSON array or one per line.
+///
+/// # Returns
+///
+/// The supported hash algorithms, in the order of HASH_ALGORITHMS.
+///
+fn format_algorithms(json: bool) -> String {
+    if json {
+        serde_json::to_string(&HASH_ALGORITHMS).unwrap_or_default()
+    } else {
+        HASH_ALGORITHMS.join("\n")
+    }
+}
+
+/// This function is responsible for parsing the user's choices and building the FindingConfig struct.
+///
+/// # Arguments
+///
+/// * `matches` - The ArgMatches struct containing the user's choices.
+///
+/// # Returns
+///
+/// Ok(FindingConfig) if the user's choices are valid, DeepFinderError otherwise.
+///
+fn parse_user_choices(matches: &ArgMatches) -> Result<FindingConfig, DeepFinderError> {
+    let filter_report: Option<String> = matches.get_one::<String>("filter_report").cloned();
+    let merge_reports: Option<Vec<String>> = matches.get_many::<String>("merge_reports").map(|values| values.cloned().collect());
+    let compare: Option<(String, String)> = match matches.get_many::<String>("compare").map(|values| values.collect::<Vec<_>>()) {
+        Some(files) => Some((check_compare_arg(files[0])?, check_compare_arg(files[1])?)),
+        None => None,
+    };
+
+    let exclude_regex: Vec<PathRegex> = match matches.get_many::<String>("exclude_regex") {
+        Some(patterns) => patterns.map(|pattern| check_regex_arg(pattern)).collect::<Result<_, _>>()?,
+        None => Vec::new(),
+    };
+
+    let prefer_dirs: Vec<String> = match matches.get_many::<String>("prefer_dir") {
+        Some(paths) => paths.map(|path| system::is_valid_folder_path(path)).collect::<Result<_, _>>().map_err(DeepFinderError::SystemError)?,
+        None => Vec::new(),
+    };
+
+    let skip_dirs: Vec<String> = match matches.get_many::<String>("skip_dir") {
+        Some(paths) => paths.map(|path| check_skip_dir_arg(path)).collect::<Result<_, _>>()?,
+        None => Vec::new(),
+    };
+
+    // No directory is scanned when existing reports are filtered or merged, when a file list is replayed or read on stdin,
+    // nor when two files are compared or stdin is hashed.
+    let replay_filelist: Option<String> = matches.get_one::<String>("replay_filelist").cloned();
+    let search_path: String = if filter_report.is_some()
+        || merge_reports.is_some()
+        || replay_filelist.is_some()
+        || matches.get_flag("from_stdin0")
+        || compare.is_some()
+        || matches.get_flag("hash_stdin")
+    {
+        String::new()
+    } else {
+        matches
+            .get_one::<String>("path")
+            .ok_or(DeepFinderError::ArgError(ArgError::NoPathSpecified))
+            .and_then(|path| check_search_path_arg(path))
+            .map(|path| trim_trailing_separators(&path).to_string())?
+    };
+    if archive::is_supported_archive(&search_path)
+        && let Some((_, option)) = ARCHIVE_UNSUPPORTED_ARGS.iter().find(|(id, _)| matches.value_source(id) == Some(ValueSource::CommandLine))
+    {
+        return Err(DeepFinderError::ArgError(ArgError::UnsupportedWithArchive(option.to_string())));
+    }
+
+    // The patterns of the ignore file are merged with the '--exclude' ones.
+    let mut exclude: Vec<String> = matches.get_many::<String>("exclude").map(|values| values.cloned().collect()).unwrap_or_default();
+    let ignore_file: Option<String> = matches.get_one::<String>("ignore_file").cloned().or_else(|| {
+        let default_path: std::path::PathBuf = Path::new(&search_path).join(IGNORE_FILE_NAME);
+        (!search_path.is_empty() && default_path.is_file()).then(|| default_path.to_string_lossy().to_string())
+    });
+    if let Some(path) = ignore_file {
+        exclude.extend(system::read_ignore_file(&path).map_err(DeepFinderError::SystemError)?);
+    }
+
+    let hash: Option<Vec<String>> = match matches.get_many::<String>("hash_algorithm") {
+        Some(values) => Some(normalize_algorithms(values.map(String::as_str))?),
+        None => None,
+    };
+
+    // The display and output arguments of a same format can be combined to write the results in both stdin and a file.
+    let compress: bool = matches.get_flag("compress");
+    let output: CliOutput = match (
+        matches.get_flag("csv_display"),
+        matches.get_one::<String>("csv_output"),
+        matches.get_flag("json_display"),
+        matches.get_one::<String>("json_output"),
+        matches.get_flag("xml_display"),
+        matches.get_one::<String>("xml_output"),
+    ) {
+        (true, Some(path), _, _, _, _) => CliOutput::CsvStdinAndFile(check_output_arg(path, compress)?),
+        (true, _, _, _, _, _) => CliOutput::CsvStdin,
+        (_, Some(path), _, _, _, _) => CliOutput::CsvFile(check_output_arg(path, compress)?),
+        (_, _, true, Some(path), _, _) => CliOutput::JsonStdinAndFile(check_output_arg(path, compress)?),
+        (_, _, true, _, _, _) => CliOutput::JsonStdin,
+        (_, _, _, Some(path), _, _) => CliOutput::JsonFile(check_output_arg(path, compress)?),
+        (_, _, _, _, true, Some(path)) => CliOutput::XmlStdinAndFile(check_output_arg(path, compress)?),
+        (_, _, _, _, true, _) => CliOutput::XmlStdin,
+        (_, _, _, _, _, Some(path)) => CliOutput::XmlFile(check_output_arg(path, compress)?),
+        _ => CliOutput::Standard,
+    };
+
+    let record_filelist: Option<String> = match matches.get_one::<String>("record_filelist") {
+        Some(path) => Some(check_output_arg(path, false)?),
+        None => None,
+    };
+
+    // Without '--temp-dir', the temporary files are written next to the output file, so they are renamed on the same filesystem.
+    // It's only resolved (and checked) if a file is written, the results displayed on stdout never need it.
+    let read_only: bool = matches.get_flag("read_only");
+    let writes_file: bool = output.file_path().is_some() || record_filelist.is_some() || matches.contains_id("emit_script");
+    let temp_dir: Option<String> = matches.get_one::<String>("temp_dir").cloned().filter(|_| writes_file);
+
+    // Every path written is checked before anything is written (even the probe file of the temporary directory).
+    if read_only && !search_path.is_empty() {
+        for path in [output.file_path(), record_filelist.as_deref(), temp_dir.as_deref()].into_iter().flatten() {
+            if system::is_same_filesystem(path, &search_path) {
+                return Err(DeepFinderError::SystemError(SystemError::WriteOnScannedFilesystem(path.to_string())));
+            }
+        }
+    }
+
+    // The files of '--from-stdin0' and '--replay-filelist' are only known once they're read,
+    // so the paths written are checked (and the temporary directory probed) by the search, before anything is written.
+    let temp_dir: Option<String> = match temp_dir {
+        Some(path) if read_only && search_path.is_empty() => Some(system::is_valid_folder_path(&path).map_err(DeepFinderError::SystemError)?),
+        Some(path) => Some(check_temp_dir_arg(&path)?),
+        None => None,
+    };
+
+    let config: FindingConfig = FindingConfig {
+        search_path,
+        enable_search_by_name: matches.get_flag("name") || !(matches.contains_id("hash_algorithm") || matches.get_flag("image_ahash")),
+        include_hidden_files: matches.get_flag("hidden_files"),
+        exclude,
+        exclude_regex,
+        content_type: matches.get_one::<String>("content_type").cloned(),
+        skip_dirs,
+        exclude_stubs: matches.get_flag("exclude_stubs"),
+        follow_reparse_points: cfg!(target_family = "windows") && matches.get_flag("follow_reparse_points"),
+        include_symlinks_as_entries: matches.get_flag("include_symlinks_as_entries"),
+        skip_top: matches.get_many::<String>("skip_top").map(|values| values.cloned().collect()).unwrap_or_default(),
+        // The checksums of a loaded report can be emitted again, without any algorithm.
+        include_hashes_in_output: (matches.contains_id("hash_algorithm") || filter_report.is_some() || merge_reports.is_some())
+            && (matches.get_flag("include_hashes") || matches.get_flag("combined_hash")),
+        combined_hash: matches.get_flag("combined_hash"),
+        hash_truncate: matches.get_one::<u16>("hash_truncate").map(|&length| usize::from(length)),
+        hash,
+        prefilter: matches.get_one::<String>("prefilter").cloned(),
+        algo_for_large: matches.get_one::<String>("algo_for_large").cloned().zip(matches.get_one::<u64>("large_threshold").copied()),
+        output,
+        temp_dir,
+        compress,
+        read_only,
+        filter_report,
+        record_filelist,
+        replay_filelist,
+        from_stdin0: matches.get_flag("from_stdin0"),
+        merge_reports,
+        compare,
+        verify_sidecars: matches.get_flag("verify_sidecars"),
+        hash_stdin: matches.get_flag("hash_stdin"),
+        min_size: matches.get_one::<u64>("min_size").copied(),
+        min_dir_size: matches.get_one::<u64>("min_dir_size").copied(),
+        min_wasted: matches.get_one::<u64>("min_wasted").copied(),
+        min_occurrences: matches.get_one::<usize>("min_occurrences").copied(),
+        only: matches.get_many::<String>("only").map(|values| values.cloned().collect()).unwrap_or_default(),
+        csv_style: CsvStyle {
+            delimiter: matches.get_one::<u8>("csv_delimiter").copied().unwrap_or(b';'),
+            always_quote: matches.get_flag("csv_always_quote"),
+            hash_columns: matches.get_flag("csv_hash_columns"),
+        },
+        xml_style: XmlStyle {
+            indent: matches.get_flag("xml_pretty"),
+            crlf: matches.get_flag("crlf"),
+        },
+        image_ahash: matches.get_flag("image_ahash").then(|| matches.get_one::<u32>("ahash_distance").copied().unwrap_or(5)),
+        size_tolerance: matches.get_one::<u32>("size_tolerance").copied(),
+        ignore_extension: matches.get_flag("ignore_extension"),
+        normalize_unicode: matches.get_flag("normalize_unicode"),
+        detect_confusables: matches.get_flag("detect_confusables"),
+        name_then_hash: matches.get_flag("name_then_hash"),
+        distinct_empty: matches.get_flag("distinct_empty"),
+        unique_content: matches.get_flag("unique_content"),
+        largest_first: matches.get_flag("largest_first"),
+        list_hashes: matches.get_flag("list_hashes"),
+        byte_budget: matches.get_one::<u64>("byte_budget").copied(),
+        checkpoint_interval: matches.get_one::<u64>("checkpoint_interval").copied(),
+        io_throttle: matches.get_one::<u64>("io_throttle").map(|megabytes| megabytes.saturating_mul(1_000_000)),
+        slow_file_threshold: matches.get_one::<u64>("slow_file_threshold").copied(),
+        keep_policy: match matches.get_one::<String>("keep").map(String::as_str) {
+            Some("last") => KeepPolicy::Last,
+            Some("shortest") => KeepPolicy::Shortest,
+            Some("longest") => KeepPolicy::Longest,
+            Some("oldest") => KeepPolicy::Oldest,
+            Some("newest") => KeepPolicy::Newest,
+            _ => KeepPolicy::First,
+        },
+        prefer_dirs,
+        scope: match matches.get_one::<String>("scope").map(String::as_str) {
+            Some("cross-dir") => Scope::CrossDir,
+            _ => Scope::Global,
+        },
+        list_deletable: matches.get_flag("list_deletable"),
+        emit_script: match matches.get_one::<String>("emit_script") {
+            Some(path) => Some(check_output_arg(path, false)?),
+            None => None,
+        },
+        on_group: matches.get_one::<String>("on_group").cloned(),
+        strict: matches.get_flag("strict"),
+        parallel_hash_within_file: matches.get_flag("parallel_hash_within_file"),
+        transform: matches.get_one::<String>("transform").and_then(|name| Transform::from_name(name)).unwrap_or_default(),
+        content_case_insensitive: matches.get_flag("content_case_insensitive"),
+        ignore_metadata: matches.get_flag("ignore_metadata"),
+        max_paths_per_group: matches.get_one::<usize>("max_paths_per_group").copied(),
+        format_template: matches.get_one::<String>("format_template").cloned(),
+        all_files: matches.get_flag("all_files"),
+        summary_report: matches.get_one::<String>("report").is_some_and(|report| report == "summary"),
+        by_dir_pairs: matches.get_flag("by_dir_pairs"),
+        report_empty_dirs: matches.get_flag("report_empty_dirs"),
+        json_tree: matches.get_flag("json_tree"),
+        quiet_if_empty: matches.get_flag("quiet_if_empty"),
+        fail_on_duplicates: matches.get_flag("fail_on_duplicates"),
+        quiet: matches.get_flag("quiet"),
+        progress_json: matches.get_flag("progress_json"),
+        benchmark: matches.get_flag("benchmark"),
+        relative_times: matches.get_flag("relative_times"),
+        color: !matches.get_flag("no_color") && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && io::stdout().is_terminal(),
+        warnings: WarningCollector::default(),
+        empty_dirs: EmptyDirCollector::default(),
+    };
+
+    for path in [config.output.file_path(), config.record_filelist.as_deref(), config.emit_script.as_deref()].into_iter().flatten() {
+        if fs::metadata(path).is_ok_and(|m| m.permissions().readonly()) {
+            record_warning(&config, "read_only_output", format!("Warning: the output file '{path}' is read-only, it may not be overwritten."));
+        }
+    }
+    if config.hash_truncate.is_some() {
+        record_warning(&config, "truncated_checksums", "Warning: the checksums are truncated in the output, don't use them to verify the files.".to_string());
+    }
+    if !cfg!(target_family = "windows") && matches.get_flag("follow_reparse_points") {
+        record_warning(&config, "ignored_option", "Warning: the reparse points only exist on Windows, --follow-reparse-points is ignored.".to_string());
+    }
+
+    Ok(config)
+}
+
+/// This function is responsible for normalizing the hash algorithms chosen by the user.
+/// The duplicated algorithms are removed (the first occurrence keeps its position), so a file is never hashed twice with the same one.
+/// Clap already restricts the values of '-a', but the algorithms are checked again whatever their source.
+///
+/// # Arguments
+///
+/// * `algorithms` - The hash algorithms, in the order chosen by the user.
+///
+/// # Returns
+///
+/// Ok(Vec<String>) with the unique algorithms, DeepFinderError if an algorithm isn't supported.
+///
+fn normalize_algorithms<'a>(algorithms: impl Iterator<Item = &'a str>) -> Result<Vec<String>, DeepFinderError> {
+    let mut normalized: Vec<String> = Vec::new();
+    for algorithm in algorithms {
+        if !HASH_ALGORITHMS.contains(&algorithm) {
+            return Err(DeepFinderError::ArgError(ArgError::UnknownAlgorithm(algorithm.to_string(), HASH_ALGORITHMS.join(", "))));
+        }
+
+        if !normalized.iter().any(|a| a == algorithm) {
+            normalized.push(algorithm.to_string());
+        }
+    }
+
+    Ok(normalized)
+}
+
+/// This function is responsible for checking the path to search, it must be a directory or a supported archive.
+///
+/// # Arguments
+///
+/// * `path` - The path to check.
+///
+/// # Returns
+///
+/// Ok(String) containing the full path if the path is valid, DeepFinderError otherwise.
+///
+fn check_search_path_arg(path: &str) -> Result<String, DeepFinderError> {
+    match system::is_valid_folder_path(path) {
+        Err(SystemError::NotADirectory(full_path)) if archive::is_supported_archive(&full_path) => Ok(full_path),
+        result => result.map_err(DeepFinderError::SystemError),
+    }
+}
+
+/// This function is responsible for removing the trailing separators of the search path.
+/// A root keeps its separator ("/" on Unix, "C:\" on Windows), since "" and "C:" aren't the root folder.
+///
+/// # Arguments
+///
+/// * `path` - The path to trim.
+///
+/// # Returns
+///
+/// The path without its trailing separators, or the root with a single separator.
+///
+fn trim_trailing_separators(path: &str) -> &str {
+    let trimmed: &str = path.trim_end_matches(['/', '\\']);
+    let is_root: bool = trimmed.is_empty() || (trimmed.len() == 2 && trimmed.ends_with(':'));
+    if is_root && trimmed.len() < path.len() { &path[..=trimmed.len()] } else { trimmed }
+}
+
+/// This function is responsible for compiling a regular expression of the '--exclude-regex' argument.
+///
+/// # Arguments
+///
+/// * `pattern` - The regular expression to compile.
+///
+/// # Returns
+///
+/// Ok(PathRegex) if the regular expression is valid, DeepFinderError with the compile error otherwise.
+///
+fn check_regex_arg(pattern: &str) -> Result<PathRegex, DeepFinderError> {
+    Regex::new(pattern)
+        .map(PathRegex)
+        .map_err(|e| DeepFinderError::ArgError(ArgError::InvalidRegex(pattern.to_string(), e.to_string())))
+}
+
+/// This function is responsible for checking a path of the '--skip-dir' argument, it must be an existing directory.
+/// The path is canonicalized, to be compared with the canonicalized paths of the traversed folders.
+///
+/// # Arguments
+///
+/// * `path` - The path to check.
+///
+/// # Returns
+///
+/// Ok(String) containing the canonical path if the path is a directory, DeepFinderError otherwise.
+///
+fn check_skip_dir_arg(path: &str) -> Result<String, DeepFinderError> {
+    let full_path: String = system::is_valid_folder_path(path).map_err(DeepFinderError::SystemError)?;
+    fs::canonicalize(&full_path)
+        .map(|canonical_path| canonical_path.to_string_lossy().to_string())
+        .map_err(|_| DeepFinderError::SystemError(SystemError::InvalidFolder(full_path)))
+}
+
+/// This function is responsible for checking the temporary directory, it must be an existing and writable directory.
+/// The check is done at startup, so a long scan isn't lost because its results can't be written.
+///
+/// # Arguments
+///
+/// * `path` - The path to check.
+///
+/// # Returns
+///
+/// Ok(String) containing the full path if the directory is writable, DeepFinderError otherwise.
+///
+fn check_temp_dir_arg(path: &str) -> Result<String, DeepFinderError> {
+    let full_path: String = system::is_valid_folder_path(path).map_err(DeepFinderError::SystemError)?;
+    system::check_writable_folder(&full_path).map_err(DeepFinderError::SystemError)?;
+    Ok(full_path)
+}
+
+/// This function is responsible for checking the paths of the '--compare' argument, they must be regular files.
+///
+/// # Arguments
+///
+/// * `path` - The path to check.
+///
+/// # Returns
+///
+/// Ok(String) containing the full path if it's a regular file, DeepFinderError otherwise.
+///
+fn check_compare_arg(path: &str) -> Result<String, DeepFinderError> {
+    let full_path: String = system::is_valid_file_path(path).map_err(DeepFinderError::SystemError)?;
+    if !Path::new(&full_path).is_file() {
+        return Err(DeepFinderError::SystemError(SystemError::NotAFile(full_path)));
+    }
+
+    Ok(full_path)
+}
+
+/// This function is responsible for checking the path for the 'output' arguments, if it's a valid path on the filesystem.
+/// If the output is compressed, the '.zst' extension is appended to the path (unless it already ends with it).
+///
+/// # Arguments
+///
+/// * `path` - The path to check.
+/// * `compress` - A boolean that indicates if the output file is compressed with zstd or not.
+///
+/// # Returns
+///
+/// Ok(String) if the path is valid, DeepFinderError otherwise.
+/// SystemError::OutputIsADirectory if the path is an existing directory.
+///
+fn check_output_arg(path: &str, compress: bool) -> Result<String, DeepFinderError> {
+    let path: String = if compress && !path.to_ascii_lowercase().ends_with(".zst") { format!("{path}.zst") } else { path.to_string() };
+    let full_path: String = system::is_valid_file_path(&path).map_err(DeepFinderError::SystemError)?;
+
+    if fs::metadata(&full_path).is_ok_and(|m| m.is_dir()) {
+        return Err(DeepFinderError::SystemError(SystemError::OutputIsADirectory(full_path)));
+    }
+
+    Ok(full_path)
+}
+
+/// This function is responsible for parsing a size given by the user, in bytes.
+/// The size can be followed by a unit suffix (K, M, G, T), optionally ending with 'B', in powers of 1024.
+///
+/// # Arguments
+///
+/// * `value` - The size to parse (e.g. "512", "100M", "2GB").
+///
+/// # Returns
+///
+/// Ok(u64) containing the size in bytes, an error message otherwise.
+///
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value: String = value.trim().to_ascii_uppercase();
+    let digits: &str = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let multiplier: u64 = match value[digits.len()..].trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        unit => return Err(format!("unknown size unit '{unit}'")),
+    };
+
+    digits
+        .trim()
+        .parse::<u64>()
+        .map_err(|e| e.to_string())?
+        .checked_mul(multiplier)
+        .ok_or_else(|| "size too large".to_string())
+}
+
+/// This function is responsible for parsing a percentage given by the user, with at most two decimals.
+///
+/// # Arguments
+///
+/// * `value` - The percentage to parse (e.g. "1", "0.5", "2.25%").
+///
+/// # Returns
+///
+/// Ok(u32) containing the percentage in hundredths of a percent (e.g. 50 for "0.5"), an error message otherwise.
+///
+fn parse_percent(value: &str) -> Result<u32, String> {
+    let value: &str = value.trim().trim_end_matches('%');
+    let (integer, decimals): (&str, &str) = value.split_once('.').unwrap_or((value, ""));
+    if decimals.len() > 2 || !decimals.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("invalid percentage '{value}', at most two decimals are allowed"));
+    }
+
+    let hundredths: u32 = integer.parse::<u32>().map_err(|e| e.to_string())?.saturating_mul(100) + format!("{decimals:0<2}").parse::<u32>().unwrap_or(0);
+    if hundredths > 100 * 100 {
+        return Err("the percentage must be between 0 and 100".to_string());
+    }
+
+    Ok(hundredths)
+}
+
+/// This function is responsible for parsing the template of the standard output given by the user.
+/// Every placeholder between braces must be known (see FORMAT_PLACEHOLDERS), a literal brace is escaped as "{{" or "}}".
+///
+/// # Arguments
+///
+/// * `value` - The template to parse (e.g. "{count} {name}").
+///
+/// # Returns
+///
+/// Ok(String) containing the template, an error message otherwise.
+///
+fn parse_format_template(value: &str) -> Result<String, String> {
+    if let Some(placeholder) = export::template_placeholders(value)?.into_iter().find(|placeholder| !FORMAT_PLACEHOLDERS.contains(placeholder)) {
+        return Err(format!("unknown placeholder '{{{placeholder}}}', expected one of: {}", FORMAT_PLACEHOLDERS.join(", ")));
+    }
+
+    Ok(value.to_string())
+}
+
+/// This function is responsible for parsing the CSV delimiter given by the user.
+///
+/// # Arguments
+///
+/// * `value` - The delimiter to parse, a single ASCII character or '\t' for a tab.
+///
+/// # Returns
+///
+/// Ok(u8) containing the delimiter byte, an error message otherwise.
+/// The quote and the line breaks are refused, the fields couldn't be split anymore.
+///
+fn parse_csv_delimiter(value: &str) -> Result<u8, String> {
+    match value.as_bytes() {
+        b"\\t" => Ok(b'\t'),
+        [b'"' | b'\n' | b'\r'] => Err(format!("the CSV delimiter can't be a quote or a line break, got '{}'", value.escape_default())),
+        [byte] if byte.is_ascii() => Ok(*byte),
+        _ => Err(format!("the CSV delimiter must be a single ASCII character, got '{value}'")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_user_choices() {
+        let command_context: Command = build_command_context();
+
+        // Set different settings for Unix and Windows.
+        let search_path: String;
+        let matches: ArgMatches;
+        let output: CliOutput = if cfg!(target_family = "unix") {
+            search_path = "/tmp".to_string();
+            matches = command_context.clone().get_matches_from(vec!["deepfinder", "/tmp", "-a", "md5,sha256", "-f", "-n", "-C", "/tmp/output.csv"]);
+            CliOutput::CsvFile("/tmp/output.csv".to_string())
+        } else {
+            search_path = "C:\\Windows".to_string();
+            matches = command_context.clone().get_matches_from(vec!["deepfinder", "C:\\Windows\\", "-a", "md5,sha256", "-f", "-n", "-C", "C:\\output.csv"]);
+            CliOutput::CsvFile("C:\\output.csv".to_string())
         };
 
         let expected: FindingConfig = FindingConfig {
@@ -338,6 +1791,7 @@ mod tests {
             include_hashes_in_output: false,
             hash: Some(vec!["md5".to_string(), "sha256".to_string()]),
             output,
+            ..Default::default()
         };
         assert_eq!(parse_user_choices(&matches).unwrap(), expected);
 
@@ -347,7 +1801,326 @@ mod tests {
         let matches_error2: ArgMatches = command_context.clone().get_matches_from(vec!["deepfinder", "/test", "-a", "md5,sha256", "-f", "-n", "-J", "./output.json"]); // Wrong searching path.
         assert!(parse_user_choices(&matches_error2).is_err());
 
+        let matches_tee: ArgMatches = command_context.clone().get_matches_from(vec!["deepfinder", "./", "--json-display", "--json-output", "./output.json"]);
+        assert!(matches!(parse_user_choices(&matches_tee).unwrap().output, CliOutput::JsonStdinAndFile(_)));
+        assert!(command_context.clone().try_get_matches_from(vec!["deepfinder", "./", "-j", "-C", "./output.csv"]).is_err());
+
         let matches_error3: ArgMatches = command_context.get_matches_from(vec!["deepfinder", "/tmp", "-a", "md5,sha256", "-f", "-n", "-J", "/test/output.json"]); // Wrong output path.
         assert!(parse_user_choices(&matches_error3).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_user_choices_with_ignore_file() {
+        let dir: std::path::PathBuf = env::temp_dir().join("deepfinder_test_ignore_file");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(IGNORE_FILE_NAME), "# Build outputs\n*.tmp, *.log\n\nnode_modules\ntarget\n").unwrap();
+        let other_file: String = env::temp_dir().join("deepfinder_test_other_ignore_file").to_string_lossy().to_string();
+        fs::write(&other_file, "*.bak").unwrap();
+        let dir_path: String = dir.to_string_lossy().to_string();
+
+        // The ignore file of the scan root is merged with the '--exclude' patterns.
+        let matches: ArgMatches = build_command_context().get_matches_from(vec!["deepfinder", &dir_path, "--exclude", "*.iso"]);
+        assert_eq!(parse_user_choices(&matches).unwrap().exclude, ["*.iso", "*.tmp", "*.log", "node_modules", "target"]);
+
+        // Another ignore file replaces it.
+        let matches: ArgMatches = build_command_context().get_matches_from(vec!["deepfinder", &dir_path, "--ignore-file", &other_file]);
+        assert_eq!(parse_user_choices(&matches).unwrap().exclude, ["*.bak"]);
+
+        let matches: ArgMatches = build_command_context().get_matches_from(vec!["deepfinder", &dir_path, "--ignore-file", "/missing/.deepfinderignore"]);
+        assert!(matches!(parse_user_choices(&matches), Err(DeepFinderError::SystemError(SystemError::UnableToReadFile(_, _)))));
+
+        let _ = fs::remove_file(other_file);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_parse_user_choices_with_read_only() {
+        // The harness records the size and modification time of every entry, a write changes at least one folder.
+        fn snapshot(dir: &Path, entries: &mut Vec<(std::path::PathBuf, u64, std::time::SystemTime)>) {
+            let metadata: fs::Metadata = fs::metadata(dir).unwrap();
+            entries.push((dir.to_path_buf(), metadata.len(), metadata.modified().unwrap()));
+            if metadata.is_dir() {
+                let mut children: Vec<std::path::PathBuf> = fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+                children.sort();
+                children.iter().for_each(|child| snapshot(child, entries));
+            }
+        }
+
+        let dir: std::path::PathBuf = env::temp_dir().join("deepfinder_test_read_only");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "content").unwrap();
+        fs::write(dir.join("sub").join("a.txt"), "content").unwrap();
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let output_path: String = dir.join("report.json").to_string_lossy().to_string();
+
+        // A path written on the scanned filesystem is refused, before anything is written.
+        for (option, path) in [("-J", &output_path), ("--record-filelist", &output_path)] {
+            let matches: ArgMatches = build_command_context().get_matches_from(vec!["deepfinder", &dir_path, "--read-only", option, path]);
+            assert!(matches!(parse_user_choices(&matches), Err(DeepFinderError::SystemError(SystemError::WriteOnScannedFilesystem(_)))));
+        }
+        assert!(build_command_context().try_get_matches_from(vec!["deepfinder", &dir_path, "--no-write", "--emit-script", &output_path]).is_err());
+
+        // The search itself doesn't write anything.
+        let mut before: Vec<(std::path::PathBuf, u64, std::time::SystemTime)> = Vec::new();
+        snapshot(&dir, &mut before);
+        let matches: ArgMatches = build_command_context().get_matches_from(vec!["deepfinder", &dir_path, "--read-only", "-a", "md5", "-q"]);
+        let config: FindingConfig = parse_user_choices(&matches).unwrap();
+        assert!(crate::search_engine::search_engine_scheduler(&config).unwrap());
+        let mut after: Vec<(std::path::PathBuf, u64, std::time::SystemTime)> = Vec::new();
+        snapshot(&dir, &mut after);
+        assert_eq!(before, after);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_parse_user_choices_with_temp_dir() {
+        let dir: std::path::PathBuf = env::temp_dir().join("deepfinder_test_temp_dir_arg");
+        fs::create_dir_all(&dir).unwrap();
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let output_path: String = dir.join("report.json").to_string_lossy().to_string();
+        let missing_path: String = dir.join("missing").to_string_lossy().to_string();
+
+        // The temporary directory is only checked if a file is written.
+        let matches: ArgMatches = build_command_context().get_matches_from(vec!["deepfinder", &dir_path, "-j", "--temp-dir", &missing_path]);
+        assert_eq!(parse_user_choices(&matches).unwrap().temp_dir, None);
+        let matches: ArgMatches = build_command_context().get_matches_from(vec!["deepfinder", &dir_path, "-J", &output_path, "--temp-dir", &missing_path]);
+        assert!(parse_user_choices(&matches).is_err());
+        let matches: ArgMatches = build_command_context().get_matches_from(vec!["deepfinder", &dir_path, "-J", &output_path, "--temp-dir", &dir_path]);
+        assert_eq!(parse_user_choices(&matches).unwrap().temp_dir, Some(dir_path.clone()));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_parse_user_choices_with_report() {
+        // The checksums stored in a report can be emitted again without any algorithm.
+        let matches: ArgMatches =
+            build_command_context().get_matches_from(vec!["deepfinder", "--filter-report", "report.json", "-i", "--only", "*.iso", "--only", "*.img"]);
+        let config: FindingConfig = parse_user_choices(&matches).unwrap();
+        assert!(config.include_hashes_in_output);
+        assert_eq!(config.only, vec!["*.iso".to_string(), "*.img".to_string()]);
+
+        let matches: ArgMatches = build_command_context().get_matches_from(vec!["deepfinder", ".", "-i"]);
+        assert!(!parse_user_choices(&matches).unwrap().include_hashes_in_output);
+    }
+
+    #[test]
+    fn test_emit_script_requires_identical_contents() {
+        // The script only deletes the files of identical contents.
+        assert!(build_command_context().try_get_matches_from(vec!["deepfinder", ".", "--emit-script", "rm.sh"]).is_err());
+        assert!(build_command_context().try_get_matches_from(vec!["deepfinder", ".", "--emit-script", "rm.sh", "--size-tolerance", "5"]).is_err());
+        for option in ["-n", "--ignore-metadata", "--content-case-insensitive"] {
+            assert!(build_command_context().try_get_matches_from(vec!["deepfinder", ".", "--emit-script", "rm.sh", "-a", "md5", option]).is_err());
+        }
+        assert!(build_command_context().try_get_matches_from(vec!["deepfinder", ".", "--emit-script", "rm.sh", "-a", "md5"]).is_ok());
+    }
+
+    #[test]
+    fn test_list_deletable_requires_identical_contents() {
+        // Only the files of identical contents are listed as deletable.
+        assert!(build_command_context().try_get_matches_from(vec!["deepfinder", ".", "--list-deletable"]).is_err());
+        assert!(build_command_context().try_get_matches_from(vec!["deepfinder", ".", "--list-deletable", "-n"]).is_err());
+        for option in ["-n", "--ignore-metadata", "--content-case-insensitive"] {
+            assert!(build_command_context().try_get_matches_from(vec!["deepfinder", ".", "--list-deletable", "-a", "md5", option]).is_err());
+        }
+        assert!(build_command_context().try_get_matches_from(vec!["deepfinder", ".", "--list-deletable", "-a", "md5"]).is_ok());
+    }
+
+    #[test]
+    fn test_skip_top_with_search_path() {
+        // A single name is taken per occurrence, so the search path after it stays positional.
+        let matches: ArgMatches = build_command_context().get_matches_from(vec!["deepfinder", "--skip-top", "vendor", "--skip-top", "archive", "/data"]);
+        let names: Vec<&String> = matches.get_many::<String>("skip_top").unwrap().collect();
+        assert_eq!(names, ["vendor", "archive"]);
+        assert_eq!(matches.get_one::<String>("path").map(String::as_str), Some("/data"));
+    }
+
+    #[test]
+    fn test_exclude_regex_with_search_path() {
+        // A single pattern is taken per occurrence, so the search path after it stays positional.
+        let matches: ArgMatches = build_command_context().get_matches_from(vec!["deepfinder", "--exclude-regex", r"\.bak$", "/data"]);
+        let patterns: Vec<&String> = matches.get_many::<String>("exclude_regex").unwrap().collect();
+        assert_eq!(patterns, [r"\.bak$"]);
+        assert_eq!(matches.get_one::<String>("path").map(String::as_str), Some("/data"));
+    }
+
+    #[test]
+    fn test_trim_trailing_separators() {
+        assert_eq!(trim_trailing_separators("/"), "/");
+        assert_eq!(trim_trailing_separators("///"), "/");
+        assert_eq!(trim_trailing_separators("C:\\"), "C:\\");
+        assert_eq!(trim_trailing_separators("C:/"), "C:/");
+        assert_eq!(trim_trailing_separators("/home/user/dir/"), "/home/user/dir");
+        assert_eq!(trim_trailing_separators("C:\\Users\\dir\\\\"), "C:\\Users\\dir");
+        assert_eq!(trim_trailing_separators("dir"), "dir");
+    }
+
+    #[test]
+    fn test_check_output_arg() {
+        let dir: std::path::PathBuf = env::temp_dir().join("deepfinder_test_output_dir");
+        fs::create_dir_all(&dir).unwrap();
+        let dir_path: String = dir.to_string_lossy().to_string();
+
+        assert_eq!(check_output_arg(&dir_path, false), Err(DeepFinderError::SystemError(SystemError::OutputIsADirectory(dir_path.clone()))));
+        assert_eq!(check_output_arg(&format!("{dir_path}/report.json"), false), Ok(format!("{dir_path}/report.json")));
+        assert_eq!(check_output_arg(&format!("{dir_path}/report.json"), true), Ok(format!("{dir_path}/report.json.zst")));
+
+        // The compression needs an output file.
+        assert!(build_command_context().try_get_matches_from(["deepfinder", &dir_path, "-j", "--compress"]).is_err());
+        let matches: ArgMatches = build_command_context().get_matches_from(["deepfinder", &dir_path, "-J", &format!("{dir_path}/report.json"), "--compress"]);
+        assert!(parse_user_choices(&matches).unwrap().compress);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("4K"), Ok(4096));
+        assert_eq!(parse_size("100MB"), Ok(100 * 1024 * 1024));
+        assert_eq!(parse_size("2g"), Ok(2 * 1024 * 1024 * 1024));
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("MB").is_err());
+    }
+
+    #[test]
+    fn test_parse_percent() {
+        assert_eq!(parse_percent("1"), Ok(100));
+        assert_eq!(parse_percent("0.5"), Ok(50));
+        assert_eq!(parse_percent("2.25%"), Ok(225));
+        assert_eq!(parse_percent("100"), Ok(10_000));
+        assert!(parse_percent("0.125").is_err());
+        assert!(parse_percent("101").is_err());
+        assert!(parse_percent("-1").is_err());
+    }
+
+    #[test]
+    fn test_normalize_algorithms() {
+        assert_eq!(normalize_algorithms(["md5", "md5", "sha256", "md5"].into_iter()), Ok(vec!["md5".to_string(), "sha256".to_string()]));
+        assert!(matches!(
+            normalize_algorithms(["md5", "crc32"].into_iter()),
+            Err(DeepFinderError::ArgError(ArgError::UnknownAlgorithm(algorithm, _))) if algorithm == "crc32"
+        ));
+
+        let search_path: String = env::temp_dir().to_string_lossy().to_string();
+        let matches: ArgMatches = build_command_context().get_matches_from(["deepfinder", &search_path, "-a", "md5", "md5", "sha256"]);
+        assert_eq!(parse_user_choices(&matches).unwrap().hash, Some(vec!["md5".to_string(), "sha256".to_string()]));
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn test_follow_reparse_points_on_unix() {
+        let search_path: String = env::temp_dir().to_string_lossy().to_string();
+        let matches: ArgMatches = build_command_context().get_matches_from(["deepfinder", &search_path, "--follow-reparse-points", "-q"]);
+        let config: FindingConfig = parse_user_choices(&matches).unwrap();
+        assert!(!config.follow_reparse_points);
+        assert_eq!(crate::search_engine::take_warnings(&config).iter().map(|w| w.kind).collect::<Vec<&str>>(), ["ignored_option"]);
+    }
+
+    #[test]
+    fn test_unsupported_args_with_archive() {
+        let archive_path: String = env::temp_dir().join("deepfinder_test_content_type.zip").to_string_lossy().to_string();
+        fs::write(&archive_path, b"").unwrap();
+
+        let matches: ArgMatches = build_command_context().get_matches_from(["deepfinder", &archive_path, "--content-type", "text"]);
+        assert!(matches!(
+            parse_user_choices(&matches),
+            Err(DeepFinderError::ArgError(ArgError::UnsupportedWithArchive(option))) if option == "--content-type"
+        ));
+        let matches: ArgMatches = build_command_context().get_matches_from(["deepfinder", &archive_path, "--verify-sidecars"]);
+        assert!(matches!(
+            parse_user_choices(&matches),
+            Err(DeepFinderError::ArgError(ArgError::UnsupportedWithArchive(option))) if option == "--verify-sidecars"
+        ));
+        let matches: ArgMatches = build_command_context().get_matches_from(["deepfinder", &archive_path, "--image-ahash"]);
+        assert!(matches!(
+            parse_user_choices(&matches),
+            Err(DeepFinderError::ArgError(ArgError::UnsupportedWithArchive(option))) if option == "--image-ahash"
+        ));
+        let matches: ArgMatches = build_command_context().get_matches_from(["deepfinder", &archive_path]);
+        assert!(parse_user_choices(&matches).is_ok());
+
+        let _ = fs::remove_file(archive_path);
+    }
+
+    #[test]
+    fn test_format_algorithms() {
+        let command_context: Command = build_command_context();
+        let text_algorithms: Vec<String> = format_algorithms(false).lines().map(ToString::to_string).collect();
+        let json_algorithms: Vec<String> = serde_json::from_str(&format_algorithms(true)).unwrap();
+        assert_eq!(text_algorithms, json_algorithms);
+
+        // Every listed algorithm is accepted by the parser, and the parser accepts nothing else.
+        for algorithm in &text_algorithms {
+            assert!(command_context.clone().try_get_matches_from(["deepfinder", "/tmp", "-a", algorithm]).is_ok());
+        }
+        let accepted: Vec<String> = command_context
+            .get_arguments()
+            .find(|arg| arg.get_id() == "hash_algorithm")
+            .map(|arg| arg.get_possible_values().iter().map(|value| value.get_name().to_string()).collect())
+            .unwrap_or_default();
+        assert_eq!(accepted, text_algorithms);
+    }
+
+    #[test]
+    fn test_hash_self_check() {
+        // Every supported algorithm has a test vector, and computes the expected checksum.
+        let self_check: Vec<(&str, bool)> = hash_self_check();
+        assert_eq!(self_check.iter().map(|(algorithm, _)| *algorithm).collect::<Vec<&str>>(), HASH_ALGORITHMS);
+        assert!(self_check.iter().all(|(_, passed)| *passed));
+
+        let (report, passed): (String, bool) = format_doctor_report(&self_check);
+        assert!(passed);
+        assert!(report.contains("  whirlpool    ok\n"));
+        assert!(report.ends_with("Every check has passed.\n"));
+
+        let (report, passed): (String, bool) = format_doctor_report(&[("md5", true), ("sha1", false)]);
+        assert!(!passed);
+        assert!(report.contains("  sha1         FAILED\n"));
+    }
+
+    #[test]
+    fn test_list_algorithms_format() {
+        let get_format = |args: &[&str]| {
+            let matches: ArgMatches = build_command_context().try_get_matches_from(args).unwrap();
+            (matches.get_one::<String>("list_algorithms").cloned(), matches.get_one::<String>("path").cloned())
+        };
+        assert_eq!(get_format(&["deepfinder", "--list-algorithms"]), (Some("text".to_string()), None));
+        assert_eq!(get_format(&["deepfinder", "--list-algorithms=json"]), (Some("json".to_string()), None));
+
+        // The format must be attached, so a following value is the search path.
+        assert_eq!(get_format(&["deepfinder", "--list-algorithms", "/data"]), (Some("text".to_string()), Some("/data".to_string())));
+    }
+
+    #[test]
+    fn test_format_version_json() {
+        let version: serde_json::Value = serde_json::from_str(&format_version_json()).unwrap();
+        assert_eq!(version["name"], "deepfinder");
+        assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+        let expected: [&str; 6] = ["archive-zip", "archive-tar", "zstd-output", "image-ahash", "parallel-blake3", "xxh3-prefilter"];
+        assert_eq!(version["features"], serde_json::json!(expected));
+    }
+
+    #[test]
+    fn test_parse_format_template() {
+        assert_eq!(parse_format_template("{count}x {name} ({wasted}/{size}): {path}"), Ok("{count}x {name} ({wasted}/{size}): {path}".to_string()));
+        assert_eq!(parse_format_template("no placeholder"), Ok("no placeholder".to_string()));
+        assert!(parse_format_template("{name} {checksum}").is_err());
+        assert!(parse_format_template("{name").is_err());
+        assert!(parse_format_template("{name} }").is_err());
+        assert_eq!(parse_format_template("{{checksum}} {name}"), Ok("{{checksum}} {name}".to_string()));
+    }
+
+    #[test]
+    fn test_parse_csv_delimiter() {
+        assert_eq!(parse_csv_delimiter(","), Ok(b','));
+        assert_eq!(parse_csv_delimiter("\\t"), Ok(b'\t'));
+        assert!(parse_csv_delimiter(",;").is_err());
+        assert!(parse_csv_delimiter("é").is_err());
+        for value in ["\"", "\n", "\r"] {
+            assert!(parse_csv_delimiter(value).is_err());
+        }
+    }
+}
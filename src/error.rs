@@ -1,5 +1,7 @@
 // External crates.
 use core::fmt;
+use std::error::Error;
+use std::io;
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum DeepFinderError {
@@ -11,20 +13,35 @@ pub enum DeepFinderError {
 pub enum ArgError {
     NoPathSpecified,
     SyntaxError,
+    InvalidRegex(String, String),
+    UnknownAlgorithm(String, String),
+    UnsupportedWithArchive(String),
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug)]
 pub enum SystemError {
     InvalidPath(String),
     InvalidFilename(String),
-    UnableToCreateFile(String, String),
+    UnableToCreateFile(String, io::Error),
     UnableToSerialize(String, String),
+    UnableToReadFile(String, io::Error),
+    UnableToWriteOutput(io::Error),
+    UnableToDeserialize(String, String),
     #[cfg(target_family = "windows")]
     PathTooLong(String),
     ParentFolderDoesntExist(String),
     InvalidFolder(String),
-    UnableToReadDir(String),
-    UnableToGetCurrentDir(String),
+    NotADirectory(String),
+    NotAFile(String),
+    OutputIsADirectory(String),
+    UnableToReadDir(String, io::Error),
+    AccessDenied(String),
+    UnableToGetCurrentDir(String, io::Error),
+    UnableToReadArchive(String, io::Error),
+    TempDirNotWritable(String, io::Error),
+    InsufficientSpace(String, u64, u64),
+    HookFailed(String, String, String),
+    WriteOnScannedFilesystem(String),
     ThreadError,
 }
 
@@ -42,6 +59,9 @@ impl fmt::Display for ArgError {
         match self {
             Self::NoPathSpecified => write!(f, "Error: no path specified.\nUsage: deefinder <path> [options]\nTry 'deefinder --help' for more information."),
             Self::SyntaxError => write!(f, "Error: syntax error, please check the command line arguments.\nUsage: deefinder <path> [options]\nTry 'deefinder --help' for more information."),
+            Self::InvalidRegex(pattern, e) => write!(f, "Error: invalid regular expression '{pattern}':\n{e}"),
+            Self::UnknownAlgorithm(algorithm, valid) => write!(f, "Error: unknown hash algorithm '{algorithm}'.\nYou can choose between: {valid}."),
+            Self::UnsupportedWithArchive(option) => write!(f, "Error: the option '{option}' can't be used when the search path is an archive."),
         }
     }
 }
@@ -50,16 +70,95 @@ impl fmt::Display for SystemError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::InvalidFolder(p) => write!(f, "Error: invalid folder specified '{p}'.\nThis folder may not exist.\nUsage: deefinder <path> [options]\nTry 'deefinder --help' for more information."),
+            Self::NotADirectory(p) => write!(f, "Error: '{p}' is not a directory (nor a supported archive).\nUsage: deefinder <path> [options]\nTry 'deefinder --help' for more information."),
+            Self::NotAFile(p) => write!(f, "Error: '{p}' is not a regular file."),
+            Self::OutputIsADirectory(p) => write!(f, "Error: the output path '{p}' is an existing directory, please specify a file path."),
             Self::InvalidPath(p) => write!(f, "Error: invalid path '{p}'"),
             Self::InvalidFilename(file) => write!(f, "Error: invalid filename '{file}'"),
             Self::UnableToCreateFile(p, e) => write!(f, "Error: unable to create file '{p}': {e}"),
             Self::UnableToSerialize(format, e) => write!(f, "Error: unable to serialize data to '{format}' format: {e}"),
+            Self::UnableToWriteOutput(e) => write!(f, "Error: unable to write the output: {e}"),
+            Self::UnableToReadFile(p, e) => write!(f, "Error: unable to read file '{p}': {e}"),
+            Self::UnableToDeserialize(format, e) => write!(f, "Error: unable to deserialize data from '{format}' format: {e}"),
             #[cfg(target_family = "windows")]
             Self::PathTooLong(p) => write!(f, "Error: path too long '{p}'"),
             Self::ParentFolderDoesntExist(p) => write!(f, "Error: parent folder doesn't exist '{p}'"),
-            Self::UnableToReadDir(p) => write!(f, "Error: unable to read directory '{p}'"),
-            Self::UnableToGetCurrentDir(e) => write!(f, "Error: unable to get current directory.\n{e}"),
+            Self::UnableToReadDir(p, e) => write!(f, "Error: unable to read directory '{p}': {e}"),
+            Self::AccessDenied(p) => write!(f, "Error: access denied to '{p}'"),
+            Self::UnableToGetCurrentDir(p, e) => write!(f, "Error: unable to get the current directory to resolve '{p}', it may have been deleted or unmounted.\nPlease run DeepFinder from an existing directory or use an absolute path.\n{e}"),
+            Self::UnableToReadArchive(p, e) => write!(f, "Error: unable to read archive '{p}': {e}"),
+            Self::InsufficientSpace(p, needed, available) => write!(f, "Error: not enough free space in '{p}' to write the output ({needed} bytes needed, {available} bytes available)."),
+            Self::TempDirNotWritable(p, e) => write!(f, "Error: the temporary directory '{p}' isn't writable: {e}\nPlease specify another one with --temp-dir."),
+            Self::HookFailed(command, group, e) => write!(f, "Error: the command '{command}' has failed for the group '{group}': {e}"),
+            Self::WriteOnScannedFilesystem(p) => write!(f, "Error: '{p}' is on the scanned filesystem, nothing is written there with --read-only.\nPlease write the results to stdout or to another volume."),
             Self::ThreadError => write!(f, "Error: thread error"),
         }
     }
-}
\ No newline at end of file
+}
+
+// The I/O errors can't be compared, two errors are equal if they have the same variant and message.
+impl PartialEq for SystemError {
+    fn eq(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other) && self.to_string() == other.to_string()
+    }
+}
+
+impl Eq for SystemError {}
+
+// The wrappers display the message of the inner error, so the source is the cause of the inner error.
+impl Error for DeepFinderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::ArgError(e) => e.source(),
+            Self::SystemError(e) => e.source(),
+        }
+    }
+}
+
+impl Error for ArgError {}
+
+impl Error for SystemError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::UnableToCreateFile(_, e)
+            | Self::UnableToReadFile(_, e)
+            | Self::UnableToReadDir(_, e)
+            | Self::UnableToReadArchive(_, e)
+            | Self::UnableToGetCurrentDir(_, e)
+            | Self::TempDirNotWritable(_, e) => Some(e),
+            Self::UnableToWriteOutput(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_source_chaining() {
+        let cause: io::Error = io::Error::new(io::ErrorKind::NotFound, "No such file or directory");
+        let error: Box<dyn Error> = Box::new(DeepFinderError::SystemError(SystemError::UnableToReadFile("/test".to_string(), cause)));
+        assert_eq!(error.to_string(), "Error: unable to read file '/test': No such file or directory");
+
+        // The source is the I/O error itself, not a copy of its message.
+        let source: &dyn Error = error.source().expect("The I/O error should be the source");
+        let io_error: &io::Error = source.downcast_ref::<io::Error>().expect("The source should be an io::Error");
+        assert_eq!(io_error.kind(), io::ErrorKind::NotFound);
+        assert!(source.source().is_none());
+
+        // So is the I/O error of a folder or an archive, stored with its path.
+        let error: SystemError = SystemError::UnableToReadDir("/test".to_string(), io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(error.to_string().starts_with("Error: unable to read directory '/test': "));
+        assert!(error.source().and_then(|e| e.downcast_ref::<io::Error>()).is_some_and(|e| e.kind() == io::ErrorKind::PermissionDenied));
+        let error: SystemError = SystemError::UnableToReadArchive("/test.zip".to_string(), io::Error::other("invalid Zip archive"));
+        assert!(error.source().is_some_and(|e| e.to_string() == "invalid Zip archive"));
+
+        // The errors without any underlying cause have no source.
+        let error: Box<dyn Error> = DeepFinderError::SystemError(SystemError::InvalidFolder("/test".to_string())).into();
+        assert!(error.source().is_none());
+        let arg_error: Box<dyn Error> = DeepFinderError::ArgError(ArgError::NoPathSpecified).into();
+        assert!(arg_error.source().is_none());
+    }
+}
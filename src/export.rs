@@ -1,219 +1,1448 @@
 // Internal crates.
 use crate::{
-    cli::{CliOutput, FindingConfig},
+    cli::{CliOutput, CsvStyle, FindingConfig, KeepPolicy, XmlStyle},
     error::{DeepFinderError, SystemError},
-    search_engine::DuplicateFile,
+    search_engine::{DuplicateFile, EmptyDir, Warning},
+    system::{self, VirtualFile},
 };
 
 // External crates.
-use csv::WriterBuilder;
+use csv::{QuoteStyle, WriterBuilder};
+use schemars::{JsonSchema, generate::SchemaSettings};
 use serde::Serialize;
-use std::{collections::{HashMap, HashSet}, fs};
+use serde_json::{Map, Value};
+use serde_xml_rs::SerdeXml;
+use sha2::{Digest, Sha256};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Component, Path},
+    time::{Duration, SystemTime},
+};
+use xml::EmitterConfig;
 
 /// This struct is used to serialize (except for CSV format) the DuplicateFile struct without checkums.
 /// The "checksums" fields is None if `include_hashes` is false or if there isn't any checksum.
+/// The "occurrences" and "paths_truncated" fields are only serialized if the paths have been truncated.
+/// The "group_id" field identifies the group across runs, it only depends on its paths.
 ///
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields, description = "A group of duplicates.")]
 struct DuplicateFileSerialized<'a> {
+    /// The index of the group in the report, starting at 1.
+    #[schemars(range(min = 1))]
     pub index: usize,
-    pub paths: &'a HashSet<String>,
+    /// The identifier of the group across runs, derived from its paths.
+    pub group_id: String,
+    pub paths: Vec<&'a str>,
+    /// The number of paths of the group, only if the paths are truncated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occurrences: Option<usize>,
+    /// Only present (true) if the paths are truncated.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub paths_truncated: bool,
     pub name: &'a str,
+    /// The checksums by hash algorithm.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub checksums: Option<&'a HashMap<String, String>>,
+    pub checksums: Option<&'a BTreeMap<String, String>>,
+    /// The size of each file of the group, in bytes (the size of the largest one if they differ).
+    pub size: u64,
+}
+
+/// This struct describes the object written by json_display, so the schema of the JSON output ('--export-schema json') is derived from the serialized types.
+///
+// The report is streamed group by group, so this struct is never built: it only gives the schema its top level.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields, title = "DeepFinder report", description = "The duplicate groups found by DeepFinder and the warnings of the search.")]
+struct JsonReport<'a> {
+    pub duplicates: Vec<DuplicateFileSerialized<'a>>,
+    pub warnings: Vec<Warning>,
+}
+
+impl<'a> DuplicateFileSerialized<'a> {
+    /// This function is responsible for building the serialized form of a duplicate group.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the group in the findings, starting at 1.
+    /// * `duplicate` - The DuplicateFile to serialize.
+    /// * `include_hashes` - A boolean that indicates if the checksums should be serialized or not.
+    /// * `max_paths` - The maximum number of paths to serialize, None for all of them.
+    ///
+    /// # Returns
+    ///
+    /// The DuplicateFileSerialized struct.
+    ///
+    fn new(index: usize, duplicate: &'a DuplicateFile, include_hashes: bool, max_paths: Option<usize>) -> Self {
+        let (paths, hidden_paths): (Vec<&'a str>, usize) = displayed_paths(&duplicate.paths, max_paths);
+        Self {
+            index,
+            group_id: group_id(&duplicate.paths),
+            paths,
+            occurrences: (hidden_paths > 0).then_some(duplicate.paths.len()),
+            paths_truncated: hidden_paths > 0,
+            name: &duplicate.name,
+            checksums: if include_hashes { duplicate.checksums.as_ref() } else { None },
+            size: duplicate.size,
+        }
+    }
+}
+
+// ANSI escape codes used by the colorized standard output.
+const BOLD_CYAN: &str = "1;36";
+const BOLD_GREEN: &str = "1;32";
+const DIM: &str = "2";
+
+/// This struct is used to serialize a path that can be deleted, with the kept path of its group.
+///
+#[derive(Serialize)]
+struct DeletableFile<'a> {
+    pub path: &'a str,
+    pub kept_path: &'a str,
+    pub name: &'a str,
     pub size: u64,
 }
 
+/// This struct holds the analytical rollup of the findings ('--report summary').
+///
+struct SummaryReport<'a> {
+    pub groups: usize,
+    pub files: usize,
+    pub duplicated_bytes: u64,
+    pub reclaimable_bytes: u64,
+    pub dedupe_ratio: f64,
+    pub top_offenders: Vec<&'a DuplicateFile>,
+}
+
+/// This struct holds the duplicates shared by two directories ('--by-dir-pairs').
+///
+struct DirPair<'a> {
+    pub first: &'a str,
+    pub second: &'a str,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// The magic number starting a zstd frame, to recognize a compressed report whatever its extension.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// The number of groups listed as top offenders in the summary report.
+const TOP_OFFENDERS: usize = 10;
+
+/// This enum is used to determine where the serialized findings are written.
+///
+#[derive(Clone, Copy)]
+enum OutputTarget<'a> {
+    Stdout,
+    File(&'a str),
+    StdoutAndFile(&'a str),
+}
+
+/// This struct is used to determine how an output file is written.
+///
+#[derive(Clone, Copy, Default)]
+struct FileOptions<'a> {
+    temp_dir: Option<&'a str>,
+    compress: bool,
+}
+
 /// This function is the scheduler for exporting findings.
 ///
 /// # Arguments
 ///
 /// * `duplicates` - Array of DuplicateFile containing the findings.
+/// * `warnings` - The warnings raised during the search, reported in the standard, JSON and XML outputs.
 /// * `config` - The FindingConfig struct with the user's configuration.
 ///
 /// # Returns
 ///
 /// The result of the export findings scheduler, DeepFinderError otherwise.
 ///
-pub fn export_findings_scheduler(duplicates: &[DuplicateFile], config: &FindingConfig) -> Result<(), DeepFinderError> {
+pub fn export_findings_scheduler(duplicates: &[DuplicateFile], warnings: &[Warning], config: &FindingConfig) -> Result<(), DeepFinderError> {
+    // Nothing is displayed nor written when there is nothing to act on, the warnings of a text output are still displayed on stderr.
+    if config.quiet_if_empty && duplicates.is_empty() {
+        if !config.quiet && (config.summary_report || config.by_dir_pairs || config.output == CliOutput::Standard) {
+            eprint!("{}", format_warnings(warnings));
+        }
+        return Ok(());
+    }
+
+    if config.summary_report {
+        print!("{}", format_summary_report(&build_summary_report(duplicates)));
+        print!("{}", format_warnings(warnings));
+        return Ok(());
+    }
+
+    if config.by_dir_pairs {
+        print!("{}", format_dir_pairs(&build_dir_pairs(duplicates)));
+        print!("{}", format_warnings(warnings));
+        return Ok(());
+    }
+
+    if config.json_tree {
+        let (_, target): (&str, OutputTarget) = output_format_and_target(&config.output);
+        return json_tree_display(duplicates, warnings, target, output_file_options(config));
+    }
+
+    match &config.output {
+        CliOutput::Standard => { simple_display(duplicates, warnings, config, config.format_template.as_deref()); Ok(()) },
+        CliOutput::JsonStdin => json_display(duplicates, warnings, OutputTarget::Stdout, output_file_options(config), config.include_hashes_in_output, config.max_paths_per_group),
+        CliOutput::CsvStdin => csv_display(duplicates, OutputTarget::Stdout, output_file_options(config), config.include_hashes_in_output, config.csv_style, config.max_paths_per_group),
+        CliOutput::XmlStdin => xml_display(duplicates, warnings, OutputTarget::Stdout, output_file_options(config), config.include_hashes_in_output, config.xml_style, config.max_paths_per_group),
+        CliOutput::JsonFile(path) => json_display(duplicates, warnings, OutputTarget::File(path), output_file_options(config), config.include_hashes_in_output, config.max_paths_per_group),
+        CliOutput::CsvFile(path) => csv_display(duplicates, OutputTarget::File(path), output_file_options(config), config.include_hashes_in_output, config.csv_style, config.max_paths_per_group),
+        CliOutput::XmlFile(path) => xml_display(duplicates, warnings, OutputTarget::File(path), output_file_options(config), config.include_hashes_in_output, config.xml_style, config.max_paths_per_group),
+        CliOutput::JsonStdinAndFile(path) => json_display(duplicates, warnings, OutputTarget::StdoutAndFile(path), output_file_options(config), config.include_hashes_in_output, config.max_paths_per_group),
+        CliOutput::CsvStdinAndFile(path) => csv_display(duplicates, OutputTarget::StdoutAndFile(path), output_file_options(config), config.include_hashes_in_output, config.csv_style, config.max_paths_per_group),
+        CliOutput::XmlStdinAndFile(path) => xml_display(duplicates, warnings, OutputTarget::StdoutAndFile(path), output_file_options(config), config.include_hashes_in_output, config.xml_style, config.max_paths_per_group),
+    }?;
+
+    let reclaimable_size: u64 = duplicates.iter().map(DuplicateFile::reclaimable_size).sum();
+    let summary: String = format!("Wrote {} duplicate groups ({} reclaimable)", duplicates.len(), format_size(reclaimable_size));
+    write_summary(&summary, output_format_and_target(&config.output).1, config.quiet, &mut io::stderr())
+}
+
+/// This function is responsible for writing the duplicates confirmed so far to the output file ('--checkpoint-interval').
+/// The output file is overwritten in its format, nothing is displayed so the final results are the only ones on stdout.
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile containing the partial findings.
+/// * `config` - The FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// The result of the checkpoint, DeepFinderError otherwise.
+///
+pub fn export_checkpoint(duplicates: &[DuplicateFile], config: &FindingConfig) -> Result<(), DeepFinderError> {
+    let file_options: FileOptions = output_file_options(config);
     match &config.output {
-        CliOutput::Standard => { simple_display(duplicates, config.include_hashes_in_output); Ok(()) },
-        CliOutput::JsonStdin => json_display(duplicates, None, config.include_hashes_in_output),
-        CliOutput::CsvStdin => csv_display(duplicates, None, config.include_hashes_in_output),
-        CliOutput::XmlStdin => xml_display(duplicates, None, config.include_hashes_in_output),
-        CliOutput::JsonFile(path) => json_display(duplicates, Some(path), config.include_hashes_in_output),
-        CliOutput::CsvFile(path) => csv_display(duplicates, Some(path), config.include_hashes_in_output),
-        CliOutput::XmlFile(path) => xml_display(duplicates, Some(path), config.include_hashes_in_output),
+        CliOutput::JsonFile(path) | CliOutput::JsonStdinAndFile(path) => json_display(duplicates, &[], OutputTarget::File(path), file_options, config.include_hashes_in_output, config.max_paths_per_group),
+        CliOutput::CsvFile(path) | CliOutput::CsvStdinAndFile(path) => csv_display(duplicates, OutputTarget::File(path), file_options, config.include_hashes_in_output, config.csv_style, config.max_paths_per_group),
+        CliOutput::XmlFile(path) | CliOutput::XmlStdinAndFile(path) => xml_display(duplicates, &[], OutputTarget::File(path), file_options, config.include_hashes_in_output, config.xml_style, config.max_paths_per_group),
+        CliOutput::Standard | CliOutput::CsvStdin | CliOutput::JsonStdin | CliOutput::XmlStdin => Ok(()),
+    }
+}
+
+/// This function is responsible for loading the findings from an existing JSON report.
+/// The report must have been generated by DeepFinder (with the -j or -J arguments), it can be compressed with zstd.
+///
+/// # Arguments
+///
+/// * `path` - The path of the JSON report to load.
+///
+/// # Returns
+///
+/// A vector of DuplicateFile containing the findings of the report, SystemError otherwise.
+///
+pub fn load_json_report(path: &str) -> Result<Vec<DuplicateFile>, SystemError> {
+    let mut json_data: Vec<u8> = fs::read(path).map_err(|e| SystemError::UnableToReadFile(path.to_string(), e))?;
+    if json_data.starts_with(&ZSTD_MAGIC) {
+        json_data = zstd::decode_all(json_data.as_slice()).map_err(|e| SystemError::UnableToDeserialize("zstd".to_string(), e.to_string()))?;
+    }
+
+    // The reports written before the warnings were added to the JSON output are a bare array of groups.
+    let mut report: Value = serde_json::from_slice(&json_data).map_err(|e| SystemError::UnableToDeserialize("json".to_string(), e.to_string()))?;
+    let groups: Value = match report.get_mut("duplicates") {
+        Some(duplicates) => duplicates.take(),
+        None => report,
+    };
+    serde_json::from_value(groups).map_err(|e| SystemError::UnableToDeserialize("json".to_string(), e.to_string()))
+}
+
+/// This function is responsible for saving the list of the files found by a scan, so the same scan can be replayed later.
+/// The list is saved as a JSON array, so any path (even with a line break) is kept as is.
+///
+/// # Arguments
+///
+/// * `file_paths` - The paths of the files found by the scan, in their order.
+/// * `path` - The path of the file list to write.
+/// * `temp_dir` - The directory of the temporary file written before the file list, None to write it next to the file list.
+///
+/// # Returns
+///
+/// Ok if the file list has been written, DeepFinderError otherwise.
+///
+pub fn save_filelist(file_paths: &[String], path: &str, temp_dir: Option<&str>) -> Result<(), DeepFinderError> {
+    let filelist_data: String = serde_json::to_string(file_paths)
+        .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("json".to_string(), e.to_string())))?;
+    write_output(&filelist_data, OutputTarget::File(path), FileOptions { temp_dir, ..Default::default() }, &mut io::stdout())
+}
+
+/// This function is responsible for loading a list of files saved by a previous scan ('--record-filelist').
+///
+/// # Arguments
+///
+/// * `path` - The path of the file list to load.
+///
+/// # Returns
+///
+/// The paths of the files, in their recorded order, SystemError otherwise.
+///
+pub fn load_filelist(path: &str) -> Result<Vec<String>, SystemError> {
+    let filelist_data: Vec<u8> = fs::read(path).map_err(|e| SystemError::UnableToReadFile(path.to_string(), e))?;
+    serde_json::from_slice(&filelist_data).map_err(|e| SystemError::UnableToDeserialize("json".to_string(), e.to_string()))
+}
+
+/// This function displays the findings in a simple text format, or rendered with the user's template.
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile structs containing the findings.
+/// * `warnings` - The warnings raised during the search, displayed in a trailing section.
+/// * `config` - The FindingConfig struct with the user's configuration (checksums, colors, paths per group and relative times).
+/// * `template` - The template of the lines (see FORMAT_PLACEHOLDERS), None for the default format.
+///
+fn simple_display(duplicates: &[DuplicateFile], warnings: &[Warning], config: &FindingConfig, template: Option<&str>) {
+    let max_paths: Option<usize> = config.max_paths_per_group;
+    match template {
+        Some(template) => print!("{}", format_template_display(duplicates, template, max_paths)),
+        None => {
+            let now: Option<SystemTime> = config.relative_times.then(SystemTime::now);
+            print!("{}", format_simple_display(duplicates, config.include_hashes_in_output, config.color, max_paths, now));
+        }
+    }
+    print!("{}", format_warnings(warnings));
+}
+
+/// This function formats the warnings raised during the search, as the trailing section of the standard output.
+///
+/// # Arguments
+///
+/// * `warnings` - The warnings to format.
+///
+/// # Returns
+///
+/// The formatted section, empty if there isn't any warning.
+///
+fn format_warnings(warnings: &[Warning]) -> String {
+    if warnings.is_empty() {
+        return String::new();
+    }
+
+    warnings.iter().fold(format!("\nWarnings ({}):\n", warnings.len()), |output, warning| output + &format!("  [{}] {}\n", warning.kind, warning.detail))
+}
+
+/// This function formats the empty directories found by the traversal ('--report-empty-dirs'), as a section of the standard output.
+///
+/// # Arguments
+///
+/// * `empty_dirs` - The empty directories, sorted by path.
+///
+/// # Returns
+///
+/// The formatted section, each directory being "immediate" (no entry at all) or "recursive" (only empty directories).
+///
+pub fn format_empty_dirs(empty_dirs: &[EmptyDir]) -> String {
+    if empty_dirs.is_empty() {
+        return "\nNo empty directory found.\n".to_string();
+    }
+
+    empty_dirs.iter().fold(format!("\nEmpty directories ({}):\n", empty_dirs.len()), |output, empty_dir| {
+        output + &format!("  [{}] {}\n", if empty_dir.recursive { "recursive" } else { "immediate" }, empty_dir.path)
+    })
+}
+
+/// This function formats the findings with the user's template, one line per group.
+/// If the template contains the "{path}" placeholder, it is rendered once per path of each group instead,
+/// followed by a "... and N more" line when some paths are hidden.
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile structs containing the findings.
+/// * `template` - The template of the lines, its placeholders have been checked when parsing the arguments.
+/// * `max_paths` - The maximum number of paths rendered per group, None for all of them.
+///
+/// # Returns
+///
+/// The rendered lines, empty if there isn't any duplicate.
+///
+fn format_template_display(duplicates: &[DuplicateFile], template: &str, max_paths: Option<usize>) -> String {
+    let mut output: String = String::new();
+    for duplicate in duplicates {
+        let (count, size, wasted): (String, String, String) =
+            (duplicate.paths.len().to_string(), duplicate.size.to_string(), duplicate.reclaimable_size().to_string());
+        let mut values: Vec<(&str, &str)> = vec![("name", &duplicate.name), ("count", &count), ("size", &size), ("wasted", &wasted)];
+
+        if template_placeholders(template).unwrap_or_default().contains(&"path") {
+            let (paths, hidden_paths): (Vec<&str>, usize) = displayed_paths(&duplicate.paths, max_paths);
+            for path in paths {
+                values.push(("path", path));
+                output += &render_template(template, &values);
+                output += "\n";
+                values.pop();
+            }
+            if hidden_paths > 0 {
+                output += &format!("... and {hidden_paths} more\n");
+            }
+        } else {
+            output += &render_template(template, &values);
+            output += "\n";
+        }
+    }
+
+    output
+}
+
+/// The placeholders that can be used in a template of the standard output.
+pub const FORMAT_PLACEHOLDERS: [&str; 5] = ["name", "count", "size", "path", "wasted"];
+
+/// This function is responsible for listing the placeholders of a template, the escaped braces ("{{" and "}}") being skipped.
+///
+/// # Arguments
+///
+/// * `template` - The template to read.
+///
+/// # Returns
+///
+/// The names of the placeholders, in their order, an error message if a brace is unmatched.
+///
+pub fn template_placeholders(template: &str) -> Result<Vec<&str>, String> {
+    let mut placeholders: Vec<&str> = Vec::new();
+    let mut rest: &str = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            rest = &rest[2..];
+        } else if rest.starts_with('}') {
+            return Err("unmatched '}', use '}}' for a literal brace".to_string());
+        } else {
+            let Some(end) = rest.find('}') else { return Err("unclosed placeholder, a '}' is missing (use '{{' for a literal brace)".to_string()) };
+            placeholders.push(&rest[1..end]);
+            rest = &rest[end + 1..];
+        }
+    }
+
+    Ok(placeholders)
+}
+
+/// This function is responsible for replacing the placeholders of a template (e.g. "{name}") by their values.
+/// The template is read once, so a value containing a placeholder is never replaced itself.
+/// The escaped braces "{{" and "}}" are rendered as "{" and "}".
+///
+/// # Arguments
+///
+/// * `template` - The template to render.
+/// * `values` - The values of the placeholders, by name.
+///
+/// # Returns
+///
+/// The rendered template, the unknown placeholders being kept as is.
+///
+fn render_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut output: String = String::new();
+    let mut rest: &str = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        output += &rest[..start];
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            output += &rest[..1];
+            rest = &rest[2..];
+            continue;
+        }
+
+        let placeholder: Option<(&str, usize)> = rest.strip_prefix('{').and_then(|_| rest.find('}')).map(|end| (&rest[1..end], end));
+        match placeholder.and_then(|(name, end)| values.iter().find(|(n, _)| *n == name).map(|(_, value)| (*value, end))) {
+            Some((value, end)) => {
+                output += value;
+                rest = &rest[end + 1..];
+            }
+            None => {
+                output += &rest[..1];
+                rest = &rest[1..];
+            }
+        }
     }
+
+    output + rest
 }
 
-/// This function displays the findings in a simple text format.
+/// This function formats the findings in a simple text format.
+/// The occurrences and the checksums are aligned, and the colors are ANSI escape codes (only used if `color` is true).
 ///
 /// # Arguments
 ///
 /// * `duplicates` - Array of DuplicateFile structs containing the findings.
+/// * `include_hashes` - A boolean that indicates if the checksums should be displayed or not.
+/// * `color` - A boolean that indicates if the output should be colorized or not.
+/// * `max_paths` - The maximum number of paths displayed per group, None for all of them.
+/// * `now` - The current time, to display the modification time of each path relative to it. None to not display them.
+///
+/// # Returns
 ///
-fn simple_display(duplicates: &[DuplicateFile], include_hashes: bool) {
+/// The formatted findings.
+///
+fn format_simple_display(duplicates: &[DuplicateFile], include_hashes: bool, color: bool, max_paths: Option<usize>, now: Option<SystemTime>) -> String {
+    let paint = |text: &str, style: &str| if color { format!("\x1b[{style}m{text}\x1b[0m") } else { text.to_string() };
+
     if duplicates.is_empty() {
-        println!("No duplicate files found.");
-        return;
+        return "No duplicate files found.\n".to_string();
     }
 
-    println!("{} duplicate files found:", duplicates.len());
+    let occurrences_width: usize = duplicates.iter().map(|d| d.paths.len().to_string().len()).max().unwrap_or(1);
+    let mut output: String = format!("{} duplicate files found:\n", duplicates.len());
     for duplicate in duplicates {
-        println!("Duplicate file found: {}", duplicate.name);
-        duplicate.paths.iter().for_each(|path| println!(" - {path}"));
-        
-        println!("Occurrences: {}", duplicate.paths.len());
+        output += &format!("Duplicate file found: {}\n", paint(&duplicate.name, BOLD_CYAN));
+        let (paths, hidden_paths): (Vec<&str>, usize) = displayed_paths(&duplicate.paths, max_paths);
+        for path in paths {
+            let modified: Option<SystemTime> = now.and_then(|_| fs::metadata(path).and_then(|m| m.modified()).ok());
+            match (now, modified) {
+                (Some(now), Some(modified)) => output += &format!(" - {} (modified {})\n", paint(path, DIM), humanize_age(modified, now)),
+                _ => output += &format!(" - {}\n", paint(path, DIM)),
+            }
+        }
+        if hidden_paths > 0 {
+            output += &format!(" ... and {hidden_paths} more\n");
+        }
+
+        output += &format!("Occurrences: {:>occurrences_width$}\n", duplicate.paths.len());
         if include_hashes && let Some(checksums) = &duplicate.checksums {
-            for c in checksums { println!("Checksum ({0}) : {1}", c.0, c.1); }
+            let algorithm_width: usize = checksums.keys().map(String::len).max().unwrap_or(0);
+            for c in checksums { output += &format!("Checksum ({:<algorithm_width$}) : {1}\n", c.0, c.1); }
+        }
+        output += "\n";
+    }
+
+    let reclaimable_size: u64 = duplicates.iter().map(DuplicateFile::reclaimable_size).sum();
+    output += &format!("Reclaimable space: {}\n", paint(&format_size(reclaimable_size), BOLD_GREEN));
+    output
+}
+
+/// This function is responsible for describing the age of a file relative to the current time (e.g. "3 hours ago").
+/// A time after the current time (e.g. a clock skew between machines) is described as being in the future.
+///
+/// # Arguments
+///
+/// * `time` - The time to describe (e.g. the modification time of a file).
+/// * `now` - The current time.
+///
+/// # Returns
+///
+/// The relative age of the time.
+///
+fn humanize_age(time: SystemTime, now: SystemTime) -> String {
+    match now.duration_since(time) {
+        Ok(elapsed) => humanize_duration(elapsed),
+        Err(_) => "in the future".to_string(),
+    }
+}
+
+/// This function is responsible for formatting a duration in the past as a human-relative string, in its largest unit.
+///
+/// # Arguments
+///
+/// * `elapsed` - The duration elapsed since the event.
+///
+/// # Returns
+///
+/// The duration in its largest unit (e.g. "2 months ago"), "just now" under a minute.
+///
+fn humanize_duration(elapsed: Duration) -> String {
+    const UNITS: [(&str, u64); 6] = [("year", 365 * 86_400), ("month", 30 * 86_400), ("week", 7 * 86_400), ("day", 86_400), ("hour", 3_600), ("minute", 60)];
+
+    let seconds: u64 = elapsed.as_secs();
+    UNITS
+        .iter()
+        .find(|(_, unit_seconds)| seconds >= *unit_seconds)
+        .map_or_else(|| "just now".to_string(), |(unit, unit_seconds)| {
+            let count: u64 = seconds / unit_seconds;
+            format!("{count} {unit}{} ago", if count > 1 { "s" } else { "" })
+        })
+}
+
+/// This function is responsible for building the analytical rollup of the findings.
+/// The dedupe ratio is the size of all the duplicated files divided by the size they would take with a single copy of each group.
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile structs containing the findings.
+///
+/// # Returns
+///
+/// The SummaryReport, its top offenders being the groups wasting the most space (ties in the order of the findings).
+///
+#[allow(clippy::cast_precision_loss)]
+fn build_summary_report(duplicates: &[DuplicateFile]) -> SummaryReport<'_> {
+    let duplicated_bytes: u64 = duplicates.iter().map(DuplicateFile::total_size).sum();
+    let reclaimable_bytes: u64 = duplicates.iter().map(DuplicateFile::reclaimable_size).sum();
+    let unique_bytes: u64 = duplicated_bytes - reclaimable_bytes;
+
+    let mut top_offenders: Vec<&DuplicateFile> = duplicates.iter().collect();
+    top_offenders.sort_by_key(|d| Reverse(d.reclaimable_size()));
+    top_offenders.truncate(TOP_OFFENDERS);
+
+    SummaryReport {
+        groups: duplicates.len(),
+        files: duplicates.iter().map(|d| d.paths.len()).sum(),
+        duplicated_bytes,
+        reclaimable_bytes,
+        dedupe_ratio: if unique_bytes == 0 { 1.0 } else { duplicated_bytes as f64 / unique_bytes as f64 },
+        top_offenders,
+    }
+}
+
+/// This function formats the summary report in a simple text format.
+///
+/// # Arguments
+///
+/// * `report` - The SummaryReport to format.
+///
+/// # Returns
+///
+/// The formatted summary report.
+///
+fn format_summary_report(report: &SummaryReport) -> String {
+    let mut output: String = format!("Duplicate groups: {}\n", report.groups);
+    output += &format!("Duplicated files: {}\n", report.files);
+    output += &format!("Duplicated size: {}\n", format_size(report.duplicated_bytes));
+    output += &format!("Reclaimable space: {}\n", format_size(report.reclaimable_bytes));
+    output += &format!("Dedupe ratio: {:.2}:1\n", report.dedupe_ratio);
+
+    if !report.top_offenders.is_empty() {
+        output += &format!("\nTop {} groups by reclaimable space:\n", report.top_offenders.len());
+        for (rank, duplicate) in report.top_offenders.iter().enumerate() {
+            output += &format!("{:>2}. {} ({} x {}, {} reclaimable)\n", rank + 1, duplicate.name, duplicate.paths.len(), format_size(duplicate.size), format_size(duplicate.reclaimable_size()));
         }
-        println!();
     }
+
+    output
 }
 
-/// This function displays the findings in JSON format.
+/// This function is responsible for aggregating the duplicates per unordered pair of parent directories.
+/// A group counts once for each pair of distinct directories holding one of its copies.
 ///
 /// # Arguments
 ///
 /// * `duplicates` - Array of DuplicateFile structs containing the findings.
-/// * `path` - An optional path to save the JSON output.
 ///
 /// # Returns
 ///
-/// Result<(), DeepFinderError> - Returns Ok if the display (and saving if a path was specified) is successful, DeepFinderError otherwise.
+/// The pairs of directories, sorted by shared size (then by directories).
 ///
-fn json_display(duplicates: &[DuplicateFile], path: Option<&str>, include_hashes: bool) -> Result<(), DeepFinderError> {
-    let json_values: Vec<DuplicateFileSerialized> = duplicates.iter().enumerate().map(|(idx, d)| {
-        DuplicateFileSerialized {
-            index: idx + 1,
-            paths: &d.paths,
-            name: &d.name,
-            checksums: if include_hashes { d.checksums.as_ref() } else { None },
-            size: d.size,
+fn build_dir_pairs(duplicates: &[DuplicateFile]) -> Vec<DirPair<'_>> {
+    let mut pairs: BTreeMap<(&str, &str), (usize, u64)> = BTreeMap::new();
+    for duplicate in duplicates {
+        let directories: Vec<&str> = duplicate
+            .paths
+            .iter()
+            .map(|path| Path::new(path).parent().and_then(Path::to_str).unwrap_or(""))
+            .collect::<BTreeSet<&str>>()
+            .into_iter()
+            .collect();
+
+        for (i, first) in directories.iter().enumerate() {
+            for second in &directories[i + 1..] {
+                let (files, bytes): &mut (usize, u64) = pairs.entry((first, second)).or_default();
+                *files += 1;
+                *bytes = bytes.saturating_add(duplicate.size);
+            }
         }
-    }).collect();
+    }
 
-    let json_data: String = serde_json::to_string(&json_values)
-        .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("json".to_string(), e.to_string())))?;
-    
-    if let Some(file_path) = path {
-        fs::write(file_path, json_data)
-            .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToCreateFile(file_path.to_string(), e.to_string())))?;
-    } else {
-        println!("{}", json_data);
+    let mut dir_pairs: Vec<DirPair> = pairs.into_iter().map(|((first, second), (files, bytes))| DirPair { first, second, files, bytes }).collect();
+    dir_pairs.sort_by_key(|pair| Reverse(pair.bytes));
+    dir_pairs
+}
+
+/// This function formats the pairs of directories in a simple text format.
+///
+/// # Arguments
+///
+/// * `pairs` - The pairs of directories to format.
+///
+/// # Returns
+///
+/// The formatted pairs of directories.
+///
+fn format_dir_pairs(pairs: &[DirPair]) -> String {
+    if pairs.is_empty() {
+        return "No pair of directories shares a duplicate.\n".to_string();
+    }
+
+    pairs
+        .iter()
+        .map(|pair| format!("{} <-> {}: {} duplicate files ({})\n", pair.first, pair.second, pair.files, format_size(pair.bytes)))
+        .collect()
+}
+
+/// This function is responsible for selecting the paths of a group to display, sorted in the alphabetical order.
+///
+/// # Arguments
+///
+/// * `paths` - The paths of the group.
+/// * `max_paths` - The maximum number of paths to display, None for all of them.
+///
+/// # Returns
+///
+/// The sorted paths to display, and the number of paths that are not displayed.
+///
+fn displayed_paths(paths: &HashSet<String>, max_paths: Option<usize>) -> (Vec<&str>, usize) {
+    let mut sorted_paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    sorted_paths.sort_unstable();
+
+    let hidden_paths: usize = max_paths.map_or(0, |max| sorted_paths.len().saturating_sub(max));
+    sorted_paths.truncate(sorted_paths.len() - hidden_paths);
+    (sorted_paths, hidden_paths)
+}
+
+/// This function is responsible for computing the stable identifier of a duplicate group.
+/// The paths are sorted before being hashed, so the identifier doesn't depend on the order of the search.
+///
+/// # Arguments
+///
+/// * `paths` - The paths of the group.
+///
+/// # Returns
+///
+/// The first 16 hexadecimal characters of the SHA-256 checksum of the sorted paths.
+///
+fn group_id(paths: &HashSet<String>) -> String {
+    let mut sorted_paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    sorted_paths.sort_unstable();
+
+    let mut hasher: Sha256 = Sha256::new();
+    for path in sorted_paths {
+        // The separator avoids collisions between ["a", "bc"] and ["ab", "c"].
+        hasher.update(path.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())[..16].to_string()
+}
+
+/// This function formats a size in bytes in a human readable way, with binary units.
+///
+/// # Arguments
+///
+/// * `size` - The size in bytes.
+///
+/// # Returns
+///
+/// The formatted size (e.g. "512 B", "1.5 MiB").
+///
+#[allow(clippy::cast_precision_loss)]
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if size < 1024 {
+        return format!("{size} B");
+    }
+
+    let mut value: f64 = size as f64 / 1024.0;
+    let mut unit: usize = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// This function displays the findings in JSON format, as an object with the "duplicates" and "warnings" arrays.
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile structs containing the findings.
+/// * `warnings` - The warnings raised during the search.
+/// * `target` - Where to write the JSON output (stdin, a file or both).
+/// * `file_options` - How the output file is written (temporary directory and compression).
+/// * `max_paths` - The maximum number of paths serialized per group, None for all of them.
+///
+/// # Returns
+///
+/// Result<(), DeepFinderError> - Returns Ok if the display (and saving if a path was specified) is successful, DeepFinderError otherwise.
+///
+fn json_display(duplicates: &[DuplicateFile], warnings: &[Warning], target: OutputTarget, file_options: FileOptions, include_hashes: bool, max_paths: Option<usize>) -> Result<(), DeepFinderError> {
+    // The groups are serialized one by one while being written, the whole report is never held in memory.
+    stream_output(target, file_options, estimate_json_size(duplicates), &mut io::stdout(), |writer| {
+        let json_values = duplicates.iter().enumerate().map(|(idx, d)| DuplicateFileSerialized::new(idx + 1, d, include_hashes, max_paths));
+        writer.write_all(b"{\"duplicates\":")?;
+        write_json_array(writer, json_values)?;
+        writer.write_all(b",\"warnings\":")?;
+        serde_json::to_writer(&mut *writer, warnings)?;
+        writer.write_all(b"}")
+    })
+}
+
+/// The XML Schema (XSD) of the XML output ('--export-schema xml'), matching the serialization of DuplicateFileSerialized and Warning.
+/// The children of "checksums" are named after the hash algorithms, so any element is accepted there.
+const XML_SCHEMA: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:element name="duplicate_files">
+    <xs:complexType>
+      <xs:sequence>
+        <xs:element name="duplicate_file" minOccurs="0" maxOccurs="unbounded">
+          <xs:complexType>
+            <xs:sequence>
+              <xs:element name="index" type="xs:positiveInteger"/>
+              <xs:element name="group_id" type="xs:string"/>
+              <xs:element name="paths" type="xs:string" maxOccurs="unbounded"/>
+              <xs:element name="occurrences" type="xs:nonNegativeInteger" minOccurs="0"/>
+              <xs:element name="paths_truncated" type="xs:boolean" minOccurs="0"/>
+              <xs:element name="name" type="xs:string"/>
+              <xs:element name="checksums" minOccurs="0">
+                <xs:complexType>
+                  <xs:sequence>
+                    <xs:any processContents="skip" minOccurs="0" maxOccurs="unbounded"/>
+                  </xs:sequence>
+                </xs:complexType>
+              </xs:element>
+              <xs:element name="size" type="xs:nonNegativeInteger"/>
+            </xs:sequence>
+          </xs:complexType>
+        </xs:element>
+        <xs:element name="warning" minOccurs="0" maxOccurs="unbounded">
+          <xs:complexType>
+            <xs:sequence>
+              <xs:element name="kind" type="xs:string"/>
+              <xs:element name="detail" type="xs:string"/>
+            </xs:sequence>
+          </xs:complexType>
+        </xs:element>
+      </xs:sequence>
+    </xs:complexType>
+  </xs:element>
+</xs:schema>"#;
+
+/// This function is responsible for building the schema of the JSON or XML output ('--export-schema').
+/// The JSON Schema is derived from the types written by json_display, the XSD describes the document written by xml_display.
+///
+/// # Arguments
+///
+/// * `format` - A string slice that holds the format of the output: "json" or "xml".
+///
+/// # Returns
+///
+/// The JSON Schema (draft 2020-12) or the XSD of the output, as a string.
+///
+pub fn output_schema(format: &str) -> String {
+    if format == "xml" {
+        return XML_SCHEMA.to_string();
+    }
+
+    // The optional fields are skipped while serializing, so the schema describes the serialized form.
+    let schema: schemars::Schema = SchemaSettings::draft2020_12().for_serialize().into_generator().into_root_schema_for::<JsonReport>();
+    serde_json::to_string_pretty(&schema).unwrap_or_default()
+}
+
+/// This function is responsible for displaying and/or saving the findings in JSON, as a tree of nested folders.
+/// The JSON object holds the "tree" and the "warnings" raised by the search.
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile structs containing the findings.
+/// * `warnings` - The warnings raised during the search.
+/// * `target` - Where to write the JSON output (stdin, a file or both).
+/// * `file_options` - How the output file is written (temporary directory and compression).
+///
+/// # Returns
+///
+/// Result<(), DeepFinderError> - Returns Ok if the display (and saving if a path was specified) is successful, DeepFinderError otherwise.
+///
+fn json_tree_display(duplicates: &[DuplicateFile], warnings: &[Warning], target: OutputTarget, file_options: FileOptions) -> Result<(), DeepFinderError> {
+    let tree: Map<String, Value> = build_json_tree(duplicates);
+    stream_output(target, file_options, estimate_json_size(duplicates), &mut io::stdout(), |writer| {
+        writer.write_all(b"{\"tree\":")?;
+        serde_json::to_writer(&mut *writer, &tree)?;
+        writer.write_all(b",\"warnings\":")?;
+        serde_json::to_writer(&mut *writer, warnings)?;
+        writer.write_all(b"}")
+    })
+}
+
+/// This function is responsible for estimating the size of the findings serialized in JSON, to check the free space before streaming them.
+/// The estimate counts the paths and a fixed overhead per group (identifier, name, size and checksums).
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile structs containing the findings.
+///
+/// # Returns
+///
+/// The estimated size of the JSON output, in bytes.
+///
+fn estimate_json_size(duplicates: &[DuplicateFile]) -> u64 {
+    const GROUP_OVERHEAD: u64 = 256;
+    duplicates
+        .iter()
+        .map(|d| GROUP_OVERHEAD + d.name.len() as u64 + d.paths.iter().map(|path| path.len() as u64 + 4).sum::<u64>())
+        .sum()
+}
+
+/// This function is responsible for building the tree of the duplicate files, with one nested JSON object per folder.
+/// Each leaf is a duplicate file, its value is the stable identifier of its group (e.g. {"home": {"a.txt": "<group id>"}}).
+/// A duplicate archive holding duplicate files is both a leaf and a folder: it is a folder whose "" key holds its group identifier.
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile structs containing the findings.
+///
+/// # Returns
+///
+/// The root folder of the tree, its keys (and the keys of every folder) are sorted.
+///
+fn build_json_tree(duplicates: &[DuplicateFile]) -> Map<String, Value> {
+    let mut tree: Map<String, Value> = Map::new();
+    for duplicate in duplicates {
+        let id: String = group_id(&duplicate.paths);
+        'paths: for path in &duplicate.paths {
+            // The root and the current folder aren't folders of the tree, a Windows prefix ("C:") is the top folder.
+            let mut components: Vec<String> = Path::new(path)
+                .components()
+                .filter(|component| !matches!(component, Component::RootDir | Component::CurDir))
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .collect();
+            let Some(file_name) = components.pop() else { continue };
+
+            let mut folder: &mut Map<String, Value> = &mut tree;
+            for component in components {
+                let entry: &mut Value = folder.entry(component).or_insert_with(|| Value::Object(Map::new()));
+                if entry.is_string() {
+                    *entry = Value::Object(Map::from_iter([(String::new(), entry.take())]));
+                }
+                let Some(sub_folder) = entry.as_object_mut() else { continue 'paths };
+                folder = sub_folder;
+            }
+
+            match folder.get_mut(&file_name) {
+                Some(Value::Object(sub_folder)) => {
+                    sub_folder.insert(String::new(), Value::String(id.clone()));
+                }
+                _ => {
+                    folder.insert(file_name, Value::String(id.clone()));
+                }
+            }
+        }
+    }
+
+    tree
+}
+
+/// This function is responsible for writing items as a JSON array, serialized one after the other.
+///
+/// # Arguments
+///
+/// * `writer` - Where to write the JSON array, it should be buffered.
+/// * `items` - The items to serialize.
+///
+/// # Returns
+///
+/// Ok if the array has been written, an I/O error otherwise (serialization errors included).
+///
+fn write_json_array<T: Serialize>(writer: &mut dyn Write, items: impl Iterator<Item = T>) -> io::Result<()> {
+    writer.write_all(b"[")?;
+    for (idx, item) in items.enumerate() {
+        if idx > 0 {
+            writer.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut *writer, &item)?;
+    }
+    writer.write_all(b"]")
+}
+
+/// This function displays the findings in CSV format.
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile structs containing the findings.
+/// * `target` - Where to write the CSV output (stdin, a file or both).
+/// * `file_options` - How the output file is written (temporary directory and compression).
+/// * `style` - The delimiter, quoting style and checksums layout of the CSV output.
+/// * `max_paths` - The maximum number of paths exported per group, None for all of them.
+///
+/// # Returns
+///
+/// Result<(), DeepFinderError> - Returns Ok if the display (and saving if a path was specified) is successful, DeepFinderError otherwise.
+///
+fn csv_display(duplicates: &[DuplicateFile], target: OutputTarget, file_options: FileOptions, include_hashes: bool, style: CsvStyle, max_paths: Option<usize>) -> Result<(), DeepFinderError> {
+    // The algorithms are sorted, so the checksums columns (or the lines of the checksums cell) are always in the same order.
+    let algorithms: BTreeSet<&str> = duplicates.iter().filter_map(|d| d.checksums.as_ref()).flat_map(|checksums| checksums.keys().map(String::as_str)).collect();
+    let mut header: Vec<String> = ["Index", "Filename", "Paths", "Occurrences", "Size"].map(String::from).to_vec();
+    if include_hashes && style.hash_columns {
+        header.extend(algorithms.iter().map(|algorithm| algorithm.to_ascii_uppercase()));
+    } else if include_hashes {
+        header.push("Checksums".to_string());
+    }
+    header.push("Group ID".to_string());
+
+    // The truncated paths are replaced by an indicator, the occurrences column keeps the full count.
+    // The group ID is the last column, so the position of the existing columns doesn't change.
+    let joined_paths = |file: &DuplicateFile| {
+        let (paths, hidden_paths): (Vec<&str>, usize) = displayed_paths(&file.paths, max_paths);
+        let paths: String = paths.join("\n");
+        if hidden_paths > 0 { format!("{paths}\n... and {hidden_paths} more") } else { paths }
+    };
+
+    let mut wtr: csv::Writer<Vec<u8>> = build_csv_writer(style);
+    wtr.write_record(&header)
+        .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("csv".to_string(), e.to_string())))?;
+
+    for (idx, file) in duplicates.iter().enumerate() {
+        let mut record: Vec<String> = vec![(idx + 1).to_string(), file.name.clone(), joined_paths(file), file.paths.len().to_string(), file.size.to_string()];
+        if include_hashes && style.hash_columns {
+            record.extend(algorithms.iter().map(|algorithm| {
+                file.checksums.as_ref().and_then(|checksums| checksums.get(*algorithm)).map_or_else(|| "N/A".to_string(), Clone::clone)
+            }));
+        } else if include_hashes {
+            let checksums_str: String =  file.checksums.as_ref().map_or_else(
+                || "N/A".to_string(),
+                |checksums| {
+                    checksums.iter()
+                        .map(|(algo, checksum)| format!("{}:{}", algo, checksum))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                },
+            );
+            record.push(checksums_str);
+        }
+        record.push(group_id(&file.paths));
+
+        wtr.write_record(&record)
+            .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("csv".to_string(), e.to_string())))?;
+    }
+    
+    let csv_data: String = String::from_utf8(wtr.into_inner().unwrap_or_default())
+        .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("csv".to_string(), e.to_string())))?;
+
+    write_output(&csv_data, target, file_options, &mut io::stdout())
+}
+
+/// This function is responsible for building an in-memory CSV writer with the style chosen by the user.
+///
+/// # Arguments
+///
+/// * `style` - The delimiter and quoting style of the CSV output.
+///
+/// # Returns
+///
+/// The CSV writer, writing in a vector of bytes.
+///
+fn build_csv_writer(style: CsvStyle) -> csv::Writer<Vec<u8>> {
+    WriterBuilder::new()
+        .delimiter(style.delimiter)
+        .quote_style(if style.always_quote { QuoteStyle::Always } else { QuoteStyle::Necessary })
+        .from_writer(vec![])
+}
+
+/// This function displays the findings in XML format.
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile structs containing the findings.
+/// * `warnings` - The warnings raised during the search, serialized after the groups.
+/// * `target` - Where to write the XML output (stdin, a file or both).
+/// * `file_options` - How the output file is written (temporary directory and compression).
+/// * `style` - The style of the XML output (indentation and line endings).
+/// * `max_paths` - The maximum number of paths serialized per group, None for all of them.
+///
+/// # Returns
+///
+/// Result<(), DeepFinderError> - Returns Ok if the display (and saving if a path was specified) is successful, DeepFinderError otherwise.
+///
+fn xml_display(duplicates: &[DuplicateFile], warnings: &[Warning], target: OutputTarget, file_options: FileOptions, include_hashes: bool, style: XmlStyle, max_paths: Option<usize>) -> Result<(), DeepFinderError> {
+    #[derive(Serialize)]
+    #[serde(rename = "duplicate_files")]
+    struct DuplicateFilesWrapper<'a> {
+        #[serde(rename = "duplicate_file")]
+        files: Vec<DuplicateFileSerialized<'a>>,
+        #[serde(rename = "warning")]
+        warnings: &'a [Warning],
+    }
+
+    let xml_values: Vec<DuplicateFileSerialized> = duplicates.iter().enumerate().map(|(idx, d)| {
+        DuplicateFileSerialized::new(idx + 1, d, include_hashes, max_paths)
+    }).collect();
+    let wrapper: DuplicateFilesWrapper = DuplicateFilesWrapper { files: xml_values, warnings };
+    let emitter: EmitterConfig = EmitterConfig::new().perform_indent(style.indent).line_separator(if style.crlf { "\r\n" } else { "\n" });
+    let xml_data: String = SerdeXml::new()
+        .emitter(emitter)
+        .to_string(&wrapper)
+        .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("xml".to_string(), e.to_string())))?;
+    
+    write_output(&xml_data, target, file_options, &mut io::stdout())
+}
+
+/// This function is the scheduler for exporting the manifest, i.e. the checksums of every file.
+///
+/// # Arguments
+///
+/// * `files` - Array of VirtualFile containing the hashed files.
+/// * `config` - The FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// The result of the export manifest scheduler, DeepFinderError otherwise.
+///
+pub fn export_manifest_scheduler(files: &[VirtualFile], config: &FindingConfig) -> Result<(), DeepFinderError> {
+    let (format, target): (&str, OutputTarget) = output_format_and_target(&config.output);
+    let manifest_data: String = serialize_manifest(files, format, config.csv_style).map_err(DeepFinderError::SystemError)?;
+    write_output(&manifest_data, target, output_file_options(config), &mut io::stdout())?;
+    write_summary(&format!("Wrote the checksums of {} files", files.len()), target, config.quiet, &mut io::stderr())
+}
+
+/// This function is the scheduler for exporting the deletable paths, i.e. all the paths but the kept one of each group.
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile containing the findings.
+/// * `config` - The FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// The result of the export deletable scheduler, DeepFinderError otherwise.
+///
+pub fn export_deletable_scheduler(duplicates: &[DuplicateFile], config: &FindingConfig) -> Result<(), DeepFinderError> {
+    if config.quiet_if_empty && duplicates.is_empty() {
+        return Ok(());
+    }
+
+    let (format, target): (&str, OutputTarget) = output_format_and_target(&config.output);
+    let deletable_files: Vec<DeletableFile> = build_deletable_files(duplicates, config.keep_policy, &config.prefer_dirs);
+    let deletable_data: String = serialize_deletable(&deletable_files, format, config.csv_style).map_err(DeepFinderError::SystemError)?;
+    write_output(&deletable_data, target, output_file_options(config), &mut io::stdout())?;
+
+    let reclaimable_size: u64 = deletable_files.iter().map(|f| f.size).sum();
+    let summary: String = format!("Wrote {} deletable paths ({} reclaimable)", deletable_files.len(), format_size(reclaimable_size));
+    write_summary(&summary, target, config.quiet, &mut io::stderr())
+}
+
+/// This function is the scheduler for exporting a script deleting the deletable paths, so the user can review it before running it.
+/// The script is a PowerShell script on Windows, a POSIX shell script on the other platforms.
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile containing the findings.
+/// * `config` - The FindingConfig struct with the user's configuration.
+/// * `script_path` - The path of the script to write.
+///
+/// # Returns
+///
+/// The result of the export script scheduler, DeepFinderError otherwise.
+///
+pub fn export_script_scheduler(duplicates: &[DuplicateFile], config: &FindingConfig, script_path: &str) -> Result<(), DeepFinderError> {
+    if config.quiet_if_empty && duplicates.is_empty() {
+        return Ok(());
+    }
+
+    let deletable_files: Vec<DeletableFile> = build_deletable_files(duplicates, config.keep_policy, &config.prefer_dirs);
+    let script: String = build_delete_script(&deletable_files, cfg!(target_family = "windows"));
+    let target: OutputTarget = OutputTarget::File(script_path);
+    let file_options: FileOptions = FileOptions { temp_dir: config.temp_dir.as_deref(), ..Default::default() };
+    write_output(&script, target, file_options, &mut io::stdout())?;
+
+    let reclaimable_size: u64 = deletable_files.iter().map(|f| f.size).sum();
+    let summary: String = format!("Wrote a script deleting {} paths ({} reclaimable)", deletable_files.len(), format_size(reclaimable_size));
+    write_summary(&summary, target, config.quiet, &mut io::stderr())
+}
+
+/// This function is responsible for building the script deleting the deletable files, one command per file.
+/// The paths are single-quoted (the quotes they contain are escaped), so the spaces and the special characters are never interpreted.
+/// Each group starts with a comment naming its kept path, escaped so it can't end the comment.
+///
+/// # Arguments
+///
+/// * `files` - Array of DeletableFile to delete.
+/// * `powershell` - A boolean that indicates if the script is a PowerShell script (Remove-Item) or a POSIX shell script (rm).
+///
+/// # Returns
+///
+/// The script, with a header summarizing the reclaimable space.
+///
+fn build_delete_script(files: &[DeletableFile], powershell: bool) -> String {
+    // PowerShell also ends a single-quoted string with the typographic quotes, every quote is doubled to escape it.
+    let quote = |path: &str| -> String {
+        let escaped: String = if powershell {
+            path.chars().flat_map(|c| if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') { vec![c, c] } else { vec![c] }).collect()
+        } else {
+            path.replace('\'', r"'\''")
+        };
+        format!("'{escaped}'")
+    };
+
+    let reclaimable_size: u64 = files.iter().map(|f| f.size).sum();
+    let mut script: String = if powershell { String::new() } else { "#!/bin/sh\n".to_string() };
+    script += &format!("# Generated by DeepFinder, review this script before running it.\n# It deletes {} duplicate files, {} reclaimable.\n", files.len(), format_size(reclaimable_size));
+
+    let mut kept_path: Option<&str> = None;
+    for file in files {
+        if kept_path != Some(file.kept_path) {
+            kept_path = Some(file.kept_path);
+            script += &format!("\n# Kept: {}\n", file.kept_path.escape_debug());
+        }
+
+        script += &if powershell { format!("Remove-Item -LiteralPath {}\n", quote(file.path)) } else { format!("rm -- {}\n", quote(file.path)) };
+    }
+
+    script
+}
+
+/// This function is responsible for building the deletable files from the duplicate groups.
+/// The paths of each group are sorted, so the list is always the same for a given group.
+///
+/// # Arguments
+///
+/// * `duplicates` - Array of DuplicateFile containing the findings.
+/// * `policy` - The policy used to choose the path to keep in each group.
+/// * `prefer_dirs` - The preferred directories of the kept paths, from the highest priority to the lowest one.
+///
+/// # Returns
+///
+/// A vector of DeletableFile, one per path that can be deleted.
+///
+fn build_deletable_files<'a>(duplicates: &'a [DuplicateFile], policy: KeepPolicy, prefer_dirs: &[String]) -> Vec<DeletableFile<'a>> {
+    let mut deletable_files: Vec<DeletableFile> = Vec::new();
+    for duplicate in duplicates {
+        let Some(kept_path) = duplicate.kept_path(policy, prefer_dirs) else { continue };
+        let mut paths: Vec<&String> = duplicate.paths.iter().filter(|p| *p != kept_path).collect();
+        paths.sort();
+
+        deletable_files.extend(paths.into_iter().map(|path| DeletableFile {
+            path,
+            kept_path,
+            name: &duplicate.name,
+            size: duplicate.path_size(path),
+        }));
+    }
+    deletable_files
+}
+
+/// This function is responsible for serializing the deletable files in the given format.
+/// The standard format uses one "path<TAB>kept path" line per file, so the paths can be extracted with 'cut -f1'.
+///
+/// # Arguments
+///
+/// * `files` - Array of DeletableFile to serialize.
+/// * `format` - The format of the output ("standard", "json", "csv" or "xml").
+/// * `style` - The delimiter and quoting style of the CSV output.
+///
+/// # Returns
+///
+/// The serialized deletable files, SystemError otherwise.
+///
+fn serialize_deletable(files: &[DeletableFile], format: &str, style: CsvStyle) -> Result<String, SystemError> {
+    #[derive(Serialize)]
+    #[serde(rename = "deletable_files")]
+    struct DeletableFilesWrapper<'a> {
+        #[serde(rename = "deletable_file")]
+        files: &'a [DeletableFile<'a>],
+    }
+
+    match format {
+        "json" => serde_json::to_string(files).map_err(|e| SystemError::UnableToSerialize("json".to_string(), e.to_string())),
+        "xml" => serde_xml_rs::to_string(&DeletableFilesWrapper { files }).map_err(|e| SystemError::UnableToSerialize("xml".to_string(), e.to_string())),
+        "csv" => {
+            let mut wtr: csv::Writer<Vec<u8>> = build_csv_writer(style);
+            wtr.write_record(["Path", "Kept path", "Filename", "Size"])
+                .map_err(|e| SystemError::UnableToSerialize("csv".to_string(), e.to_string()))?;
+
+            for file in files {
+                wtr.write_record([file.path, file.kept_path, file.name, &file.size.to_string()])
+                    .map_err(|e| SystemError::UnableToSerialize("csv".to_string(), e.to_string()))?;
+            }
+
+            String::from_utf8(wtr.into_inner().unwrap_or_default()).map_err(|e| SystemError::UnableToSerialize("csv".to_string(), e.to_string()))
+        }
+        _ => Ok(files.iter().map(|file| format!("{}\t{}", file.path, file.kept_path)).collect::<Vec<String>>().join("\n")),
+    }
+}
+
+/// This function is responsible for getting the format and the target of the output chosen by the user.
+///
+/// # Arguments
+///
+/// * `output` - The output chosen by the user.
+///
+/// # Returns
+///
+/// A tuple with the format ("standard", "json", "csv" or "xml") and the target of the output.
+///
+fn output_format_and_target(output: &CliOutput) -> (&'static str, OutputTarget<'_>) {
+    match output {
+        CliOutput::Standard => ("standard", OutputTarget::Stdout),
+        CliOutput::JsonStdin => ("json", OutputTarget::Stdout),
+        CliOutput::CsvStdin => ("csv", OutputTarget::Stdout),
+        CliOutput::XmlStdin => ("xml", OutputTarget::Stdout),
+        CliOutput::JsonFile(path) => ("json", OutputTarget::File(path)),
+        CliOutput::CsvFile(path) => ("csv", OutputTarget::File(path)),
+        CliOutput::XmlFile(path) => ("xml", OutputTarget::File(path)),
+        CliOutput::JsonStdinAndFile(path) => ("json", OutputTarget::StdoutAndFile(path)),
+        CliOutput::CsvStdinAndFile(path) => ("csv", OutputTarget::StdoutAndFile(path)),
+        CliOutput::XmlStdinAndFile(path) => ("xml", OutputTarget::StdoutAndFile(path)),
+    }
+}
+
+/// This function is responsible for serializing the manifest in the given format.
+/// The standard format uses one "ALGORITHM (path) = checksum" line per checksum, like the BSD checksum tools.
+/// A symbolic link listed as an entry has a "SYMLINK (path) -> target" line instead.
+///
+/// # Arguments
+///
+/// * `files` - Array of VirtualFile containing the hashed files.
+/// * `format` - The format of the manifest ("standard", "json", "csv" or "xml").
+/// * `style` - The delimiter and quoting style of the CSV output.
+///
+/// # Returns
+///
+/// The serialized manifest, SystemError otherwise.
+///
+fn serialize_manifest(files: &[VirtualFile], format: &str, style: CsvStyle) -> Result<String, SystemError> {
+    #[derive(Serialize)]
+    #[serde(rename = "files")]
+    struct VirtualFilesWrapper<'a> {
+        #[serde(rename = "file")]
+        files: &'a [VirtualFile],
+    }
+
+    // The checksums are sorted by algorithm, to keep the same order for every file.
+    let sorted_checksums = |file: &VirtualFile| -> Vec<(String, String)> { file.checksums.clone().unwrap_or_default().into_iter().collect() };
+
+    match format {
+        "json" => serde_json::to_string(files).map_err(|e| SystemError::UnableToSerialize("json".to_string(), e.to_string())),
+        "xml" => serde_xml_rs::to_string(&VirtualFilesWrapper { files }).map_err(|e| SystemError::UnableToSerialize("xml".to_string(), e.to_string())),
+        "csv" => {
+            let mut wtr: csv::Writer<Vec<u8>> = build_csv_writer(style);
+            wtr.write_record(["Path", "Filename", "Size", "Checksums"])
+                .map_err(|e| SystemError::UnableToSerialize("csv".to_string(), e.to_string()))?;
+
+            for file in files {
+                let checksums_str: String = match &file.target {
+                    Some(target) => format!("symlink:{target}"),
+                    None => sorted_checksums(file).iter().map(|(algo, checksum)| format!("{algo}:{checksum}")).collect::<Vec<_>>().join("\n"),
+                };
+                wtr.write_record([&file.full_path, &file.name, &file.size.to_string(), &checksums_str])
+                    .map_err(|e| SystemError::UnableToSerialize("csv".to_string(), e.to_string()))?;
+            }
+
+            String::from_utf8(wtr.into_inner().unwrap_or_default()).map_err(|e| SystemError::UnableToSerialize("csv".to_string(), e.to_string()))
+        }
+        _ => Ok(files
+            .iter()
+            .flat_map(|file| match &file.target {
+                Some(target) => vec![format!("SYMLINK ({}) -> {target}", file.full_path)],
+                None => sorted_checksums(file).into_iter().map(|(algo, checksum)| format!("{} ({}) = {checksum}", algo.to_uppercase(), file.full_path)).collect(),
+            })
+            .collect::<Vec<String>>()
+            .join("\n")),
+    }
+}
+
+/// This function is responsible for writing the serialized findings to their target.
+/// The file is compressed with zstd if asked by the file options, stdin is never compressed.
+///
+/// # Arguments
+///
+/// * `data` - The serialized findings.
+/// * `target` - Where to write the findings (stdin, a file or both).
+/// * `file_options` - How the output file is written (temporary directory and compression).
+/// * `stdout` - The writer used as standard output.
+///
+/// # Returns
+///
+/// Ok if the findings have been written, DeepFinderError otherwise.
+///
+fn write_output(data: &str, target: OutputTarget, file_options: FileOptions, stdout: &mut impl Write) -> Result<(), DeepFinderError> {
+    let temp_dir: Option<&str> = file_options.temp_dir;
+    if let OutputTarget::File(file_path) | OutputTarget::StdoutAndFile(file_path) = target {
+        let file_data: Vec<u8> = if file_options.compress {
+            zstd::encode_all(data.as_bytes(), 0)
+                .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("zstd".to_string(), e.to_string())))?
+        } else {
+            data.as_bytes().to_vec()
+        };
+
+        system::check_free_space(file_path, temp_dir, file_data.len() as u64, system::available_space).map_err(DeepFinderError::SystemError)?;
+        system::write_atomically(file_path, temp_dir, |writer| writer.write_all(&file_data))
+            .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToCreateFile(file_path.to_string(), e)))?;
+    }
+
+    if let OutputTarget::Stdout | OutputTarget::StdoutAndFile(_) = target {
+        writeln!(stdout, "{data}").map_err(|e| DeepFinderError::SystemError(SystemError::UnableToWriteOutput(e)))?;
+    }
+
+    Ok(())
+}
+
+/// This function is responsible for streaming the serialized findings to their target, through buffered writers.
+/// The file is compressed with zstd on the fly if asked by the file options, stdin is never compressed.
+/// The findings are written once per target, so the serialization is done twice for stdin and a file.
+///
+/// # Arguments
+///
+/// * `target` - Where to write the findings (stdin, a file or both).
+/// * `file_options` - How the output file is written (temporary directory and compression).
+/// * `estimated_size` - The estimated size of the findings, checked against the free space unless the file is compressed.
+/// * `stdout` - The writer used as standard output.
+/// * `write` - The closure serializing the findings into a writer.
+///
+/// # Returns
+///
+/// Ok if the findings have been written, DeepFinderError otherwise.
+///
+fn stream_output<F>(target: OutputTarget, file_options: FileOptions, estimated_size: u64, stdout: &mut impl Write, write: F) -> Result<(), DeepFinderError>
+where
+    F: Fn(&mut dyn Write) -> io::Result<()>,
+{
+    let temp_dir: Option<&str> = file_options.temp_dir;
+    if let OutputTarget::File(file_path) | OutputTarget::StdoutAndFile(file_path) = target {
+        let file_error = |e: io::Error| DeepFinderError::SystemError(SystemError::UnableToCreateFile(file_path.to_string(), e));
+        if !file_options.compress {
+            system::check_free_space(file_path, temp_dir, estimated_size, system::available_space).map_err(DeepFinderError::SystemError)?;
+        }
+
+        system::write_atomically(file_path, temp_dir, |writer| {
+            if file_options.compress {
+                let mut encoder: zstd::Encoder<'_, &mut BufWriter<File>> = zstd::Encoder::new(writer, 0)?;
+                write(&mut encoder)?;
+                encoder.finish().map(|_| ())
+            } else {
+                write(writer)
+            }
+        })
+        .map_err(file_error)?;
+    }
+
+    if let OutputTarget::Stdout | OutputTarget::StdoutAndFile(_) = target {
+        let mut writer: BufWriter<&mut _> = BufWriter::new(stdout);
+        write(&mut writer)
+            .and_then(|()| writeln!(writer))
+            .and_then(|()| writer.flush())
+            .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToWriteOutput(e)))?;
     }
 
     Ok(())
 }
 
-/// This function displays the findings in CSV format.
+/// This function is responsible for building the options of the output file from the user's configuration.
 ///
 /// # Arguments
 ///
-/// * `duplicates` - Array of DuplicateFile structs containing the findings.
-/// * `path` - An optional path to save the CSV output.
+/// * `config` - The FindingConfig struct with the user's configuration.
 ///
 /// # Returns
 ///
-/// Result<(), DeepFinderError> - Returns Ok if the display (and saving if a path was specified) is successful, DeepFinderError otherwise.
+/// The FileOptions of the output file ('--temp-dir' and '--compress').
 ///
-fn csv_display(duplicates: &[DuplicateFile], path: Option<&str>, include_hashes: bool) -> Result<(), DeepFinderError> {
-    let header: Vec<&str> = if include_hashes {
-        ["Index", "Filename", "Paths", "Occurrences", "Size", "Checksums"].to_vec()
-    } else {
-        ["Index", "Filename", "Paths", "Occurrences", "Size"].to_vec()
-    };
-
-    let mut wtr: csv::Writer<Vec<u8>> = WriterBuilder::new().delimiter(b';').from_writer(vec![]);
-    wtr.write_record(&header)
-        .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("csv".to_string(), e.to_string())))?;
-
-    for (idx, file) in duplicates.iter().enumerate() {
-        if include_hashes {
-            let checksums_str: String =  file.checksums.as_ref().map_or_else(
-                || "N/A".to_string(),
-                |checksums| {
-                    checksums.iter()
-                        .map(|(algo, checksum)| format!("{}:{}", algo, checksum))
-                        .collect::<Vec<_>>()
-                        .join("\n")
-                },
-            );
-            
-            wtr.write_record(
-                [
-                    &(idx + 1).to_string(),
-                    &file.name,
-                    &file.paths.iter().cloned().collect::<Vec<String>>().join("\n"),
-                    &file.paths.len().to_string(),
-                    &file.size.to_string(),
-                    &checksums_str,
-                ]
-            ).map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("csv".to_string(), e.to_string())))?;
-        } else {
-            wtr.write_record(
-                [   
-                    &(idx + 1).to_string(),
-                    &file.name,
-                    &file.paths.iter().cloned().collect::<Vec<String>>().join("\n"),
-                    &file.paths.len().to_string(),
-                    &file.size.to_string(),
-                ]
-            ).map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("csv".to_string(), e.to_string())))?;
-        }
-        
-    }
-    
-    let csv_data: String = String::from_utf8(wtr.into_inner().unwrap_or_default())
-        .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("csv".to_string(), e.to_string())))?;
-
-    if let Some(file_path) = path {
-        fs::write(file_path, csv_data)
-            .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToCreateFile(file_path.to_string(), e.to_string())))?;
-    } else {
-        println!("{}", csv_data);
-    }
-
-    Ok(())
+fn output_file_options(config: &FindingConfig) -> FileOptions<'_> {
+    FileOptions { temp_dir: config.temp_dir.as_deref(), compress: config.compress }
 }
 
-/// This function displays the findings in XML format.
+/// This function is responsible for writing a short summary of a file output, so the user gets feedback on the console.
+/// Nothing is written if the target isn't a file or if the quiet mode is enabled.
 ///
 /// # Arguments
 ///
-/// * `duplicates` - Array of DuplicateFile structs containing the findings.
-/// * `path` - An optional path to save the XML output.
+/// * `summary` - The summary of the findings written (e.g. "Wrote 3 duplicate groups").
+/// * `target` - Where the findings have been written.
+/// * `quiet` - A boolean that indicates if the informational messages are disabled or not.
+/// * `stderr` - The writer used as standard error.
 ///
 /// # Returns
 ///
-/// Result<(), DeepFinderError> - Returns Ok if the display (and saving if a path was specified) is successful, DeepFinderError otherwise.
+/// Ok if the summary has been written (or skipped), DeepFinderError otherwise.
 ///
-fn xml_display(duplicates: &[DuplicateFile], path: Option<&str>, include_hashes: bool) -> Result<(), DeepFinderError> {
-    #[derive(Serialize)]
-    #[serde(rename = "duplicate_files")]
-    struct DuplicateFilesWrapper<'a> {
-        #[serde(rename = "duplicate_file")]
-        files: Vec<DuplicateFileSerialized<'a>>,
-    }
-
-    let xml_values: Vec<DuplicateFileSerialized> = duplicates.iter().enumerate().map(|(idx, d)| {
-        DuplicateFileSerialized {
-            index: idx + 1,
-            paths: &d.paths,
-            name: &d.name,
-            checksums: if include_hashes { d.checksums.as_ref() } else { None },
-            size: d.size,
-        }
-    }).collect();
-    let wrapper: DuplicateFilesWrapper = DuplicateFilesWrapper { files: xml_values };
-    let xml_data: String = serde_xml_rs::to_string(&wrapper)
-        .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("xml".to_string(), e.to_string())))?;
-    
-    if let Some(file_path) = path {
-        fs::write(file_path, xml_data)
-            .map_err(|e| DeepFinderError::SystemError(SystemError::UnableToCreateFile(file_path.to_string(), e.to_string())))?;
-    } else {
-        println!("{}", xml_data);
+fn write_summary(summary: &str, target: OutputTarget, quiet: bool, stderr: &mut impl Write) -> Result<(), DeepFinderError> {
+    if let OutputTarget::File(file_path) | OutputTarget::StdoutAndFile(file_path) = target && !quiet {
+        writeln!(stderr, "{summary} to {file_path}").map_err(|e| DeepFinderError::SystemError(SystemError::UnableToWriteOutput(e)))?;
     }
 
     Ok(())
@@ -232,9 +1461,10 @@ mod tests {
                 paths: ["path1".to_string(), "path2".to_string()].into_iter().collect(),
                 size: 123,
                 checksums: None,
+                sizes: BTreeMap::new(),
             }
         ];
-        assert!(json_display(&duplicates.clone(), None, true).is_ok());
+        assert!(json_display(&duplicates.clone(), &[], OutputTarget::Stdout, FileOptions::default(), true, None).is_ok());
     }
 
     #[test]
@@ -245,10 +1475,11 @@ mod tests {
                 paths: ["pathA".to_string(), "pathB".to_string()].into_iter().collect(),
                 size: 456,
                 checksums: None,
+                sizes: BTreeMap::new(),
             }
         ];
         let test_path: &'static str = "test_output.json";
-        assert!(json_display(&duplicates.clone(), Some(test_path), false).is_ok());
+        assert!(json_display(&duplicates.clone(), &[], OutputTarget::File(test_path), FileOptions::default(), false, None).is_ok());
 
         let content: String = fs::read_to_string(test_path).expect("File should exist");
         assert!(content.contains("file2.txt"));
@@ -263,9 +1494,10 @@ mod tests {
                 paths: ["path1".to_string(), "path2".to_string()].into_iter().collect(),
                 size: 123,
                 checksums: None,
+                sizes: BTreeMap::new(),
             }
         ];
-        assert!(csv_display(&duplicates.clone(), None, false).is_ok());
+        assert!(csv_display(&duplicates.clone(), OutputTarget::Stdout, FileOptions::default(), false, CsvStyle::default(), None).is_ok());
     }
 
     #[test]
@@ -276,17 +1508,85 @@ mod tests {
                 paths: ["pathA".to_string(), "pathB".to_string()].into_iter().collect(),
                 size: 456,
                 checksums: None,
+                sizes: BTreeMap::new(),
             }
         ];
         
         let test_path: &'static str = "test_output.csv";
-        assert!(csv_display(&duplicates.clone(), Some(test_path), true).is_ok());
+        assert!(csv_display(&duplicates.clone(), OutputTarget::File(test_path), FileOptions::default(), true, CsvStyle::default(), None).is_ok());
         
         let content: String = fs::read_to_string(test_path).expect("File should exist");
         assert!(content.contains("file2.txt"));
         let _ = fs::remove_file(test_path);
     }
 
+    #[test]
+    fn test_csv_display_with_custom_style() {
+        let duplicates: Vec<DuplicateFile> = vec![
+            DuplicateFile {
+                name: "file,3.txt".to_string(),
+                paths: ["pathA".to_string(), "pathB".to_string()].into_iter().collect(),
+                size: 789,
+                checksums: None,
+                sizes: BTreeMap::new(),
+            }
+        ];
+
+        let test_path: &'static str = "test_output_comma.csv";
+        let style: CsvStyle = CsvStyle { delimiter: b',', always_quote: true, hash_columns: false };
+        assert!(csv_display(&duplicates, OutputTarget::File(test_path), FileOptions::default(), false, style, None).is_ok());
+
+        let content: String = fs::read_to_string(test_path).expect("File should exist");
+        assert!(content.starts_with("\"Index\",\"Filename\""));
+
+        let mut reader: csv::Reader<&[u8]> = csv::ReaderBuilder::new().delimiter(b',').from_reader(content.as_bytes());
+        let records: Vec<csv::StringRecord> = reader.records().map(|r| r.expect("Record should be valid")).collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(&records[0][1], "file,3.txt");
+        assert_eq!(&records[0][3], "2");
+        assert_eq!(&records[0][5], group_id(&duplicates[0].paths));
+        let _ = fs::remove_file(test_path);
+    }
+
+    #[test]
+    fn test_csv_display_with_hash_columns() {
+        let duplicates: Vec<DuplicateFile> = vec![
+            DuplicateFile {
+                name: "file1.txt".to_string(),
+                paths: ["pathA".to_string(), "pathB".to_string()].into_iter().collect(),
+                size: 12,
+                checksums: Some(BTreeMap::from([("sha256".to_string(), "5678".to_string()), ("md5".to_string(), "1234".to_string())])),
+                sizes: BTreeMap::new(),
+            },
+            DuplicateFile {
+                name: "file2.txt".to_string(),
+                paths: ["pathC".to_string(), "pathD".to_string()].into_iter().collect(),
+                size: 34,
+                checksums: Some(BTreeMap::from([("md5".to_string(), "abcd".to_string())])),
+                sizes: BTreeMap::new(),
+            },
+        ];
+
+        let test_path: String = std::env::temp_dir().join("deepfinder_test_output_hash_columns.csv").to_string_lossy().to_string();
+        let style: CsvStyle = CsvStyle { hash_columns: true, ..CsvStyle::default() };
+        csv_display(&duplicates, OutputTarget::File(&test_path), FileOptions::default(), true, style, None).unwrap();
+
+        // A column per algorithm (sorted), N/A for a checksum missing from a group.
+        let content: String = fs::read_to_string(&test_path).unwrap();
+        let mut reader: csv::Reader<&[u8]> = csv::ReaderBuilder::new().delimiter(b';').from_reader(content.as_bytes());
+        assert_eq!(reader.headers().unwrap().iter().collect::<Vec<&str>>(), ["Index", "Filename", "Paths", "Occurrences", "Size", "MD5", "SHA256", "Group ID"]);
+        let records: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!((&records[0][5], &records[0][6]), ("1234", "5678"));
+        assert_eq!((&records[1][5], &records[1][6]), ("abcd", "N/A"));
+        assert_eq!(&records[1][7], group_id(&duplicates[1].paths));
+
+        // The single cell lists the checksums in the same order.
+        csv_display(&duplicates, OutputTarget::File(&test_path), FileOptions::default(), true, CsvStyle::default(), None).unwrap();
+        assert!(fs::read_to_string(&test_path).unwrap().contains("\"md5:1234\nsha256:5678\""));
+
+        let _ = fs::remove_file(test_path);
+    }
+
     #[test]
     fn test_xml_display_to_stdout() {
         let duplicates: Vec<DuplicateFile> = vec![
@@ -295,9 +1595,10 @@ mod tests {
                 paths: ["path1".to_string(), "path2".to_string()].into_iter().collect(),
                 size: 123,
                 checksums: None,
+                sizes: BTreeMap::new(),
             }
         ];
-        assert!(xml_display(&duplicates.clone(), None, false).is_ok());
+        assert!(xml_display(&duplicates.clone(), &[], OutputTarget::Stdout, FileOptions::default(), false, XmlStyle::default(), None).is_ok());
     }
 
     #[test]
@@ -308,14 +1609,610 @@ mod tests {
                 paths: ["pathA".to_string(), "pathB".to_string()].into_iter().collect(),
                 size: 456,
                 checksums: None,
+                sizes: BTreeMap::new(),
             }
         ];
         
         let test_path: &'static str = "test_output.xml";
-        assert!(xml_display(&duplicates.clone(), Some(test_path), true).is_ok());
+        assert!(xml_display(&duplicates.clone(), &[], OutputTarget::File(test_path), FileOptions::default(), true, XmlStyle::default(), None).is_ok());
         
         let content: String = fs::read_to_string(test_path).expect("File should exist");
         assert!(content.contains("file2.txt"));
         let _ = fs::remove_file(test_path);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_xml_display_pretty() {
+        let duplicates: Vec<DuplicateFile> = vec![
+            DuplicateFile {
+                name: "file2.txt".to_string(),
+                paths: ["pathA".to_string(), "pathB".to_string()].into_iter().collect(),
+                size: 456,
+                checksums: None,
+                sizes: BTreeMap::new(),
+            }
+        ];
+        let test_path: String = std::env::temp_dir().join("deepfinder_test_output_pretty.xml").to_string_lossy().to_string();
+        let read_xml = |style: XmlStyle| {
+            xml_display(&duplicates, &[], OutputTarget::File(&test_path), FileOptions::default(), false, style, None).unwrap();
+            fs::read_to_string(&test_path).unwrap()
+        };
+
+        let compact: String = read_xml(XmlStyle::default());
+        assert!(!compact.contains('\n'));
+        let pretty: String = read_xml(XmlStyle { indent: true, crlf: false });
+        assert!(pretty.contains("\n  <duplicate_file>\n    <index>1</index>\n"));
+        let crlf: String = read_xml(XmlStyle { indent: true, crlf: true });
+        assert!(crlf.contains("\r\n  <duplicate_file>\r\n") && !crlf.replace("\r\n", "").contains('\n'));
+
+        // Only whitespaces are added between the elements, the document is the same.
+        assert_eq!(crlf.lines().map(str::trim).collect::<String>(), compact);
+
+        let _ = fs::remove_file(test_path);
+    }
+
+    #[test]
+    fn test_load_json_report() {
+        let duplicates: Vec<DuplicateFile> = vec![
+            DuplicateFile {
+                name: "file3.txt".to_string(),
+                paths: ["pathX".to_string(), "pathY".to_string()].into_iter().collect(),
+                size: 789,
+                checksums: Some([("md5".to_string(), "abc123".to_string())].into_iter().collect()),
+                sizes: BTreeMap::new(),
+            }
+        ];
+
+        let test_path: &'static str = "test_report.json";
+        assert!(json_display(&duplicates, &[], OutputTarget::File(test_path), FileOptions::default(), true, None).is_ok());
+
+        let loaded: Vec<DuplicateFile> = load_json_report(test_path).expect("Report should be loaded");
+        assert!(loaded == duplicates);
+        assert!(load_json_report("test_missing_report.json").is_err());
+
+        // The reports written as a bare array of groups can still be loaded.
+        fs::write(test_path, serde_json::to_string(&duplicates).unwrap()).unwrap();
+        assert!(load_json_report(test_path).unwrap() == duplicates);
+        let _ = fs::remove_file(test_path);
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_json_display_with_warnings() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_json_warnings");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("file.txt"), "content").unwrap();
+        std::os::unix::fs::symlink(dir.join("missing.txt"), dir.join("broken.txt")).unwrap();
+
+        // The broken link is skipped by the traversal, and reported in the JSON output.
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let config: FindingConfig = FindingConfig::default();
+        assert_eq!(crate::search_engine::search_files(&dir_path, &config).unwrap(), [format!("{dir_path}/file.txt")]);
+        let warnings: Vec<Warning> = crate::search_engine::take_warnings(&config);
+        let output_path: String = format!("{dir_path}/report.json");
+        json_display(&[], &warnings, OutputTarget::File(&output_path), FileOptions::default(), false, None).unwrap();
+
+        let report: Value = serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert_eq!(report["duplicates"], serde_json::json!([]));
+        assert_eq!(report["warnings"].as_array().unwrap().len(), 1);
+        assert_eq!(report["warnings"][0]["kind"], "skipped_file");
+        assert!(report["warnings"][0]["detail"].as_str().unwrap().contains("broken.txt"));
+        assert!(format_warnings(&warnings).starts_with("\nWarnings (1):\n  [skipped_file] the symbolic link '"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_output_schema() {
+        let duplicates: Vec<DuplicateFile> = vec![
+            DuplicateFile {
+                name: "file1.txt".to_string(),
+                paths: ["path1".to_string(), "path2".to_string(), "path3".to_string()].into_iter().collect(),
+                size: 123,
+                checksums: Some(BTreeMap::from([("md5".to_string(), "abcd".to_string())])),
+                sizes: BTreeMap::new(),
+            },
+            DuplicateFile { name: "file2.txt".to_string(), paths: ["path4".to_string(), "path5".to_string()].into_iter().collect(), size: 0, checksums: None, sizes: BTreeMap::new() },
+        ];
+        let warnings: Vec<Warning> = vec![Warning { kind: "skipped_file", detail: "the file 'path6' has been skipped.".to_string() }];
+        let output_path: String = std::env::temp_dir().join("deepfinder_test_output_schema.json").to_string_lossy().to_string();
+
+        // A real report (with truncated paths and checksums) is valid, an altered one isn't.
+        let schema: Value = serde_json::from_str(&output_schema("json")).unwrap();
+        let validator: jsonschema::Validator = jsonschema::validator_for(&schema).unwrap();
+        json_display(&duplicates, &warnings, OutputTarget::File(&output_path), FileOptions::default(), true, Some(2)).unwrap();
+        let mut report: Value = serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert!(validator.is_valid(&report));
+        report["duplicates"][0]["unknown"] = Value::Bool(true);
+        assert!(!validator.is_valid(&report));
+        report["duplicates"][0].as_object_mut().unwrap().remove("unknown");
+        report["duplicates"][0]["index"] = Value::from(0);
+        assert!(!validator.is_valid(&report));
+
+        // A real XML report is valid against the XSD, checked by xmllint when it's installed.
+        let xml_output_path: String = std::env::temp_dir().join("deepfinder_test_output_schema.xml").to_string_lossy().to_string();
+        let xsd_path: String = std::env::temp_dir().join("deepfinder_test_output_schema.xsd").to_string_lossy().to_string();
+        xml_display(&duplicates, &warnings, OutputTarget::File(&xml_output_path), FileOptions::default(), true, XmlStyle::default(), Some(2)).unwrap();
+        fs::write(&xsd_path, output_schema("xml")).unwrap();
+        if let Ok(output) = std::process::Command::new("xmllint").args(["--noout", "--schema", &xsd_path, &xml_output_path]).output() {
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        }
+
+        for path in [output_path, xml_output_path, xsd_path] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn test_format_empty_dirs() {
+        let empty_dirs: Vec<EmptyDir> = vec![EmptyDir { path: "/a".to_string(), recursive: true }, EmptyDir { path: "/a/b".to_string(), recursive: false }];
+        assert_eq!(format_empty_dirs(&empty_dirs), "\nEmpty directories (2):\n  [recursive] /a\n  [immediate] /a/b\n");
+        assert_eq!(format_empty_dirs(&[]), "\nNo empty directory found.\n");
+    }
+
+    #[test]
+    fn test_write_output_to_stdout_and_file() {
+        let duplicates: Vec<DuplicateFile> = vec![
+            DuplicateFile {
+                name: "file4.txt".to_string(),
+                paths: ["pathA".to_string(), "pathB".to_string()].into_iter().collect(),
+                size: 42,
+                checksums: None,
+                sizes: BTreeMap::new(),
+            }
+        ];
+        let json_data: String = serde_json::to_string(&duplicates).unwrap();
+
+        let test_path: &'static str = "test_output_tee.json";
+        let mut stdout: Vec<u8> = Vec::new();
+        assert!(write_output(&json_data, OutputTarget::StdoutAndFile(test_path), FileOptions::default(), &mut stdout).is_ok());
+
+        let content: String = fs::read_to_string(test_path).expect("File should exist");
+        assert_eq!(content, json_data);
+        assert_eq!(String::from_utf8(stdout).unwrap(), format!("{json_data}\n"));
+        let _ = fs::remove_file(test_path);
+    }
+
+    #[test]
+    fn test_quiet_if_empty() {
+        let file_path: String = std::env::temp_dir().join("deepfinder_test_quiet_if_empty.json").to_string_lossy().to_string();
+        let _ = fs::remove_file(&file_path);
+        let mut config: FindingConfig = FindingConfig {
+            output: CliOutput::JsonStdinAndFile(file_path.clone()),
+            quiet_if_empty: true,
+            ..Default::default()
+        };
+
+        // Nothing is written, neither in the file nor in stdin.
+        export_findings_scheduler(&[], &[], &config).unwrap();
+        export_deletable_scheduler(&[], &config).unwrap();
+        assert!(fs::metadata(&file_path).is_err());
+
+        config.quiet_if_empty = false;
+        export_findings_scheduler(&[], &[], &config).unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), r#"{"duplicates":[],"warnings":[]}"#);
+
+        let _ = fs::remove_file(file_path);
+    }
+
+    #[test]
+    fn test_max_paths_per_group() {
+        let duplicate: DuplicateFile = DuplicateFile {
+            name: "file.txt".to_string(),
+            paths: ["/d", "/b", "/a", "/c", "/e"].iter().map(ToString::to_string).collect(),
+            size: 10,
+            checksums: None,
+            sizes: BTreeMap::new(),
+        };
+
+        let serialized: DuplicateFileSerialized = DuplicateFileSerialized::new(1, &duplicate, false, Some(2));
+        assert_eq!(serialized.paths, vec!["/a", "/b"]);
+        assert_eq!(serialized.occurrences, Some(5));
+        assert!(serialized.paths_truncated);
+        let json: String = serde_json::to_string(&serialized).unwrap();
+        assert!(json.contains("\"paths_truncated\":true") && json.contains("\"occurrences\":5"));
+
+        // Nothing is truncated if the group is small enough.
+        let serialized: DuplicateFileSerialized = DuplicateFileSerialized::new(1, &duplicate, false, Some(5));
+        assert_eq!(serialized.paths.len(), 5);
+        assert!(!serde_json::to_string(&serialized).unwrap().contains("paths_truncated"));
+
+        let display: String = format_simple_display(std::slice::from_ref(&duplicate), false, false, Some(2), None);
+        assert!(display.contains(" - /a\n - /b\n ... and 3 more\n"));
+        assert!(display.contains("Occurrences: 5"));
+    }
+
+    #[test]
+    fn test_group_id() {
+        let duplicate: DuplicateFile = DuplicateFile {
+            name: "file.txt".to_string(),
+            paths: ["/b/file.txt", "/a/file.txt"].iter().map(ToString::to_string).collect(),
+            size: 10,
+            checksums: None,
+            sizes: BTreeMap::new(),
+        };
+        let mut same_group: DuplicateFile = duplicate.clone();
+        same_group.paths = ["/a/file.txt", "/b/file.txt"].iter().map(ToString::to_string).collect();
+
+        // The identifier doesn't depend on the index nor on the order of the paths.
+        let first: serde_json::Value = serde_json::to_value(DuplicateFileSerialized::new(1, &duplicate, false, None)).unwrap();
+        let second: serde_json::Value = serde_json::to_value(DuplicateFileSerialized::new(7, &same_group, true, Some(1))).unwrap();
+        assert_eq!(first["group_id"].as_str().unwrap().len(), 16);
+        assert_eq!(first["group_id"], second["group_id"]);
+
+        same_group.paths.insert("/c/file.txt".to_string());
+        assert_ne!(group_id(&duplicate.paths), group_id(&same_group.paths));
+    }
+
+    #[test]
+    fn test_build_json_tree() {
+        let group = |name: &str, paths: &[&str]| DuplicateFile {
+            name: name.to_string(),
+            paths: paths.iter().map(ToString::to_string).collect(),
+            size: 10,
+            checksums: None,
+            sizes: BTreeMap::new(),
+        };
+        let duplicates: Vec<DuplicateFile> = vec![
+            group("a.txt", &["/home/user/a.txt", "/home/user/backup/a.txt", "/srv/a.txt"]),
+            group("b.zip", &["/home/user/b.zip", "/srv/b.zip"]),
+            group("c.txt", &["/home/user/b.zip/c.txt", "/srv/old/c.txt"]),
+        ];
+        let (a_id, b_id, c_id): (String, String, String) = (group_id(&duplicates[0].paths), group_id(&duplicates[1].paths), group_id(&duplicates[2].paths));
+
+        // Each folder is a nested object, each file references its group. The duplicate archive keeps both its group and its entries.
+        let expected: Value = serde_json::json!({
+            "home": { "user": { "a.txt": a_id, "b.zip": { "": b_id, "c.txt": c_id }, "backup": { "a.txt": a_id } } },
+            "srv": { "a.txt": a_id, "b.zip": b_id, "old": { "c.txt": c_id } },
+        });
+        assert_eq!(Value::Object(build_json_tree(&duplicates)), expected);
+
+        // The archive is kept whatever the order of the groups.
+        let reversed: Vec<DuplicateFile> = duplicates.into_iter().rev().collect();
+        assert_eq!(Value::Object(build_json_tree(&reversed)), expected);
+
+        // The tree is written with the warnings.
+        let output_path: String = std::env::temp_dir().join("deepfinder_test_json_tree.json").to_string_lossy().to_string();
+        let warnings: Vec<Warning> = vec![Warning { kind: "skipped_file", detail: "unreadable".to_string() }];
+        json_tree_display(&reversed, &warnings, OutputTarget::File(&output_path), FileOptions::default()).unwrap();
+        let written: Value = serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert_eq!(written["tree"], expected);
+        assert_eq!(written["warnings"], serde_json::json!([{ "kind": "skipped_file", "detail": "unreadable" }]));
+        let _ = fs::remove_file(output_path);
+
+        // A backslash is part of a name on Unix.
+        #[cfg(target_family = "unix")]
+        {
+            let duplicates: Vec<DuplicateFile> = vec![group("a\\b.txt", &["/data/a\\b.txt", "/srv/a\\b.txt"])];
+            let id: String = group_id(&duplicates[0].paths);
+            assert_eq!(Value::Object(build_json_tree(&duplicates)), serde_json::json!({ "data": { "a\\b.txt": id }, "srv": { "a\\b.txt": id } }));
+        }
+    }
+
+    #[test]
+    fn test_write_summary() {
+        let file_path: String = std::env::temp_dir().join("deepfinder_test_summary.json").to_string_lossy().to_string();
+        let duplicates: Vec<DuplicateFile> = vec![DuplicateFile {
+            name: "file.txt".to_string(),
+            paths: ["/a".to_string(), "/b".to_string()].into_iter().collect(),
+            size: 2048,
+            checksums: None,
+            sizes: BTreeMap::new(),
+        }];
+        let config: FindingConfig = FindingConfig { output: CliOutput::JsonFile(file_path.clone()), ..Default::default() };
+        export_findings_scheduler(&duplicates, &[], &config).unwrap();
+
+        // The file only contains the findings, the summary is written apart.
+        let json: String = fs::read_to_string(&file_path).unwrap();
+        assert_eq!(load_json_report(&file_path).unwrap().len(), 1);
+        assert!(!json.contains("Wrote"));
+
+        let mut stderr: Vec<u8> = Vec::new();
+        write_summary("Wrote 1 duplicate groups (2.0 KiB reclaimable)", OutputTarget::File(&file_path), false, &mut stderr).unwrap();
+        assert_eq!(String::from_utf8(stderr).unwrap(), format!("Wrote 1 duplicate groups (2.0 KiB reclaimable) to {file_path}\n"));
+
+        let mut stderr: Vec<u8> = Vec::new();
+        write_summary("Wrote 1 duplicate groups", OutputTarget::Stdout, false, &mut stderr).unwrap();
+        write_summary("Wrote 1 duplicate groups", OutputTarget::File(&file_path), true, &mut stderr).unwrap();
+        assert!(stderr.is_empty());
+
+        let _ = fs::remove_file(file_path);
+    }
+
+    #[test]
+    fn test_build_summary_report() {
+        let group = |name: &str, size: u64, occurrences: usize| DuplicateFile {
+            name: name.to_string(),
+            paths: (0..occurrences).map(|i| format!("/dir{i}/{name}")).collect(),
+            size,
+            checksums: None,
+            sizes: BTreeMap::new(),
+        };
+        let mut duplicates: Vec<DuplicateFile> = (0..12).map(|i| group(&format!("small{i}.txt"), 10 + i, 2)).collect();
+        duplicates.push(group("large.iso", 1000, 2));
+        duplicates.push(group("copied.txt", 100, 11));
+
+        let report: SummaryReport = build_summary_report(&duplicates);
+        assert_eq!(report.groups, 14);
+        assert_eq!(report.files, 12 * 2 + 2 + 11);
+        let small_bytes: u64 = (10..22).sum::<u64>();
+        assert_eq!(report.duplicated_bytes, 2 * small_bytes + 2000 + 1100);
+        assert_eq!(report.reclaimable_bytes, small_bytes + 1000 + 1000);
+        assert!((report.dedupe_ratio - (2 * small_bytes + 3100) as f64 / (small_bytes + 1100) as f64).abs() < 1e-9);
+
+        // The groups wasting the most space come first, the ties keep the order of the findings.
+        let names: Vec<&str> = report.top_offenders.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names.len(), TOP_OFFENDERS);
+        assert_eq!(names[..4], ["large.iso", "copied.txt", "small11.txt", "small10.txt"]);
+
+        let formatted: String = format_summary_report(&report);
+        assert!(formatted.contains(" 1. large.iso (2 x 1000 B, 1000 B reclaimable)\n"));
+        assert!(format_summary_report(&build_summary_report(&[])).ends_with("Dedupe ratio: 1.00:1\n"));
+    }
+
+    #[test]
+    fn test_build_dir_pairs() {
+        let group = |name: &str, size: u64, directories: &[&str]| DuplicateFile {
+            name: name.to_string(),
+            paths: directories.iter().map(|directory| format!("{directory}/{name}")).collect(),
+            size,
+            checksums: None,
+            sizes: BTreeMap::new(),
+        };
+        let duplicates: Vec<DuplicateFile> = vec![
+            group("a.jpg", 100, &["/photos", "/backup/photos"]),
+            group("b.jpg", 200, &["/backup/photos", "/photos"]),
+            group("c.jpg", 300, &["/photos", "/backup/photos", "/tmp"]),
+            group("d.txt", 5000, &["/tmp", "/docs"]),
+        ];
+
+        // The pairs are unordered, and the mirrored folders come after the pair sharing the largest size.
+        let pairs: Vec<DirPair> = build_dir_pairs(&duplicates);
+        let summary: Vec<(&str, &str, usize, u64)> = pairs.iter().map(|p| (p.first, p.second, p.files, p.bytes)).collect();
+        assert_eq!(summary, [
+            ("/docs", "/tmp", 1, 5000),
+            ("/backup/photos", "/photos", 3, 600),
+            ("/backup/photos", "/tmp", 1, 300),
+            ("/photos", "/tmp", 1, 300),
+        ]);
+        assert!(format_dir_pairs(&pairs).contains("\n/backup/photos <-> /photos: 3 duplicate files (600 B)\n"));
+
+        // The copies in the same directory don't make a pair.
+        let same_directory: DuplicateFile = DuplicateFile {
+            paths: HashSet::from(["/same/e.txt".to_string(), "/same/f.txt".to_string()]),
+            ..group("e.txt", 10, &[])
+        };
+        assert!(build_dir_pairs(&[same_directory]).is_empty());
+        assert_eq!(format_dir_pairs(&[]), "No pair of directories shares a duplicate.\n");
+    }
+
+    #[test]
+    fn test_write_json_array_streamed() {
+        // This writer records the largest write it receives, to check that the report is never written at once.
+        #[derive(Default)]
+        struct RecordingWriter {
+            data: Vec<u8>,
+            largest_write: usize,
+        }
+
+        impl Write for RecordingWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.largest_write = self.largest_write.max(buf.len());
+                self.data.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let duplicates: Vec<DuplicateFile> = (0..10_000).map(|i| DuplicateFile {
+            name: format!("file{i}.txt"),
+            paths: [format!("/a/file{i}.txt"), format!("/b/file{i}.txt")].into_iter().collect(),
+            size: i,
+            checksums: None,
+            sizes: BTreeMap::new(),
+        }).collect();
+        let json_values = || duplicates.iter().enumerate().map(|(idx, d)| DuplicateFileSerialized::new(idx + 1, d, false, None));
+
+        let mut writer: BufWriter<RecordingWriter> = BufWriter::with_capacity(8 * 1024, RecordingWriter::default());
+        write_json_array(&mut writer, json_values()).unwrap();
+        let recorder: RecordingWriter = writer.into_inner().map_err(|_| ()).unwrap();
+
+        // The output is the same as the serialization of the whole array, but written by small blocks.
+        assert_eq!(String::from_utf8(recorder.data.clone()).unwrap(), serde_json::to_string(&json_values().collect::<Vec<_>>()).unwrap());
+        assert!(recorder.largest_write <= 8 * 1024);
+        assert!(recorder.data.len() > 100 * recorder.largest_write);
+        assert_eq!(serde_json::from_slice::<Vec<DuplicateFile>>(&recorder.data).unwrap().len(), 10_000);
+
+        let mut empty: Vec<u8> = Vec::new();
+        write_json_array(&mut empty, json_values().take(0)).unwrap();
+        assert_eq!(empty, b"[]");
+    }
+
+    #[test]
+    fn test_json_display_compressed() {
+        let duplicates: Vec<DuplicateFile> = vec![DuplicateFile {
+            name: "file.txt".to_string(),
+            paths: ["/a".to_string(), "/b".to_string()].into_iter().collect(),
+            size: 10,
+            checksums: None,
+            sizes: BTreeMap::new(),
+        }];
+        let plain_path: String = std::env::temp_dir().join("deepfinder_test_compressed.json").to_string_lossy().to_string();
+        let compressed_path: String = format!("{plain_path}.zst");
+        json_display(&duplicates, &[], OutputTarget::File(&plain_path), FileOptions::default(), false, None).unwrap();
+        let file_options: FileOptions = FileOptions { compress: true, ..Default::default() };
+        json_display(&duplicates, &[], OutputTarget::File(&compressed_path), file_options, false, None).unwrap();
+
+        let compressed_data: Vec<u8> = fs::read(&compressed_path).unwrap();
+        assert_ne!(compressed_data, fs::read(&plain_path).unwrap());
+        assert_eq!(zstd::decode_all(compressed_data.as_slice()).unwrap(), fs::read(&plain_path).unwrap());
+        assert_eq!(load_json_report(&compressed_path).unwrap().len(), 1);
+
+        // The '.zst' extension alone doesn't compress the output file.
+        json_display(&duplicates, &[], OutputTarget::File(&compressed_path), FileOptions::default(), false, None).unwrap();
+        assert_eq!(fs::read(&compressed_path).unwrap(), fs::read(&plain_path).unwrap());
+        assert_eq!(load_json_report(&compressed_path).unwrap().len(), 1);
+
+        let _ = fs::remove_file(plain_path);
+        let _ = fs::remove_file(compressed_path);
+    }
+
+    #[test]
+    fn test_serialize_manifest() {
+        let file = |name: &str, md5: &str| VirtualFile {
+            name: name.to_string(),
+            size: 3,
+            full_path: format!("/tmp/{name}"),
+            checksums: Some([("md5".to_string(), md5.to_string())].into_iter().collect()),
+            ..Default::default()
+        };
+        let files: Vec<VirtualFile> = vec![file("a.txt", "aaa"), file("b.txt", "aaa"), file("c.txt", "ccc")];
+
+        let json_data: String = serialize_manifest(&files, "json", CsvStyle::default()).unwrap();
+        let values: Vec<serde_json::Value> = serde_json::from_str(&json_data).unwrap();
+        assert_eq!(values.len(), 3);
+        for (value, file) in values.iter().zip(&files) {
+            assert_eq!(value["full_path"], file.full_path.as_str());
+            assert_eq!(value["checksums"]["md5"], file.checksums.as_ref().unwrap()["md5"].as_str());
+        }
+
+        let standard_data: String = serialize_manifest(&files, "standard", CsvStyle::default()).unwrap();
+        assert_eq!(standard_data, "MD5 (/tmp/a.txt) = aaa\nMD5 (/tmp/b.txt) = aaa\nMD5 (/tmp/c.txt) = ccc");
+    }
+
+    #[test]
+    fn test_build_deletable_files() {
+        let duplicates: Vec<DuplicateFile> = vec![
+            DuplicateFile {
+                name: "file1.txt".to_string(),
+                paths: ["/b/file1.txt".to_string(), "/a/file1.txt".to_string(), "/c/file1.txt".to_string()].into_iter().collect(),
+                size: 10,
+                checksums: None,
+                sizes: BTreeMap::new(),
+            },
+            DuplicateFile {
+                name: "file2.txt".to_string(),
+                paths: ["/a/file2.txt".to_string(), "/b/file2.txt".to_string()].into_iter().collect(),
+                size: 20,
+                checksums: None,
+                sizes: BTreeMap::new(),
+            },
+        ];
+
+        let deletable_files: Vec<DeletableFile> = build_deletable_files(&duplicates, KeepPolicy::First, &[]);
+        let total_paths: usize = duplicates.iter().map(|d| d.paths.len()).sum();
+        assert_eq!(deletable_files.len(), total_paths - duplicates.len());
+        assert!(deletable_files.iter().all(|f| f.path != f.kept_path && f.kept_path.starts_with("/a/")));
+
+        let standard_data: String = serialize_deletable(&deletable_files, "standard", CsvStyle::default()).unwrap();
+        assert_eq!(standard_data, "/b/file1.txt\t/a/file1.txt\n/c/file1.txt\t/a/file1.txt\n/b/file2.txt\t/a/file2.txt");
+    }
+
+    #[test]
+    fn test_build_delete_script() {
+        let group = |paths: &[&str]| DuplicateFile {
+            paths: paths.iter().map(ToString::to_string).collect(),
+            name: "file.txt".to_string(),
+            checksums: None,
+            size: 1024,
+            sizes: BTreeMap::new(),
+        };
+        let duplicates: Vec<DuplicateFile> = vec![
+            group(&["/data/a file.txt", "/data/it's $HOME.txt", "/data/-rf.txt"]),
+            group(&["/data/b\nkept.txt", "/data/line\nbreak.txt"]),
+        ];
+        let deletable_files: Vec<DeletableFile> = build_deletable_files(&duplicates, KeepPolicy::First, &[]);
+
+        // One delete command per extra path, the kept paths only appear (escaped) in the comments.
+        let script: String = build_delete_script(&deletable_files, false);
+        assert!(script.starts_with("#!/bin/sh\n") && script.contains("It deletes 3 duplicate files, 3.0 KiB reclaimable."));
+        assert_eq!(script.lines().filter(|line| line.starts_with("rm -- ")).count(), 3);
+        assert!(script.contains("rm -- '/data/a file.txt'\n"));
+        assert!(script.contains(r"rm -- '/data/it'\''s $HOME.txt'"));
+        assert!(script.contains("rm -- '/data/line\nbreak.txt'\n"));
+        assert!(script.contains("# Kept: /data/-rf.txt\n") && script.contains("# Kept: /data/b\\nkept.txt\n"));
+
+        let script: String = build_delete_script(&deletable_files, true);
+        assert!(!script.starts_with("#!") && script.contains("Remove-Item -LiteralPath '/data/it''s $HOME.txt'\n"));
+        assert_eq!(script.lines().filter(|line| line.starts_with("Remove-Item -LiteralPath ")).count(), 3);
+    }
+
+    #[test]
+    fn test_humanize_duration() {
+        assert_eq!(humanize_duration(Duration::from_secs(30)), "just now");
+        assert_eq!(humanize_duration(Duration::from_secs(60)), "1 minute ago");
+        assert_eq!(humanize_duration(Duration::from_secs(3 * 3_600 + 59)), "3 hours ago");
+        assert_eq!(humanize_duration(Duration::from_secs(86_400)), "1 day ago");
+        assert_eq!(humanize_duration(Duration::from_secs(15 * 86_400)), "2 weeks ago");
+        assert_eq!(humanize_duration(Duration::from_secs(65 * 86_400)), "2 months ago");
+        assert_eq!(humanize_duration(Duration::from_secs(800 * 86_400)), "2 years ago");
+
+        let now: SystemTime = SystemTime::now();
+        assert_eq!(humanize_age(now - Duration::from_secs(7_200), now), "2 hours ago");
+        assert_eq!(humanize_age(now + Duration::from_secs(60), now), "in the future");
+
+        // The relative times are only displayed on demand, next to each path.
+        let path: String = std::env::temp_dir().join("deepfinder_test_relative_times.txt").to_string_lossy().to_string();
+        fs::write(&path, "content").unwrap();
+        let duplicate: DuplicateFile = DuplicateFile { paths: HashSet::from([path.clone()]), name: "file.txt".to_string(), checksums: None, size: 7, sizes: BTreeMap::new() };
+        let display: String = format_simple_display(std::slice::from_ref(&duplicate), false, false, None, Some(SystemTime::now() + Duration::from_secs(7_200)));
+        assert!(display.contains(&format!(" - {path} (modified 2 hours ago)\n")));
+        assert!(!format_simple_display(std::slice::from_ref(&duplicate), false, false, None, None).contains("modified"));
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_format_simple_display_colors() {
+        let duplicates: Vec<DuplicateFile> = vec![
+            DuplicateFile {
+                name: "file1.txt".to_string(),
+                paths: ["path1".to_string(), "path2".to_string(), "path3".to_string()].into_iter().collect(),
+                size: 1024,
+                checksums: Some([("md5".to_string(), "abc".to_string())].into_iter().collect()),
+                sizes: BTreeMap::new(),
+            }
+        ];
+
+        let plain: String = format_simple_display(&duplicates, true, false, None, None);
+        assert!(!plain.contains('\x1b'));
+        assert!(plain.contains("Occurrences: 3\n"));
+        assert!(plain.contains("Checksum (md5) : abc\n"));
+        assert!(plain.ends_with("Reclaimable space: 2.0 KiB\n"));
+
+        let colored: String = format_simple_display(&duplicates, true, true, None, None);
+        assert!(colored.contains("\x1b[1;36mfile1.txt\x1b[0m"));
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(3 * 1024 * 1024 / 2), "1.5 MiB");
+    }
+
+    #[test]
+    fn test_format_template_display() {
+        let duplicates: Vec<DuplicateFile> = vec![
+            DuplicateFile {
+                name: "file1.txt".to_string(),
+                paths: ["/b/file1.txt".to_string(), "/a/file1.txt".to_string(), "/c/file1.txt".to_string()].into_iter().collect(),
+                size: 1024,
+                checksums: None,
+                sizes: BTreeMap::new(),
+            }
+        ];
+
+        let rendered: String = format_template_display(&duplicates, "{name}\t{count}\t{size}\t{wasted}", None);
+        assert_eq!(rendered, "file1.txt\t3\t1024\t2048\n");
+
+        // The "{path}" placeholder renders a line per path, the values are never rendered again.
+        // The hidden paths are counted on a last line.
+        let rendered: String = format_template_display(&duplicates, "{path} ({name}) {x}", Some(2));
+        assert_eq!(rendered, "/a/file1.txt (file1.txt) {x}\n/b/file1.txt (file1.txt) {x}\n... and 1 more\n");
+        assert_eq!(render_template("{name}", &[("name", "{name}")]), "{name}");
+
+        // The escaped braces are rendered as single braces, "{{path}}" isn't a placeholder.
+        let rendered: String = format_template_display(&duplicates, "{{\"name\": \"{name}\", \"{{path}}\": {count}}}", None);
+        assert_eq!(rendered, "{\"name\": \"file1.txt\", \"{path}\": 3}\n");
+        assert!(format_template_display(&[], "{name}", None).is_empty());
+    }
+}
@@ -0,0 +1,111 @@
+// External crates.
+use image::{DynamicImage, ImageReader, imageops::FilterType};
+use std::path::Path;
+
+/// The image extensions supported by the perceptual hashing.
+///
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "bmp", "webp"];
+
+/// This function is responsible for checking if a file is an image, according to its extension.
+///
+/// # Arguments
+///
+/// * `name` - A string slice that holds the name of the file.
+///
+/// # Returns
+///
+/// True if the file has a supported image extension, false otherwise.
+///
+pub fn is_image_file(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.iter().any(|ext| e.eq_ignore_ascii_case(ext)))
+}
+
+/// This function is responsible for computing the perceptual average hash (aHash) of an image.
+/// The image is converted to grayscale and downscaled to 8x8 pixels, then each bit of the hash
+/// tells if the corresponding pixel is brighter than the mean of the 64 pixels.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path of the image.
+///
+/// # Returns
+///
+/// The 64 bits average hash, or None if the image cannot be read or decoded.
+///
+pub fn average_hash(path: &str) -> Option<u64> {
+    let image: DynamicImage = ImageReader::open(path).ok()?.with_guessed_format().ok()?.decode().ok()?;
+    let pixels: Vec<u8> = image.grayscale().resize_exact(8, 8, FilterType::Triangle).to_luma8().into_raw();
+    let mean: u32 = pixels.iter().map(|&p| u32::from(p)).sum::<u32>() / 64;
+
+    Some(pixels.iter().enumerate().fold(0, |hash, (i, &p)| if u32::from(p) > mean { hash | (1 << i) } else { hash }))
+}
+
+/// This function is responsible for decoding the pixels of an image, without its metadata (EXIF, ICC profile, comments...).
+/// The dimensions and the color type come first, so two images with the same bytes but different shapes don't match.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path of the image.
+///
+/// # Returns
+///
+/// The decoded pixels, or None if the image cannot be read or decoded.
+///
+pub fn decoded_pixels(path: &str) -> Option<Vec<u8>> {
+    let image: DynamicImage = ImageReader::open(path).ok()?.with_guessed_format().ok()?.decode().ok()?;
+    let mut pixels: Vec<u8> = format!("{}x{}:{:?}\n", image.width(), image.height(), image.color()).into_bytes();
+    pixels.extend_from_slice(image.as_bytes());
+    Some(pixels)
+}
+
+/// This function is responsible for computing the Hamming distance between two average hashes.
+///
+/// # Arguments
+///
+/// * `a` - The first average hash.
+/// * `b` - The second average hash.
+///
+/// # Returns
+///
+/// The number of bits that differ between both hashes.
+///
+pub const fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+    use std::{env, fs, path::PathBuf};
+
+    #[test]
+    fn test_average_hash_of_resized_image() {
+        let original: GrayImage = GrayImage::from_fn(64, 64, |x, y| Luma([if (x / 16 + y / 16) % 2 == 0 { 230 } else { 20 }]));
+        let resized: GrayImage = image::imageops::resize(&original, 48, 48, FilterType::Triangle);
+        let inverted: GrayImage = GrayImage::from_fn(64, 64, |x, y| Luma([255 - original.get_pixel(x, y)[0]]));
+
+        let original_path: PathBuf = env::temp_dir().join("deepfinder_test_ahash_original.png");
+        let resized_path: PathBuf = env::temp_dir().join("deepfinder_test_ahash_resized.jpg");
+        let inverted_path: PathBuf = env::temp_dir().join("deepfinder_test_ahash_inverted.png");
+        original.save(&original_path).unwrap();
+        resized.save(&resized_path).unwrap();
+        inverted.save(&inverted_path).unwrap();
+
+        let original_hash: u64 = average_hash(original_path.to_str().unwrap()).unwrap();
+        let resized_hash: u64 = average_hash(resized_path.to_str().unwrap()).unwrap();
+        let inverted_hash: u64 = average_hash(inverted_path.to_str().unwrap()).unwrap();
+        assert!(hamming_distance(original_hash, resized_hash) <= 5);
+        assert!(hamming_distance(original_hash, inverted_hash) > 5);
+
+        assert!(is_image_file("photo.JPG"));
+        assert!(!is_image_file("notes.txt"));
+
+        for path in [original_path, resized_path, inverted_path] {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
@@ -4,9 +4,11 @@
 // Internal crates.
 use cli::FindingConfig;
 use error::DeepFinderError;
+mod archive;
 mod cli;
 mod error;
 mod export;
+mod image_hash;
 mod search_engine;
 mod system;
 
@@ -16,19 +18,43 @@ use std::process;
 /// This function is the "entry point" of the program.
 ///
 fn main() {
-    if let Err(e) = run_search() {
-        eprintln!("{e}");
-        process::exit(1);
+    match run_search() {
+        Ok(exit_code) => process::exit(exit_code),
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
     }
-
-    process::exit(0);
 }
 
-/// This function runs the search engine and returns the result.
+/// This function runs the search engine and returns the exit code.
 /// It permits the program to return an error if the search engine fails.
+/// The exit code is 2 if two compared files are different, if a file doesn't match its sidecar
+/// or if a duplicate is found with '--fail-on-duplicates', 0 otherwise.
 ///
-fn run_search() -> Result<(), DeepFinderError> {
+fn run_search() -> Result<i32, DeepFinderError> {
     let config: FindingConfig = cli::run()?;
-    search_engine::search_engine_scheduler(&config)?;
-    Ok(())
+    if let Some(bytes_per_second) = config.io_throttle {
+        system::set_io_throttle(bytes_per_second);
+    }
+
+    if config.progress_json {
+        search_engine::set_progress_json();
+    }
+
+    if let Some((file_a, file_b)) = &config.compare {
+        return Ok(if search_engine::compare_scheduler(file_a, file_b, &config)? { 0 } else { 2 });
+    }
+
+    if config.hash_stdin {
+        search_engine::hash_stdin_scheduler(&config)?;
+        return Ok(0);
+    }
+
+    if config.verify_sidecars {
+        return Ok(if search_engine::verify_sidecars_scheduler(&config)? { 0 } else { 2 });
+    }
+
+    let duplicates_found: bool = search_engine::search_engine_scheduler(&config)?;
+    Ok(if config.fail_on_duplicates && duplicates_found { 2 } else { 0 })
 }
@@ -1,25 +1,479 @@
 // Internal crates.
 use crate::{
-    cli::FindingConfig,
+    archive,
+    cli::{CliOutput, FindingConfig, KeepPolicy, Scope},
     error::{DeepFinderError, SystemError},
-    export::export_findings_scheduler,
-    system::{self, VirtualFile, build_virtual_files},
+    export::{
+        export_checkpoint, export_deletable_scheduler, format_empty_dirs, export_findings_scheduler, export_manifest_scheduler, export_script_scheduler, load_filelist, load_json_report, save_filelist,
+    },
+    image_hash,
+    system::{self, Transform, VirtualFile, build_virtual_files},
 };
 
 // External crates.
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
 use std::{
-    collections::{HashMap, HashSet},
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, hash_map::Entry},
     fs,
-    thread::{self, JoinHandle},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread::{self, ScopedJoinHandle},
+    time::{Duration, Instant},
 };
 
-#[derive(Clone, Eq, PartialEq, Serialize)]
+/// This enum represents a unit of work of the hashing threads, with the indexes of its files.
+///
+enum HashUnit {
+    /// A single file, hashed on its own.
+    File(usize),
+    /// The two files of a size shared by no other file, compared while they're hashed.
+    Pair(usize, usize),
+}
+
+/// This struct reads the chunks of a source sent by another thread, so several hash algorithms can stream the same source.
+///
+struct ChunkReader {
+    receiver: mpsc::Receiver<Arc<[u8]>>,
+    chunk: Arc<[u8]>,
+    position: usize,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.chunk.len() {
+            // The source is over once the sender is dropped.
+            let Ok(chunk) = self.receiver.recv() else {
+                return Ok(0);
+            };
+            self.chunk = chunk;
+            self.position = 0;
+        }
+
+        let count: usize = buf.len().min(self.chunk.len() - self.position);
+        buf[..count].copy_from_slice(&self.chunk[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+/// This struct represents the duration of a phase of the search (traversal, hashing, grouping), for the benchmark mode.
+///
+struct PhaseTiming {
+    name: &'static str,
+    elapsed: Duration,
+    files: usize,
+    bytes: u64,
+}
+
+/// This struct holds the results of the verification of the files against their sidecar checksum files.
+///
+#[derive(Debug, Default, Eq, PartialEq)]
+struct SidecarReport {
+    verified: usize,
+    failed: Vec<String>,
+    missing: usize,
+    unreadable: Vec<String>,
+}
+
+/// The hash algorithms that can be verified with a sidecar file, named after the extension of the sidecar.
+const SIDECAR_ALGORITHMS: [&str; 4] = ["md5", "sha1", "sha256", "sha512"];
+
+/// The minimum size of a file to be hashed with several threads, when the parallel hashing within a file is enabled.
+const PARALLEL_HASH_MIN_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The invisible characters removed from the names compared with their skeleton ('--detect-confusables').
+const INVISIBLE_CHARS: [char; 8] = ['\u{00AD}', '\u{180E}', '\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{2062}', '\u{FEFF}'];
+
+/// The homoglyphs replaced by the Latin letter they look like in the skeleton of a name (Cyrillic and Greek letters).
+const HOMOGLYPHS: [(char, char); 47] = [
+    ('а', 'a'), ('е', 'e'), ('һ', 'h'), ('і', 'i'), ('ј', 'j'), ('ӏ', 'l'), ('о', 'o'), ('р', 'p'), ('ԛ', 'q'), ('ѕ', 's'),
+    ('с', 'c'), ('ԁ', 'd'), ('у', 'y'), ('ԝ', 'w'), ('х', 'x'), ('А', 'A'), ('В', 'B'), ('С', 'C'), ('Е', 'E'), ('Н', 'H'),
+    ('І', 'I'), ('Ј', 'J'), ('К', 'K'), ('М', 'M'), ('О', 'O'), ('Р', 'P'), ('Ѕ', 'S'), ('Т', 'T'), ('Х', 'X'), ('α', 'a'),
+    ('ο', 'o'), ('ν', 'v'), ('Α', 'A'), ('Β', 'B'), ('Ε', 'E'), ('Ζ', 'Z'), ('Η', 'H'), ('Ι', 'I'), ('Κ', 'K'), ('Μ', 'M'),
+    ('Ν', 'N'), ('Ο', 'O'), ('Ρ', 'P'), ('Τ', 'T'), ('Υ', 'Y'), ('Χ', 'X'), ('ϳ', 'j'),
+];
+
+/// The size of the chunks of a source hashed with several algorithms at once (e.g. stdin with '--hash-stdin').
+const SOURCE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The maximum number of chunks waiting for each algorithm, so a slow algorithm bounds the memory used.
+const SOURCE_CHUNKS_IN_FLIGHT: usize = 16;
+
+/// The minimum interval between two progress events of the same phase ('--progress-json').
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// This struct represents a progress event, written as a single JSON line.
+/// The total is null while it's unknown (e.g. during the traversal).
+///
+#[derive(Serialize)]
+struct ProgressEvent<'a> {
+    phase: &'a str,
+    done: u64,
+    total: Option<u64>,
+    bytes: u64,
+}
+
+/// This struct holds the progress of the current phase and the writer of its events.
+///
+struct ProgressState<W: Write> {
+    writer: W,
+    phase: &'static str,
+    done: u64,
+    total: Option<u64>,
+    bytes: u64,
+    last_event: Option<Instant>,
+}
+
+/// This struct reports the progress of the phases of the search as newline-delimited JSON events, shared by all the threads.
+/// An event is written when a phase starts, at most once per interval while it progresses, and when it ends.
+///
+pub struct ProgressReporter<W: Write> {
+    state: Mutex<ProgressState<W>>,
+}
+
+impl<W: Write> ProgressReporter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { state: Mutex::new(ProgressState { writer, phase: "", done: 0, total: None, bytes: 0, last_event: None }) }
+    }
+
+    /// This method is responsible for starting a new phase, its first event is written at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - The name of the phase (e.g. "hashing").
+    /// * `total` - The number of files to process, None if it's unknown.
+    ///
+    pub fn start(&self, phase: &'static str, total: Option<u64>) {
+        if let Ok(mut state) = self.state.lock() {
+            (state.phase, state.done, state.total, state.bytes) = (phase, 0, total, 0);
+            Self::write_event(&mut state);
+        }
+    }
+
+    /// This method is responsible for recording the files processed, an event is written if the interval has elapsed.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The number of files processed.
+    /// * `bytes` - The number of bytes processed.
+    ///
+    pub fn advance(&self, files: u64, bytes: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            state.done += files;
+            state.bytes += bytes;
+            if state.last_event.is_none_or(|last| last.elapsed() >= PROGRESS_INTERVAL) {
+                Self::write_event(&mut state);
+            }
+        }
+    }
+
+    /// This method is responsible for ending the current phase, its last event (with the final total) is written at once.
+    ///
+    pub fn finish(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.total = Some(state.done);
+            Self::write_event(&mut state);
+        }
+    }
+
+    /// This function is responsible for writing the event of the current progress.
+    /// The progress is only informational, so a failed write is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The progress of the current phase, with the writer of its events.
+    ///
+    fn write_event(state: &mut ProgressState<W>) {
+        let event: ProgressEvent = ProgressEvent { phase: state.phase, done: state.done, total: state.total, bytes: state.bytes };
+        if let Ok(mut line) = serde_json::to_vec(&event) {
+            line.push(b'\n');
+            let _ = state.writer.write_all(&line).and_then(|()| state.writer.flush());
+        }
+        state.last_event = Some(Instant::now());
+    }
+}
+
+/// The progress reporter writing to stderr, the progress isn't reported if it isn't set.
+static PROGRESS: OnceLock<ProgressReporter<io::Stderr>> = OnceLock::new();
+
+/// This function is responsible for enabling the progress events on stderr, so the results on stdout are never mixed with them.
+/// It should be called once at startup, the later calls are ignored.
+///
+pub fn set_progress_json() {
+    let _ = PROGRESS.set(ProgressReporter::new(io::stderr()));
+}
+
+/// This function is responsible for reporting the progress, if the progress events are enabled.
+///
+/// # Arguments
+///
+/// * `report` - The closure updating the progress reporter.
+///
+fn report_progress<F: FnOnce(&ProgressReporter<io::Stderr>)>(report: F) {
+    if let Some(progress) = PROGRESS.get() {
+        report(progress);
+    }
+}
+
+/// This struct represents a warning raised during the search (e.g. a skipped file), so the outputs can report the fidelity of the scan.
+///
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields, description = "A warning raised during the search (e.g. a skipped file).")]
+pub struct Warning {
+    /// The kind of the warning (e.g. skipped_file).
+    pub kind: &'static str,
+    pub detail: String,
+}
+
+/// This struct collects the warnings (and the notes) raised during the search by any thread, so the outputs can report them.
+///
+#[derive(Debug, Default)]
+pub struct WarningCollector {
+    warnings: Mutex<Vec<Warning>>,
+}
+
+// The collected warnings aren't a choice of the user, so they never make two configurations different.
+impl PartialEq for WarningCollector {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for WarningCollector {}
+
+/// This struct represents a directory without any file, found by the traversal ('--report-empty-dirs').
+/// It's immediately empty if it has no entry at all, recursively empty if it only holds empty directories.
+///
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EmptyDir {
+    pub path: String,
+    pub recursive: bool,
+}
+
+/// This struct collects the empty directories found by the traversal, so they can be reported once the search is over.
+///
+#[derive(Debug, Default)]
+pub struct EmptyDirCollector {
+    empty_dirs: Mutex<Vec<EmptyDir>>,
+}
+
+// The collected directories aren't a choice of the user, so they never make two configurations different.
+impl PartialEq for EmptyDirCollector {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for EmptyDirCollector {}
+
+/// This function is responsible for recording a warning (or a note) for the outputs, and displaying it on stderr unless '--quiet' is set.
+/// The text outputs end with the warnings, so they aren't displayed on stderr as well (see warnings_in_text_output).
+/// It can be called from any thread of the search.
+///
+/// # Arguments
+///
+/// * `config` - A reference to the FindingConfig struct holding the collected warnings.
+/// * `kind` - The kind of the warning (e.g. "skipped_file").
+/// * `message` - The message of the warning, starting with "Warning: " (or "Note: ").
+///
+pub fn record_warning(config: &FindingConfig, kind: &'static str, message: String) {
+    if !config.quiet && !warnings_in_text_output(config) {
+        eprintln!("{message}");
+    }
+
+    let detail: String = message.strip_prefix("Warning: ").or_else(|| message.strip_prefix("Note: ")).unwrap_or(&message).to_string();
+    if let Ok(mut warnings) = config.warnings.warnings.lock() {
+        warnings.push(Warning { kind, detail });
+    }
+}
+
+/// This function is responsible for checking if the findings are displayed as text on stdout (the standard output,
+/// '--report summary' or '--by-dir-pairs'), which ends with the warnings of the search.
+///
+/// # Arguments
+///
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// True if the warnings are displayed at the end of a text output, false if they are only displayed on stderr.
+///
+fn warnings_in_text_output(config: &FindingConfig) -> bool {
+    let finds_duplicates: bool = config.compare.is_none()
+        && !config.hash_stdin
+        && !config.verify_sidecars
+        && !config.list_hashes
+        && !config.list_deletable
+        && config.emit_script.is_none();
+    finds_duplicates && (config.summary_report || config.by_dir_pairs || (config.output == CliOutput::Standard && !config.json_tree))
+}
+
+/// This function is responsible for taking the warnings recorded so far by the search.
+///
+/// # Arguments
+///
+/// * `config` - A reference to the FindingConfig struct holding the collected warnings.
+///
+/// # Returns
+///
+/// The warnings, in the order they have been raised.
+///
+pub fn take_warnings(config: &FindingConfig) -> Vec<Warning> {
+    config.warnings.warnings.lock().map(|mut warnings| std::mem::take(&mut *warnings)).unwrap_or_default()
+}
+
+/// This function is responsible for taking the empty directories found so far by the traversal.
+///
+/// # Arguments
+///
+/// * `config` - A reference to the FindingConfig struct holding the collected empty directories.
+///
+/// # Returns
+///
+/// The empty directories, sorted by path.
+///
+pub fn take_empty_dirs(config: &FindingConfig) -> Vec<EmptyDir> {
+    let mut empty_dirs: Vec<EmptyDir> = config.empty_dirs.empty_dirs.lock().map(|mut empty_dirs| std::mem::take(&mut *empty_dirs)).unwrap_or_default();
+    empty_dirs.sort_by(|a, b| a.path.cmp(&b.path));
+    empty_dirs
+}
+
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct DuplicateFile {
     pub paths: HashSet<String>,
     pub name: String,
-    pub checksums: Option<HashMap<String, String>>,
+    pub checksums: Option<BTreeMap<String, String>>,
     pub size: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sizes: BTreeMap<String, u64>,
+}
+
+impl DuplicateFile {
+    /// This function is responsible for adding a path to the group, with the size of its file.
+    /// The sizes are only kept by path once the files of the group differ in size (same name, same pixels or similar images),
+    /// the size of the group is then the size of its largest file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to add.
+    /// * `size` - The size of the file, in bytes.
+    ///
+    pub fn insert_path(&mut self, path: String, size: u64) {
+        if self.sizes.is_empty() && size != self.size {
+            self.sizes = self.paths.iter().map(|p| (p.clone(), self.size)).collect();
+        }
+        if !self.sizes.is_empty() {
+            self.sizes.insert(path.clone(), size);
+            self.size = self.size.max(size);
+        }
+        self.paths.insert(path);
+    }
+
+    /// This function is responsible for giving the size of a file of the group.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A string slice that holds the path of the file.
+    ///
+    /// # Returns
+    ///
+    /// The size of the file in bytes, the size of the group if the sizes aren't kept by path.
+    ///
+    pub fn path_size(&self, path: &str) -> u64 {
+        self.sizes.get(path).copied().unwrap_or(self.size)
+    }
+
+    /// This function is responsible for computing the space used by all the files of the group.
+    ///
+    /// # Returns
+    ///
+    /// The sum of the sizes of the files of the group, in bytes.
+    ///
+    pub fn total_size(&self) -> u64 {
+        self.paths.iter().map(|path| self.path_size(path)).fold(0, u64::saturating_add)
+    }
+
+    /// This function is responsible for computing the space that can be reclaimed by keeping a single file of the group.
+    /// When the sizes differ, the largest file is assumed to be kept, so the space is never overestimated.
+    ///
+    /// # Returns
+    ///
+    /// The size of the group, without one of its files, in bytes.
+    ///
+    pub fn reclaimable_size(&self) -> u64 {
+        self.total_size().saturating_sub(self.size)
+    }
+
+    /// This function is responsible for building the identity of the group, used to merge the groups of several reports.
+    /// The groups are identified by their checksums (sorted by algorithm), a name never proves that two contents are the same.
+    ///
+    /// # Returns
+    ///
+    /// A string identifying the group, None if the report has been written without its checksums.
+    ///
+    pub fn group_key(&self) -> Option<String> {
+        self.checksums.as_ref().map(checksums_key)
+    }
+
+    /// This function is responsible for replacing the checksums of the group with a single combined checksum.
+    /// The algorithms are joined with '+' and their checksums are concatenated, both in the alphabetical order of the algorithms.
+    ///
+    pub fn combine_checksums(&mut self) {
+        if let Some(checksums) = self.checksums.as_mut().filter(|c| c.len() > 1) {
+            let algorithms: String = checksums.keys().map(String::as_str).collect::<Vec<_>>().join("+");
+            let combined: String = checksums.values().map(String::as_str).collect();
+            *checksums = BTreeMap::from([(algorithms, combined)]);
+        }
+    }
+
+    /// This function is responsible for checking if the paths of the group are in at least two distinct folders.
+    ///
+    /// # Returns
+    ///
+    /// True if the group spans several parent folders, false if all its paths are in the same folder.
+    ///
+    pub fn spans_several_folders(&self) -> bool {
+        let parents: HashSet<Option<&Path>> = self.paths.iter().map(|path| Path::new(path).parent()).collect();
+        parents.len() > 1
+    }
+
+    /// This function is responsible for choosing the path to keep in the group, according to the preferred directories and the keep policy.
+    /// Only the paths in the directory with the highest priority are candidates, the keep policy chooses among them.
+    /// The ties are broken by the alphabetical order of the paths, so the choice is always the same.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The policy used to choose the path to keep.
+    /// * `prefer_dirs` - The preferred directories, from the highest priority to the lowest one.
+    ///
+    /// # Returns
+    ///
+    /// The path to keep, None if the group is empty.
+    ///
+    pub fn kept_path(&self, policy: KeepPolicy, prefer_dirs: &[String]) -> Option<&String> {
+        let modified = |path: &String| fs::metadata(path).and_then(|m| m.modified()).ok();
+        let priority = |path: &String| prefer_dirs.iter().position(|dir| Path::new(path).starts_with(dir)).unwrap_or(prefer_dirs.len());
+        let highest_priority: usize = self.paths.iter().map(priority).min()?;
+        let candidates = self.paths.iter().filter(|p| priority(p) == highest_priority);
+
+        match policy {
+            KeepPolicy::First => candidates.min(),
+            KeepPolicy::Last => candidates.max(),
+            KeepPolicy::Shortest => candidates.min_by_key(|p| (p.len(), *p)),
+            KeepPolicy::Longest => candidates.min_by_key(|p| (Reverse(p.len()), *p)),
+            // The files without modification time are never kept if another file has one.
+            KeepPolicy::Oldest => candidates.map(|p| (modified(p).is_none(), modified(p), p)).min().map(|(_, _, p)| p),
+            KeepPolicy::Newest => candidates.min_by_key(|p| (Reverse(modified(p)), *p)),
+        }
+    }
 }
 
 /// This function is the scheduler for the search engine.
@@ -30,257 +484,3473 @@ pub struct DuplicateFile {
 ///
 /// # Returns
 ///
-/// The result of the search engine scheduler, DeepFinderError otherwise.
+/// Ok(true) if at least one duplicate group has been found (a group of a single file isn't a duplicate), Ok(false) otherwise.
+/// DeepFinderError if the search or the export fails.
 ///
-pub fn search_engine_scheduler(config: &FindingConfig) -> Result<(), DeepFinderError> {
-    let file_paths: Vec<String> = search_files(&config.search_path, config.include_hidden_files).map_err(DeepFinderError::SystemError)?;
-    let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths);
-    
-    if let Some(hash_algorithms) = &config.hash {
-        hash_handler(hash_algorithms, &mut virtual_files)?;
+pub fn search_engine_scheduler(config: &FindingConfig) -> Result<bool, DeepFinderError> {
+    let mut timings: Vec<PhaseTiming> = Vec::new();
+
+    // The manifest lists the checksums of every file, without searching the duplicates.
+    if config.list_hashes {
+        let mut virtual_files: Vec<VirtualFile> = scan_virtual_files(config, &mut timings)?;
+        if let Some(length) = config.hash_truncate {
+            virtual_files.iter_mut().filter_map(|f| f.checksums.as_mut()).for_each(|checksums| system::truncate_checksums(checksums, length));
+        }
+        if config.benchmark {
+            eprint!("{}", format_benchmark(&timings));
+        }
+        export_manifest_scheduler(&virtual_files, config)?;
+        return Ok(false);
+    }
+
+    // An existing report is filtered in memory, without any new scan.
+    let duplicates: Vec<DuplicateFile> = if let Some(report_path) = &config.filter_report {
+        load_json_report(report_path).map_err(DeepFinderError::SystemError)?
+    } else if let Some(report_paths) = &config.merge_reports {
+        let reports: Vec<Vec<DuplicateFile>> = report_paths
+            .iter()
+            .map(|path| load_json_report(path))
+            .collect::<Result<_, _>>()
+            .map_err(DeepFinderError::SystemError)?;
+        merge_duplicates(reports, config)
+    } else {
+        let virtual_files: Vec<VirtualFile> = scan_virtual_files(config, &mut timings)?;
+        let start: Instant = Instant::now();
+        let duplicates: Vec<DuplicateFile> = match (config.image_ahash, config.size_tolerance) {
+            (Some(max_distance), _) => search_similar_images(&virtual_files, max_distance),
+            (None, Some(tolerance)) => {
+                record_warning(config, "approximate_groups", "Note: the groups are approximate, their files only share their name and a close size.".to_string());
+                search_approximate_duplicates(&virtual_files, tolerance, config)
+            }
+            (None, None) => {
+                if config.name_then_hash {
+                    record_differing_names(&virtual_files, config);
+                }
+                search_eventual_duplicates(&virtual_files, config)?
+            }
+        };
+        timings.push(PhaseTiming { name: "grouping", elapsed: start.elapsed(), files: virtual_files.len(), bytes: 0 });
+        duplicates
+    };
+
+    if config.benchmark {
+        eprint!("{}", format_benchmark(&timings));
+    }
+
+    let mut duplicates: Vec<DuplicateFile> = filter_duplicates(duplicates, config);
+    if config.largest_first {
+        duplicates.sort_by_key(|d| Reverse(d.size));
+    }
+    if config.detect_confusables {
+        duplicates.iter().filter_map(|d| confusable_warning(d, config)).for_each(|warning| record_warning(config, "confusable_names", warning));
+    }
+    if config.combined_hash {
+        duplicates.iter_mut().for_each(DuplicateFile::combine_checksums);
+    }
+
+    // The groups are already built, so the checksums can be truncated for the output only.
+    if let Some(length) = config.hash_truncate {
+        duplicates.iter_mut().filter_map(|d| d.checksums.as_mut()).for_each(|checksums| system::truncate_checksums(checksums, length));
+    }
+
+    if let Some(command) = &config.on_group {
+        run_group_hooks(&duplicates, command, config)?;
     }
 
-    let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&virtual_files, config);
-    export_findings_scheduler(&duplicates, config)
+    if let Some(script_path) = &config.emit_script {
+        export_script_scheduler(&duplicates, config, script_path)?;
+    } else if config.list_deletable {
+        export_deletable_scheduler(&duplicates, config)?;
+    } else {
+        export_findings_scheduler(&duplicates, &take_warnings(config), config)?;
+        if config.report_empty_dirs {
+            print!("{}", format_empty_dirs(&take_empty_dirs(config)));
+        }
+    }
 
+    Ok(duplicates.iter().any(|duplicate| duplicate.paths.len() > 1))
 }
 
-/// This function is responsible for searching files in a directory.
+/// This function is the scheduler for the comparison of two files, without any scan.
 ///
 /// # Arguments
 ///
-/// * `dir` - A string slice that holds the directory to search.
-/// * `include_hidden_files` - A boolean that indicates if hidden files should be included in the search or not.
+/// * `file_a` - The path of the first file.
+/// * `file_b` - The path of the second file.
+/// * `config` - The FindingConfig struct with the user's configuration.
 ///
 /// # Returns
 ///
-/// A vector of strings with the files found in the directory, SystemError otherwise.
+/// Ok(true) if the files are identical, Ok(false) otherwise. DeepFinderError if a file cannot be read.
 ///
-pub fn search_files(dir: &str, include_hidden_files: bool) -> Result<Vec<String>, SystemError> {
-    let mut paths: Vec<_> = fs::read_dir(dir)
-        .map_err(|e| SystemError::UnableToReadDir(e.to_string()))?
-        .filter_map(|entry| entry.ok().map(|e| e.path()))
-        .filter(|path| {
-            if !include_hidden_files
-                && let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                    return !name.starts_with('.');
-                }
-            true
-        })
-        .collect();
-    let mut files: Vec<String> = Vec::new();
-    let mut index: usize = paths.len();
-
-    paths.sort_by(|a, b| {
-        let aname: &str = a.file_name().and_then(|n| n.to_str()).unwrap_or("");
-        let bname: &str = b.file_name().and_then(|n| n.to_str()).unwrap_or("");
-        aname.cmp(bname)
-    });
+pub fn compare_scheduler(file_a: &str, file_b: &str, config: &FindingConfig) -> Result<bool, DeepFinderError> {
+    let identical: bool = compare_files(file_a, file_b, config.hash.as_deref().unwrap_or_default()).map_err(DeepFinderError::SystemError)?;
+    if identical {
+        println!("The files '{file_a}' and '{file_b}' are identical.");
+    } else {
+        println!("The files '{file_a}' and '{file_b}' are different.");
+    }
 
-    for path in &paths {
-        let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
-        index -= 1;
+    Ok(identical)
+}
 
-        if index == 0 {
-            if path.is_dir() {
-                files.extend(search_files(&format!("{dir}/{name}"), include_hidden_files)?);
-            }
-        } else if path.is_dir() {
-            files.extend(search_files(&format!("{dir}/{name}"), include_hidden_files)?);
+/// This function is the scheduler for hashing the content piped on stdin ('--hash-stdin'), without any scan.
+/// The checksum is printed alone for a single algorithm, prefixed by its algorithm otherwise (e.g. "md5:<checksum>").
+///
+/// # Arguments
+///
+/// * `config` - The FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// Ok if stdin has been hashed, DeepFinderError otherwise.
+///
+pub fn hash_stdin_scheduler(config: &FindingConfig) -> Result<(), DeepFinderError> {
+    let hash_algorithms: &[String] = config.hash.as_deref().unwrap_or_default();
+    let checksums: Vec<(String, String)> = hash_reader_with_algorithms(io::stdin().lock(), hash_algorithms).map_err(DeepFinderError::SystemError)?;
+    for (hash_algorithm, mut checksum) in checksums {
+        if let Some(length) = config.hash_truncate {
+            checksum = checksum.chars().take(length).collect();
         }
-    }
 
-    // Add the files to the vector.
-    files.extend(paths.iter().filter_map(|p| {
-        if p.is_file() {
-            p.to_str().map(ToString::to_string)
+        if hash_algorithms.len() == 1 {
+            println!("{checksum}");
         } else {
-            None
+            println!("{hash_algorithm}:{checksum}");
         }
-    }));
+    }
 
-    Ok(files)
+    Ok(())
 }
 
-/// This function is responsible for scheduling the hashing of files and the different threads.
+/// This function is responsible for hashing a source with several hash algorithms.
+/// The source can only be read once, so each of its chunks is sent to every algorithm, hashing in its own thread.
 ///
 /// # Arguments
 ///
-/// * `hash_algorithms` - A string slice that holds the directory to search.
-/// * `virtual_files` - A boolean that indicates if hidden files should be included in the search or not.
+/// * `reader` - The source to hash (e.g. stdin), it must implement the Read trait.
+/// * `hash_algorithms` - The hash algorithms to apply on the content.
 ///
 /// # Returns
 ///
-/// A vector of strings with the files found in the directory, SystemError otherwise.
+/// The checksums in the order of the algorithms (the unsupported ones are skipped), SystemError if the source cannot be read.
 ///
-fn hash_handler(hash_algorithms: &[String], virtual_files: &mut Vec<VirtualFile>) -> Result<(), DeepFinderError> {
-    let num_cores: usize = num_cpus::get(); // Get the number of logical cores.
-    let chunk_size: usize = virtual_files.len().div_ceil(num_cores);
-    let mut updated_files: Vec<VirtualFile> = Vec::new();
+fn hash_reader_with_algorithms<R: Read>(mut reader: R, hash_algorithms: &[String]) -> Result<Vec<(String, String)>, SystemError> {
+    let read_error = |e: io::Error| SystemError::UnableToReadFile("stdin".to_string(), e);
+    let mut checksums: Vec<(String, String)> = Vec::new();
+    if let [hash_algorithm] = hash_algorithms {
+        if let Some(checksum) = system::manage_hash_reader(reader, hash_algorithm).map_err(read_error)? {
+            checksums.push((hash_algorithm.to_string(), checksum));
+        }
+        return Ok(checksums);
+    }
 
-    for hash_algorithm in hash_algorithms {
-        let mut threads: Vec<JoinHandle<Vec<VirtualFile>>>= Vec::new();
-        for i in 0..num_cores {
-            let hash_algorithm: String = hash_algorithm.clone();
-            let start: usize = i * chunk_size;
-            let end: usize = ((i + 1) * chunk_size).min(virtual_files.len());
-
-            // No more files to process.
-            if start >= end {
+    thread::scope(|scope| {
+        let mut senders: Vec<mpsc::SyncSender<Arc<[u8]>>> = Vec::new();
+        let handles: Vec<ScopedJoinHandle<io::Result<Option<String>>>> = hash_algorithms
+            .iter()
+            .map(|hash_algorithm| {
+                let (sender, receiver) = mpsc::sync_channel::<Arc<[u8]>>(SOURCE_CHUNKS_IN_FLIGHT);
+                senders.push(sender);
+                let chunk_reader: ChunkReader = ChunkReader { receiver, chunk: Arc::from([]), position: 0 };
+                scope.spawn(move || system::manage_hash_reader(chunk_reader, hash_algorithm))
+            })
+            .collect();
+
+        let mut buffer: Vec<u8> = vec![0; SOURCE_CHUNK_SIZE];
+        loop {
+            let count: usize = reader.read(&mut buffer).map_err(read_error)?;
+            if count == 0 {
                 break;
             }
-
-            let mut chunk_files: Vec<VirtualFile> = virtual_files[start..end].to_vec();
-            threads.push(thread::spawn(move || {
-                for file in &mut chunk_files {
-                    if let Some(hash) = system::manage_hash(&file.full_path, &hash_algorithm) {
-                        file.update_checksum(&hash_algorithm, hash);
-                    }
-                }
-                chunk_files // Return the processed chunk.
-            }));
+            // An unsupported algorithm stops reading at once, so its closed channel is ignored.
+            let chunk: Arc<[u8]> = Arc::from(&buffer[..count]);
+            senders.iter().for_each(|sender| drop(sender.send(Arc::clone(&chunk))));
         }
+        drop(senders);
 
-        for thread in threads {
-            let chunk: Vec<VirtualFile> = thread.join().map_err(|_| DeepFinderError::SystemError(SystemError::ThreadError))?;
-            for file in chunk {
-                if let Some(existing_file) = updated_files.iter_mut().find(|f| f.full_path == file.full_path) {
-                    // If the file already exists, update its checksums.
-                    if let Some(checksums) = &file.checksums {
-                        existing_file.update_checksum(hash_algorithm, checksums.get(hash_algorithm.as_str()).cloned().unwrap_or_default());
-                    }
-                } else {
-                    // Otherwise, add the new file to the updated files.
-                    updated_files.push(file);
-                }
+        for (hash_algorithm, handle) in hash_algorithms.iter().zip(handles) {
+            if let Some(checksum) = handle.join().map_err(|_| SystemError::ThreadError)?.map_err(read_error)? {
+                checksums.push((hash_algorithm.to_string(), checksum));
             }
         }
+        Ok(checksums)
+    })
+}
+
+/// This function is the scheduler for the verification of the files against their sidecar checksum files ('--verify-sidecars').
+///
+/// # Arguments
+///
+/// * `config` - The FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// Ok(true) if every file with a sidecar has been verified, Ok(false) if a file is corrupted or a file or sidecar cannot be read.
+/// DeepFinderError if the files cannot be listed.
+///
+pub fn verify_sidecars_scheduler(config: &FindingConfig) -> Result<bool, DeepFinderError> {
+    let file_paths: Vec<String> = search_files(&config.search_path, config).map_err(DeepFinderError::SystemError)?;
+    let report: SidecarReport = verify_sidecars(&file_paths)?;
+    for path in &report.failed {
+        println!("FAILED: {path}");
+    }
+    for path in &report.unreadable {
+        println!("UNREADABLE: {path}");
     }
+    println!(
+        "Sidecars: {} verified, {} failed, {} missing, {} unreadable",
+        report.verified,
+        report.failed.len(),
+        report.missing,
+        report.unreadable.len()
+    );
 
-    *virtual_files = updated_files;
-    Ok(())
+    Ok(report.failed.is_empty() && report.unreadable.is_empty())
 }
 
-/// This function is responsible for searching eventual duplicates in the virtual files.
+/// This function is responsible for verifying the files against their sidecar checksum files, in scoped threads.
+/// The sidecar of a file is named after it with the algorithm as extension (e.g. "file.iso.sha256"), a file can have several of them.
+/// The sidecar files themselves are not verified. A file that cannot be read, or whose sidecar cannot be, is reported as unreadable.
 ///
 /// # Arguments
 ///
-/// * `virtual_files` - A slice of VirtualFile.
-/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+/// * `file_paths` - The paths of the files to verify.
 ///
 /// # Returns
 ///
-/// A vector of DuplicateFile containing the duplicates found.
+/// The SidecarReport with the verified, failed (sorted), missing and unreadable (sorted) files, DeepFinderError if a thread panics.
 ///
-fn search_eventual_duplicates(virtual_files: &[VirtualFile], config: &FindingConfig) -> Vec<DuplicateFile> {
-    let mut map: HashMap<String, DuplicateFile> = HashMap::new();
+fn verify_sidecars(file_paths: &[String]) -> Result<SidecarReport, DeepFinderError> {
+    let chunk_size: usize = file_paths.len().div_ceil(worker_count()).max(1);
+    let mut report: SidecarReport = thread::scope(|scope| {
+        let threads: Vec<ScopedJoinHandle<SidecarReport>> = file_paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut report: SidecarReport = SidecarReport::default();
+                    let files = chunk.iter().filter(|path| !SIDECAR_ALGORITHMS.iter().any(|algorithm| path.ends_with(&format!(".{algorithm}"))));
+                    for path in files {
+                        match verify_with_sidecars(path) {
+                            Ok(Some(true)) => report.verified += 1,
+                            Ok(Some(false)) => report.failed.push(path.to_string()),
+                            Ok(None) => report.missing += 1,
+                            Err(_) => report.unreadable.push(path.to_string()),
+                        }
+                    }
+                    report
+                })
+            })
+            .collect();
 
-    for file in virtual_files {
-        let key: String = if config.enable_search_by_name {
-            file.name.clone()
-        } else if let Some(checksums) = &file.checksums {
-            checksums.iter().map(|(k, v)| format!("{k}:{v}")).collect::<Vec<_>>().join("|")
-        } else {
-            continue;
-        };
+        threads.into_iter().try_fold(SidecarReport::default(), |mut report, thread| {
+            let partial: SidecarReport = thread.join().map_err(|_| DeepFinderError::SystemError(SystemError::ThreadError))?;
+            report.verified += partial.verified;
+            report.failed.extend(partial.failed);
+            report.missing += partial.missing;
+            report.unreadable.extend(partial.unreadable);
+            Ok(report)
+        })
+    })?;
 
-        let entry: &mut DuplicateFile = map.entry(key).or_insert_with(|| DuplicateFile {
-            paths: HashSet::new(),
-            name: file.name.clone(),
-            checksums: file.checksums.clone(),
-            size: file.size,
-        });
+    report.failed.sort();
+    report.unreadable.sort();
+    Ok(report)
+}
+
+/// This function is responsible for verifying a file against all its sidecar checksum files.
+/// The expected checksum is the first word of the sidecar (e.g. "<checksum>  file.iso"), compared without case.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to verify.
+///
+/// # Returns
+///
+/// Some(true) if every checksum matches, Some(false) if one of them doesn't, None if the file has no sidecar.
+/// SystemError if the file or a sidecar cannot be read.
+///
+fn verify_with_sidecars(path: &str) -> Result<Option<bool>, SystemError> {
+    let mut verified: Option<bool> = None;
+    for algorithm in SIDECAR_ALGORITHMS {
+        let sidecar_path: String = format!("{path}.{algorithm}");
+        if !Path::new(&sidecar_path).is_file() {
+            continue;
+        }
 
-        entry.paths.insert(file.full_path.clone());
+        let sidecar: String = fs::read_to_string(&sidecar_path).map_err(|e| SystemError::UnableToReadFile(sidecar_path.clone(), e))?;
+        let expected: String = sidecar.split_whitespace().next().unwrap_or_default().to_lowercase();
+        let matches: bool = system::manage_hash(path, algorithm)?.is_some_and(|checksum| checksum.to_lowercase() == expected);
+        verified = Some(verified.unwrap_or(true) && matches);
     }
 
-    // Filter out entries with only one occurrence.
-    map.retain(|_, v| v.paths.len() > 1);
-    map.into_values().collect()
+    Ok(verified)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::collections::HashMap;
-    use crate::cli::CliOutput;
+/// This function is responsible for checking if two files are identical.
+/// The files are compared byte by byte, or with their checksums if some hash algorithms are given.
+///
+/// # Arguments
+///
+/// * `file_a` - The path of the first file.
+/// * `file_b` - The path of the second file.
+/// * `hash_algorithms` - The hash algorithms used to compare the files, empty for a byte by byte comparison.
+///
+/// # Returns
+///
+/// True if the files are identical, false otherwise. SystemError if a file or its metadata cannot be read.
+///
+fn compare_files(file_a: &str, file_b: &str, hash_algorithms: &[String]) -> Result<bool, SystemError> {
+    let file_len = |path: &str| fs::metadata(path).map(|m| m.len()).map_err(|e| SystemError::UnableToReadFile(path.to_string(), e));
 
-    #[test]
-    fn test_search_eventual_duplicates_by_name() {
+    // Two files with different sizes can't be identical, nothing has to be read.
+    if file_len(file_a)? != file_len(file_b)? {
+        return Ok(false);
+    }
+
+    if hash_algorithms.is_empty() {
+        return system::files_equal(file_a, file_b);
+    }
+
+    for hash_algorithm in hash_algorithms {
+        if system::manage_hash(file_a, hash_algorithm)? != system::manage_hash(file_b, hash_algorithm)? {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// This function is responsible for scanning the search path and building the virtual files, hashed if necessary.
+/// The duration of the traversal and of the hashing are added to the timings (except for an archive, scanned at once).
+///
+/// # Arguments
+///
+/// * `config` - The FindingConfig struct with the user's configuration.
+/// * `timings` - The timings of the phases of the search, used by the benchmark mode.
+///
+/// # Returns
+///
+/// A vector of VirtualFile containing the files found, DeepFinderError otherwise.
+///
+fn scan_virtual_files(config: &FindingConfig, timings: &mut Vec<PhaseTiming>) -> Result<Vec<VirtualFile>, DeepFinderError> {
+    // An archive is scanned through its entries, without being extracted to the disk.
+    if archive::is_supported_archive(&config.search_path) {
+        return archive::build_archive_virtual_files(&config.search_path, config.include_hidden_files, config.hash.as_deref().unwrap_or_default())
+            .map_err(DeepFinderError::SystemError);
+    }
+
+    if config.parallel_hash_within_file && config.hash.as_ref().is_some_and(|h| h.iter().any(|a| a != "blake3")) {
+        record_warning(config, "sequential_hash", "Note: only blake3 can hash a single file with several threads, the other algorithms are computed sequentially.".to_string());
+    }
+
+    let start: Instant = Instant::now();
+    // A recorded file list replaces the traversal, the same files are hashed and grouped again.
+    // So does a NUL-delimited list of paths piped on stdin ('--from-stdin0').
+    let mut file_paths: Vec<String> = match &config.replay_filelist {
+        Some(filelist_path) => load_filelist(filelist_path).map_err(DeepFinderError::SystemError)?,
+        None if config.from_stdin0 => read_nul_delimited_paths(io::stdin().lock(), config).map_err(DeepFinderError::SystemError)?,
+        None => search_files(&config.search_path, config).map_err(DeepFinderError::SystemError)?,
+    };
+    if config.read_only && config.search_path.is_empty() {
+        check_read_only_paths(&file_paths, config)?;
+    }
+    if let Some(filelist_path) = &config.record_filelist {
+        save_filelist(&file_paths, filelist_path, config.temp_dir.as_deref())?;
+    }
+    for output_path in [config.output.file_path(), config.emit_script.as_deref()].into_iter().flatten() {
+        if let Some(warning) = output_collision_warning(output_path, &file_paths, system::is_case_insensitive(output_path)) {
+            record_warning(config, "output_collision", warning);
+        }
+    }
+
+    // The symbolic links listed as entries are never hashed, they are added back once the files are hashed.
+    let mut symlinks: Vec<VirtualFile> = Vec::new();
+    if config.include_symlinks_as_entries {
+        let link_paths: Vec<String>;
+        (link_paths, file_paths) = file_paths.into_iter().partition(|path| Path::new(path).is_symlink());
+        symlinks = system::build_symlink_entries(&link_paths);
+    }
+
+    let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, config.content_type.as_deref()).map_err(DeepFinderError::SystemError)?;
+    if config.unique_content {
+        let collapsed_paths: usize = collapse_physical_files(&mut virtual_files);
+        if collapsed_paths > 0 {
+            record_warning(config, "collapsed_paths", format!("Note: {collapsed_paths} paths lead to files already scanned (hard links, bind mounts), each physical file is counted once."));
+        }
+    }
+    if config.name_then_hash {
+        // Only the files sharing their name with another file are hashed.
+        let mut name_counts: HashMap<String, usize> = HashMap::new();
+        virtual_files.iter().for_each(|f| *name_counts.entry(name_key(f, config)).or_default() += 1);
+        virtual_files.retain(|f| name_counts[&name_key(f, config)] > 1);
+    }
+    if config.largest_first {
+        // The stable sort keeps the order of the traversal for the files of the same size.
+        virtual_files.sort_by_key(|f| Reverse(f.size));
+    }
+    timings.push(PhaseTiming { name: "traversal", elapsed: start.elapsed(), files: virtual_files.len(), bytes: 0 });
+
+    let start: Instant = Instant::now();
+
+    let budget_exceeded: bool = if config.image_ahash.is_some() {
+        // Only the images are compared with their perceptual hash.
+        virtual_files.retain(|f| image_hash::is_image_file(&f.name));
+        hash_handler(&["ahash".to_string()], &mut virtual_files, config)?
+    } else if let (Some(prefilter), Some(hash_algorithms)) = (&config.prefilter, &config.hash) {
+        let budget_exceeded: bool;
+        (virtual_files, budget_exceeded) = prefilter_handler(prefilter, hash_algorithms, virtual_files, config)?;
+        budget_exceeded
+    } else if let Some(hash_algorithms) = &config.hash {
+        hash_handler(hash_algorithms, &mut virtual_files, config)?
+    } else {
+        false
+    };
+
+    let hashed_files: Vec<&VirtualFile> = virtual_files.iter().filter(|f| f.checksums.is_some()).collect();
+    timings.push(PhaseTiming {
+        name: "hashing",
+        elapsed: start.elapsed(),
+        files: hashed_files.len(),
+        bytes: hashed_files.iter().map(|f| f.size).sum(),
+    });
+
+    if budget_exceeded {
+        record_warning(config, "byte_budget", "Warning: the byte budget has been exceeded, some files have not been hashed and the results are partial.".to_string());
+    }
+
+    virtual_files.append(&mut symlinks);
+    Ok(virtual_files)
+}
+
+/// This function is responsible for formatting the timings of the phases of the search, for the benchmark mode.
+///
+/// # Arguments
+///
+/// * `timings` - The timings of the phases of the search.
+///
+/// # Returns
+///
+/// One line per phase with its duration and throughput, followed by the number of cores used.
+///
+#[allow(clippy::cast_precision_loss)]
+fn format_benchmark(timings: &[PhaseTiming]) -> String {
+    let mut output: String = String::new();
+    for timing in timings {
+        let seconds: f64 = timing.elapsed.as_secs_f64().max(f64::EPSILON);
+        output += &format!("Benchmark: {:<9} {:>10.3} s, {} files ({:.0} files/s)", timing.name, timing.elapsed.as_secs_f64(), timing.files, timing.files as f64 / seconds);
+        if timing.bytes > 0 {
+            output += &format!(", {:.1} MB/s", timing.bytes as f64 / 1_000_000.0 / seconds);
+        }
+        output += "\n";
+    }
+
+    output + &format!("Benchmark: {} cores used\n", worker_count())
+}
+
+/// This function is responsible for reading the paths of the files to search from a NUL-delimited list ('--from-stdin0').
+/// The list is typically the output of `find -print0` or `fd -0`, so the paths can contain newlines.
+/// The paths that aren't valid UTF-8 or that aren't regular files are reported as skipped.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the list (e.g. stdin), it must implement the Read trait.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// The paths of the files, in the order of the list, SystemError if the source cannot be read.
+///
+fn read_nul_delimited_paths<R: Read>(mut reader: R, config: &FindingConfig) -> Result<Vec<String>, SystemError> {
+    let mut input: Vec<u8> = Vec::new();
+    reader.read_to_end(&mut input).map_err(|e| SystemError::UnableToReadFile("stdin".to_string(), e))?;
+
+    let mut file_paths: Vec<String> = Vec::new();
+    for entry in input.split(|&byte| byte == 0).filter(|entry| !entry.is_empty()) {
+        match String::from_utf8(entry.to_vec()) {
+            Ok(path) if Path::new(&path).is_file() => file_paths.push(path),
+            Ok(path) => record_warning(config, "skipped_file", format!("Warning: '{path}' isn't a regular file, it has been skipped.")),
+            Err(_) => record_warning(config, "skipped_file", format!("Warning: the path '{}' isn't valid UTF-8, it has been skipped.", String::from_utf8_lossy(entry))),
+        }
+    }
+
+    Ok(file_paths)
+}
+
+/// This function is responsible for searching files in a directory.
+/// The tree is traversed with an explicit stack instead of a recursion, so its depth is only limited by the memory.
+/// The files of the sub-folders (in alphabetical order) are listed before the files of their parent folder.
+///
+/// # Arguments
+///
+/// * `dir` - A string slice that holds the directory to search.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// A vector of strings with the files found in the directory, SystemError otherwise.
+///
+pub fn search_files(dir: &str, config: &FindingConfig) -> Result<Vec<String>, SystemError> {
+    report_progress(|progress| progress.start("traversal", None));
+    let mut files: Vec<String> = Vec::new();
+    let (mut top_folders, top_files): (Vec<String>, Vec<String>) = read_folder(dir, config)?;
+
+    // The top-level folders can be skipped by name ('--skip-top'), unlike the deeper ones.
+    top_folders.retain(|folder| !config.skip_top.iter().any(|name| folder.rsplit('/').next() == Some(name)));
+    let mut stack: Vec<(Vec<String>, Vec<String>)> = vec![(top_folders, top_files)];
+
+    // The size of each folder of the stack (files of its traversed sub-folders included) and the number of files found before it.
+    let mut folder_sizes: Vec<(usize, u64)> = vec![(0, 0)];
+
+    // The path and number of entries of each folder of the stack, with its number of empty sub-folders ('--report-empty-dirs').
+    // A folder is empty if all its entries are empty sub-folders, so a hidden or excluded entry keeps it from being empty.
+    let count_entries = |folder: &str| fs::read_dir(folder).map_or(usize::MAX, Iterator::count);
+    let mut folder_entries: Vec<(String, usize, usize)> = Vec::new();
+    if config.report_empty_dirs {
+        folder_entries.push((dir.to_string(), count_entries(dir), 0));
+    }
+
+    // When the reparse points are followed, a folder reached twice (e.g. through a junction to an ancestor) is traversed once.
+    let mut visited_folders: HashSet<PathBuf> = HashSet::new();
+    if config.follow_reparse_points {
+        visited_folders.extend(fs::canonicalize(dir));
+    }
+
+    while let Some((sub_folders, _)) = stack.last_mut() {
+        if let Some(sub_folder) = sub_folders.pop() {
+            if config.follow_reparse_points && !fs::canonicalize(&sub_folder).is_ok_and(|folder| visited_folders.insert(folder)) {
+                continue;
+            }
+            stack.push(read_folder(&sub_folder, config)?);
+            folder_sizes.push((files.len(), 0));
+            if config.report_empty_dirs {
+                folder_entries.push((sub_folder.clone(), count_entries(&sub_folder), 0));
+            }
+        } else if let Some((_, folder_files)) = stack.pop() {
+            // Every sub-folder has been traversed, the files of the folder come after theirs.
+            report_progress(|progress| progress.advance(folder_files.len() as u64, 0));
+            if let Some((path, entries, empty_sub_folders)) = folder_entries.pop()
+                && entries == empty_sub_folders
+            {
+                if let Some((_, _, parent_empty_sub_folders)) = folder_entries.last_mut() {
+                    *parent_empty_sub_folders += 1;
+                }
+                if let Ok(mut empty_dirs) = config.empty_dirs.empty_dirs.lock() {
+                    empty_dirs.push(EmptyDir { path, recursive: entries > 0 });
+                }
+            }
+            let (first_file, sub_folders_size): (usize, u64) = folder_sizes.pop().unwrap_or_default();
+
+            // A folder smaller than '--min-dir-size' is pruned with its whole subtree, the search path itself is always kept.
+            if let Some(min_dir_size) = config.min_dir_size {
+                let folder_size: u64 = sub_folders_size + folder_files.iter().map(|file| fs::metadata(file).map_or(0, |m| m.len())).sum::<u64>();
+                if let Some((_, parent_size)) = folder_sizes.last_mut() {
+                    *parent_size += folder_size;
+                    if folder_size < min_dir_size {
+                        files.truncate(first_file);
+                        continue;
+                    }
+                }
+            }
+            files.extend(folder_files);
+        }
+    }
+
+    report_progress(ProgressReporter::finish);
+    Ok(files)
+}
+
+/// This function is responsible for reading the entries of a single folder, without any recursion.
+/// The entries are filtered (hidden, excluded and skipped ones) before being returned, so a filtered folder is never descended.
+/// The folders that are reparse points (e.g. junctions on Windows) are skipped, unless they are followed.
+/// The symbolic links are returned as files (and never descended) when they are listed as entries.
+///
+/// # Arguments
+///
+/// * `dir` - A string slice that holds the folder to read.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// The sub-folders (in reverse alphabetical order, ready to be popped) and the files of the folder, SystemError otherwise.
+///
+fn read_folder(dir: &str, config: &FindingConfig) -> Result<(Vec<String>, Vec<String>), SystemError> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::PermissionDenied => SystemError::AccessDenied(dir.to_string()),
+            _ => SystemError::UnableToReadDir(dir.to_string(), e),
+        })?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| is_included(path, config))
+        .collect();
+
+    // The broken symbolic links and the names that aren't valid UTF-8 can't be scanned, they are reported as skipped.
+    for path in &paths {
+        if path.is_symlink() && !path.exists() && !config.include_symlinks_as_entries {
+            record_warning(config, "skipped_file", format!("Warning: the symbolic link '{}' is broken, it has been skipped.", path.display()));
+        } else if path.to_str().is_none() {
+            record_warning(config, "skipped_file", format!("Warning: the name of '{}' isn't valid UTF-8, it has been skipped.", path.display()));
+        }
+    }
+
+    paths.sort_by(|a, b| {
+        let aname: &str = a.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let bname: &str = b.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        aname.cmp(bname)
+    });
+
+    let sub_folders: Vec<String> = paths
+        .iter()
+        .rev()
+        .filter(|p| p.is_dir() && !is_skipped_folder(p, config) && (config.follow_reparse_points || !is_reparse_point(p)))
+        .filter(|p| !(config.include_symlinks_as_entries && p.is_symlink()))
+        .filter_map(|p| p.file_name().and_then(|n| n.to_str()).map(|name| format!("{}/{name}", dir.trim_end_matches(['/', '\\']))))
+        .collect();
+
+    let files: Vec<String> = paths.iter().filter_map(|p| {
+        if (p.is_file() && !(config.exclude_stubs && is_cloud_stub(p))) || (config.include_symlinks_as_entries && p.is_symlink()) {
+            p.to_str().map(ToString::to_string)
+        } else {
+            None
+        }
+    }).collect();
+
+    Ok((sub_folders, files))
+}
+
+/// This function is responsible for checking if an entry (file or folder) must be scanned, according to the user's filters.
+/// The hidden and exclude rules apply the same way to the files and the folders.
+/// The glob patterns are matched against the name of the entry, the regular expressions against its full path.
+///
+/// # Arguments
+///
+/// * `path` - The path of the entry to check.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// True if the entry must be scanned, false if it's hidden (and the hidden files are excluded) or excluded.
+///
+fn is_included(path: &Path, config: &FindingConfig) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else { return false };
+    (config.include_hidden_files || !is_hidden(path))
+        && !config.exclude.iter().any(|pattern| matches_glob(pattern, name))
+        && !config.exclude_regex.iter().any(|regex| regex.0.is_match(&path.to_string_lossy()))
+}
+
+/// This function is responsible for checking if a folder is one of the directories skipped by the user ('--skip-dir').
+/// The folder is canonicalized, so it matches a skipped directory even through a symbolic link or a relative path.
+///
+/// # Arguments
+///
+/// * `path` - The path of the folder to check.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// True if the folder (and so its whole subtree) must be skipped, false otherwise.
+///
+fn is_skipped_folder(path: &Path, config: &FindingConfig) -> bool {
+    !config.skip_dirs.is_empty()
+        && fs::canonicalize(path).is_ok_and(|canonical_path| config.skip_dirs.iter().any(|skip_dir| Path::new(skip_dir) == canonical_path))
+}
+
+/// This function is responsible for matching a name against a glob pattern.
+/// The '*' wildcard matches any sequence of characters (even empty), the '?' wildcard matches a single character.
+///
+/// # Arguments
+///
+/// * `pattern` - A string slice that holds the glob pattern (e.g. "*.tmp").
+/// * `name` - A string slice that holds the name to match.
+///
+/// # Returns
+///
+/// True if the whole name matches the pattern, false otherwise.
+///
+fn matches_glob(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n): (usize, usize) = (0, 0);
+    let mut last_star: Option<(usize, usize)> = None; // Position of the last '*' and the name index it matches up to.
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            last_star = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = last_star {
+            // The last '*' absorbs one more character, then the matching starts again after it.
+            last_star = Some((star, matched + 1));
+            p = star + 1;
+            n = matched + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// This function is responsible for checking that nothing is written on the filesystems of the listed files ('--read-only').
+/// The files of '--from-stdin0' and '--replay-filelist' have no search path, so the paths written are checked against their folders.
+///
+/// # Arguments
+///
+/// * `file_paths` - The paths of the listed files.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// Ok if every path written is on another filesystem (and the temporary directory is writable), SystemError otherwise.
+///
+fn check_read_only_paths(file_paths: &[String], config: &FindingConfig) -> Result<(), DeepFinderError> {
+    let folders: HashSet<&Path> = file_paths.iter().filter_map(|path| Path::new(path).parent()).collect();
+    for path in [config.output.file_path(), config.record_filelist.as_deref(), config.temp_dir.as_deref()].into_iter().flatten() {
+        if folders.iter().any(|folder| system::is_same_filesystem(path, &folder.to_string_lossy())) {
+            return Err(DeepFinderError::SystemError(SystemError::WriteOnScannedFilesystem(path.to_string())));
+        }
+    }
+
+    if let Some(temp_dir) = &config.temp_dir {
+        system::check_writable_folder(temp_dir).map_err(DeepFinderError::SystemError)?;
+    }
+    Ok(())
+}
+
+/// This function is responsible for building the warning about an output file that would overwrite one of the scanned files.
+/// Both paths are canonicalized, and compared case-insensitively if the filesystem of the output ignores the case (e.g. on macOS and Windows).
+///
+/// # Arguments
+///
+/// * `output_path` - The resolved path of the output file.
+/// * `file_paths` - The paths of the scanned files.
+/// * `case_insensitive` - A boolean that indicates if the filesystem of the output file ignores the case of the names.
+///
+/// # Returns
+///
+/// The warning with the scanned file matching the output file, None if there isn't any.
+///
+fn output_collision_warning(output_path: &str, file_paths: &[String], case_insensitive: bool) -> Option<String> {
+    // The output file may not exist yet, so its folder is canonicalized instead.
+    let canonicalize = |path: &str| -> PathBuf {
+        let path: &Path = Path::new(path);
+        fs::canonicalize(path)
+            .or_else(|_| match (path.parent(), path.file_name()) {
+                (Some(parent), Some(name)) => fs::canonicalize(parent).map(|parent| parent.join(name)),
+                _ => Err(io::ErrorKind::NotFound.into()),
+            })
+            .unwrap_or_else(|_| path.to_path_buf())
+    };
+    let same_name = |first: &str, second: &str| if case_insensitive { first.to_lowercase() == second.to_lowercase() } else { first == second };
+
+    let output: PathBuf = canonicalize(output_path);
+    let output_name: &str = output.file_name().and_then(|n| n.to_str())?;
+    let (path, exact): (&String, bool) = file_paths
+        .iter()
+        .filter(|path| Path::new(path).file_name().and_then(|n| n.to_str()).is_some_and(|name| same_name(name, output_name)))
+        .find_map(|path| {
+            let scanned: PathBuf = canonicalize(path);
+            let exact: bool = scanned == output;
+            (exact || same_name(&scanned.to_string_lossy(), &output.to_string_lossy())).then_some((path, exact))
+        })?;
+
+    if exact {
+        Some(format!("Warning: the output file '{output_path}' is the scanned file '{path}', it will be overwritten."))
+    } else {
+        Some(format!("Warning: the output file '{output_path}' is the scanned file '{path}' on this case-insensitive filesystem, it will be overwritten."))
+    }
+}
+
+/// This function is responsible for building the warning about a file whose hashing has been too slow ('--slow-file-threshold').
+/// A slow file often reveals a failing disk (bad sectors) or a stalled network share.
+///
+/// # Arguments
+///
+/// * `path` - The path of the hashed file.
+/// * `size` - The size of the file, in bytes.
+/// * `elapsed` - The duration of the hashing of the file (every algorithm included).
+/// * `threshold` - The duration above which the hashing is too slow.
+///
+/// # Returns
+///
+/// The warning with the path and the throughput of the file if its hashing exceeded the threshold, None otherwise.
+///
+#[allow(clippy::cast_precision_loss)]
+fn slow_file_warning(path: &str, size: u64, elapsed: Duration, threshold: Duration) -> Option<String> {
+    (elapsed > threshold).then(|| {
+        let throughput: f64 = size as f64 / 1_000_000.0 / elapsed.as_secs_f64();
+        format!("Warning: slow file '{path}', hashed in {:.3} s ({throughput:.3} MB/s).", elapsed.as_secs_f64())
+    })
+}
+
+/// This function is responsible for checking if a file or a folder is hidden.
+/// A name starting with a dot is hidden on every platform, the hidden attribute is also checked on Windows.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file or folder to check.
+///
+/// # Returns
+///
+/// True if the file or folder is hidden, false otherwise.
+///
+fn is_hidden(path: &Path) -> bool {
+    if path.file_name().and_then(|n| n.to_str()).is_some_and(|name| name.starts_with('.')) {
+        return true;
+    }
+
+    #[cfg(target_family = "windows")]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        return fs::metadata(path).is_ok_and(|m| m.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0);
+    }
+
+    #[cfg(not(target_family = "windows"))]
+    false
+}
+
+/// This function is responsible for keeping a single path per physical file, so the same bytes aren't counted twice.
+/// The first path of the traversal is kept, the other paths leading to the same device and inode are removed.
+///
+/// # Arguments
+///
+/// * `virtual_files` - The files found by the traversal.
+///
+/// # Returns
+///
+/// The number of paths removed.
+///
+fn collapse_physical_files(virtual_files: &mut Vec<VirtualFile>) -> usize {
+    let mut physical_files: HashSet<(u64, u64)> = HashSet::new();
+    let count: usize = virtual_files.len();
+    virtual_files.retain(|f| system::physical_file_id(&f.full_path).is_none_or(|id| physical_files.insert(id)));
+    count - virtual_files.len()
+}
+
+/// This function is responsible for checking if a folder is a reparse point (junction, symbolic link, mount point...).
+/// The reparse points behave like symbolic links, so they can lead to folders counted twice or to loops.
+/// Only Windows has reparse points.
+///
+/// # Arguments
+///
+/// * `path` - The path of the folder to check.
+///
+/// # Returns
+///
+/// True if the folder is a reparse point, false otherwise.
+///
+fn is_reparse_point(path: &Path) -> bool {
+    #[cfg(target_family = "windows")]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+        return fs::symlink_metadata(path).is_ok_and(|m| m.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0);
+    }
+
+    #[cfg(not(target_family = "windows"))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// This function is responsible for checking if a file is a cloud placeholder (OneDrive, Dropbox...), whose content isn't on the disk.
+/// On Windows, the offline and recall attributes are checked. On Unix, a non-empty file without any allocated block
+/// is considered as a placeholder (an empty file is a real file).
+/// Reading a placeholder would download its content, so it is never hashed.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to check.
+///
+/// # Returns
+///
+/// True if the file is a cloud placeholder, false otherwise.
+///
+fn is_cloud_stub(path: &Path) -> bool {
+    #[cfg(target_family = "windows")]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+        const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x40000;
+        const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+        let stub_attributes: u32 = FILE_ATTRIBUTE_OFFLINE | FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS;
+        return fs::metadata(path).is_ok_and(|m| m.file_attributes() & stub_attributes != 0);
+    }
+
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::MetadataExt;
+        fs::metadata(path).is_ok_and(|m| m.size() > 0 && m.blocks() == 0)
+    }
+
+    #[cfg(not(any(target_family = "windows", target_family = "unix")))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// This function is responsible for getting the number of threads used to process the files, one per logical core.
+/// A restricted environment (e.g. a container limited by its cgroups) can report no core at all, so at least one thread is used.
+///
+/// # Returns
+///
+/// The number of threads, at least 1.
+///
+fn worker_count() -> usize {
+    num_cpus::get().max(1)
+}
+
+/// This function is responsible for scheduling the hashing of files with one thread per logical core.
+///
+/// # Arguments
+///
+/// * `hash_algorithms` - A slice of the hash algorithms to use.
+/// * `virtual_files` - A mutable slice of VirtualFile to hash, their checksums are updated in place.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// Ok(true) if the byte budget has been exceeded (some files have not been hashed), Ok(false) otherwise.
+/// A file that can't be read is skipped with a warning, DeepFinderError if a thread has failed.
+///
+fn hash_handler(hash_algorithms: &[String], virtual_files: &mut [VirtualFile], config: &FindingConfig) -> Result<bool, DeepFinderError> {
+    report_progress(|progress| progress.start("hashing", Some(virtual_files.len() as u64)));
+    let checkpoint_interval: Option<Duration> = config.checkpoint_interval.map(Duration::from_secs);
+    let budget_exceeded: bool = hash_with_workers(hash_algorithms, virtual_files, config, worker_count(), checkpoint_interval, Instant::now)?;
+    report_progress(ProgressReporter::finish);
+    Ok(budget_exceeded)
+}
+
+/// This function is responsible for writing the duplicates confirmed by the files hashed so far to the output file ('--checkpoint-interval').
+///
+/// # Arguments
+///
+/// * `virtual_files` - A slice of VirtualFile being hashed.
+/// * `results` - The checksums of the files hashed so far, with the indexes of the files.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// Ok(()) if the checkpoint has been written, DeepFinderError otherwise.
+///
+fn write_checkpoint(virtual_files: &[VirtualFile], results: &[(usize, Option<BTreeMap<String, String>>)], config: &FindingConfig) -> Result<(), DeepFinderError> {
+    let hashed_files: Vec<VirtualFile> = results
+        .iter()
+        .filter(|(_, checksums)| checksums.is_some())
+        .map(|(index, checksums)| VirtualFile { checksums: checksums.clone(), ..virtual_files[*index].clone() })
+        .collect();
+    let mut duplicates: Vec<DuplicateFile> = filter_duplicates(search_eventual_duplicates(&hashed_files, config)?, config);
+    if config.largest_first {
+        duplicates.sort_by_key(|d| Reverse(d.size));
+    }
+    export_checkpoint(&duplicates, config)
+}
+
+/// This function is responsible for telling if the pairs of files with the same size are compared while they're hashed.
+/// It's not possible when every file needs its checksum (e.g. the inventory of all the files),
+/// nor when the content isn't hashed as is (transformed, case-folded or decoded pixels).
+///
+/// # Arguments
+///
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// True if the pairs of files with the same size are compared, false if every file is hashed on its own.
+///
+fn compares_size_pairs(config: &FindingConfig) -> bool {
+    let every_checksum: bool = config.enable_search_by_name || config.list_hashes || config.all_files || config.name_then_hash;
+    let raw_content: bool = config.transform == Transform::None && !config.content_case_insensitive && !config.ignore_metadata && config.image_ahash.is_none();
+    !every_checksum && raw_content
+}
+
+/// This function is responsible for splitting the files to hash into units of work, in their order.
+/// The two files of a size shared by no other file are a single unit, so they're compared while they're hashed.
+///
+/// # Arguments
+///
+/// * `virtual_files` - A slice of VirtualFile to hash.
+/// * `compare_pairs` - A boolean that indicates if the pairs of files with the same size are compared.
+///
+/// # Returns
+///
+/// A vector of HashUnit, each file being in exactly one unit.
+///
+fn hash_units(virtual_files: &[VirtualFile], compare_pairs: bool) -> Vec<HashUnit> {
+    let mut size_indexes: HashMap<u64, Vec<usize>> = HashMap::new();
+    if compare_pairs {
+        virtual_files.iter().enumerate().for_each(|(index, file)| size_indexes.entry(file.size).or_default().push(index));
+    }
+
+    (0..virtual_files.len())
+        .filter_map(|index| match size_indexes.get(&virtual_files[index].size).map(Vec::as_slice) {
+            Some(&[first, second]) if index == first => Some(HashUnit::Pair(first, second)),
+            Some(&[_, second]) if index == second => None,
+            _ => Some(HashUnit::File(index)),
+        })
+        .collect()
+}
+
+/// This function is responsible for selecting the hash algorithms of a file.
+/// The large files can be hashed with their own algorithm ('--algo-for-large'), their size keeps them apart from the others.
+///
+/// # Arguments
+///
+/// * `file` - A reference to the VirtualFile to hash.
+/// * `hash_algorithms` - A slice of the hash algorithms to use.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// A slice of the hash algorithms to apply on the file.
+///
+fn file_algorithms<'a>(file: &VirtualFile, hash_algorithms: &'a [String], config: &'a FindingConfig) -> &'a [String] {
+    match &config.algo_for_large {
+        Some((algorithm, threshold)) if file.size >= *threshold => std::slice::from_ref(algorithm),
+        _ => hash_algorithms,
+    }
+}
+
+/// This function is responsible for hashing a single file with each hash algorithm.
+///
+/// # Arguments
+///
+/// * `file` - A reference to the VirtualFile to hash.
+/// * `hash_algorithms` - A slice of the hash algorithms to use.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// The checksums of the file (None if no algorithm is supported), SystemError if the file can't be read.
+///
+fn hash_file(file: &VirtualFile, hash_algorithms: &[String], config: &FindingConfig) -> Result<Option<BTreeMap<String, String>>, SystemError> {
+    let parallel: bool = config.parallel_hash_within_file && file.size >= PARALLEL_HASH_MIN_SIZE;
+    // The images are decoded once, an image that can't be decoded is hashed as usual.
+    let pixels: Option<Vec<u8>> = (config.ignore_metadata && image_hash::is_image_file(&file.name))
+        .then(|| image_hash::decoded_pixels(&file.full_path))
+        .flatten();
+
+    let mut checksums: BTreeMap<String, String> = BTreeMap::new();
+    for hash_algorithm in file_algorithms(file, hash_algorithms, config) {
+        let hash: Option<String> = if let Some(pixels) = &pixels {
+            system::manage_hash_reader(pixels.as_slice(), hash_algorithm).map_err(|e| SystemError::UnableToReadFile(file.full_path.clone(), e))?
+        } else if config.transform != Transform::None {
+            system::manage_transformed_hash(&file.full_path, hash_algorithm, config.transform)?
+        } else if config.content_case_insensitive {
+            system::manage_case_folded_hash(&file.full_path, hash_algorithm)?
+        } else if parallel {
+            system::manage_parallel_hash(&file.full_path, hash_algorithm)?
+        } else {
+            system::manage_hash(&file.full_path, hash_algorithm)?
+        };
+
+        if let Some(hash) = hash {
+            checksums.insert(hash_algorithm.clone(), hash);
+        }
+    }
+
+    Ok((!checksums.is_empty()).then_some(checksums))
+}
+
+/// This function is responsible for hashing a pair of files with the same size, while comparing them byte by byte.
+/// A difference stops the reads of both files, the other algorithms are only computed for an identical pair.
+///
+/// # Arguments
+///
+/// * `first` - A reference to the first VirtualFile of the pair.
+/// * `second` - A reference to the second VirtualFile of the pair.
+/// * `hash_algorithms` - A slice of the hash algorithms to use.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// The checksums shared by both files, None if they differ. SystemError if a file can't be read.
+///
+fn hash_pair(first: &VirtualFile, second: &VirtualFile, hash_algorithms: &[String], config: &FindingConfig) -> Result<Option<BTreeMap<String, String>>, SystemError> {
+    let Some((hash_algorithm, other_algorithms)) = file_algorithms(first, hash_algorithms, config).split_first() else {
+        return Ok(None);
+    };
+    let Some(hash) = system::hash_if_equal(&first.full_path, &second.full_path, hash_algorithm)? else {
+        return Ok(None);
+    };
+
+    let mut checksums: BTreeMap<String, String> = BTreeMap::from([(hash_algorithm.clone(), hash)]);
+    for hash_algorithm in other_algorithms {
+        if let Some(hash) = system::manage_hash(&first.full_path, hash_algorithm)? {
+            checksums.insert(hash_algorithm.clone(), hash);
+        }
+    }
+
+    Ok(Some(checksums))
+}
+
+/// This function is responsible for hashing the files with a given number of threads.
+/// The threads take the units of work (a file, or a pair of files with the same size) one after the other, so a large file never holds back the others.
+/// If a byte budget is given, the threads stop hashing new files once the cumulative size of the hashed files would exceed it.
+/// If the parallel hashing within a file is enabled, the large files are also hashed with several threads (blake3 only).
+/// The threads send the checksums of each unit once it's done, so the duplicates confirmed so far can be written
+/// each time the checkpoint interval has elapsed. No checkpoint is written after the last file, the final results are written as usual.
+///
+/// # Arguments
+///
+/// * `hash_algorithms` - A slice of the hash algorithms to use.
+/// * `virtual_files` - A mutable slice of VirtualFile to hash, their checksums are updated in place.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+/// * `workers` - The number of threads (a single thread is used if it's 0).
+/// * `checkpoint_interval` - The minimum duration between two checkpoints, None to write none.
+/// * `clock` - A function returning the current instant.
+///
+/// # Returns
+///
+/// Ok(true) if the byte budget has been exceeded (some files have not been hashed), Ok(false) otherwise.
+/// A file that can't be read is skipped with a warning, DeepFinderError if a thread has failed or a checkpoint can't be written.
+///
+fn hash_with_workers<F: FnMut() -> Instant>(
+    hash_algorithms: &[String],
+    virtual_files: &mut [VirtualFile],
+    config: &FindingConfig,
+    workers: usize,
+    checkpoint_interval: Option<Duration>,
+    mut clock: F,
+) -> Result<bool, DeepFinderError> {
+    let units: Vec<HashUnit> = hash_units(virtual_files, compares_size_pairs(config));
+    let next_unit: AtomicUsize = AtomicUsize::new(0); // Shared between the threads.
+    let hashed_bytes: AtomicU64 = AtomicU64::new(0);
+    let budget_exceeded: AtomicBool = AtomicBool::new(false);
+    let files: &[VirtualFile] = virtual_files;
+
+    let results: Vec<(usize, Option<BTreeMap<String, String>>)> = thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel::<(usize, Option<BTreeMap<String, String>>)>();
+        let threads: Vec<ScopedJoinHandle<()>> = (0..workers.max(1))
+            .map(|_| {
+                let sender: mpsc::Sender<(usize, Option<BTreeMap<String, String>>)> = sender.clone();
+                let (units, next_unit, hashed_bytes, budget_exceeded): (&[HashUnit], &AtomicUsize, &AtomicU64, &AtomicBool) = (&units, &next_unit, &hashed_bytes, &budget_exceeded);
+                scope.spawn(move || {
+                    while let Some(unit) = units.get(next_unit.fetch_add(1, Ordering::Relaxed)) {
+                        let indexes: &[usize] = match unit {
+                            HashUnit::File(index) => std::slice::from_ref(index),
+                            HashUnit::Pair(first, second) => &[*first, *second],
+                        };
+                        let file: &VirtualFile = &files[indexes[0]];
+                        let unit_size: u64 = file.size * indexes.len() as u64;
+                        if let Some(budget) = config.byte_budget
+                            && hashed_bytes.fetch_add(unit_size, Ordering::Relaxed).saturating_add(unit_size) > budget {
+                                budget_exceeded.store(true, Ordering::Relaxed);
+                                break;
+                            }
+
+                        let start: Instant = Instant::now();
+                        let checksums: Result<Option<BTreeMap<String, String>>, SystemError> = match unit {
+                            HashUnit::File(_) => hash_file(file, hash_algorithms, config),
+                            HashUnit::Pair(_, second) => hash_pair(file, &files[*second], hash_algorithms, config),
+                        };
+
+                        // A file that can't be read (e.g. denied or deleted since the traversal) is skipped, the others are still hashed.
+                        let checksums: Option<BTreeMap<String, String>> = checksums.unwrap_or_else(|e| {
+                            record_warning(config, "skipped_file", format!("Warning: {}, it has been skipped.", e.to_string().trim_start_matches("Error: ")));
+                            None
+                        });
+
+                        if let Some(threshold) = config.slow_file_threshold
+                            && let Some(warning) = slow_file_warning(&file.full_path, unit_size, start.elapsed(), Duration::from_millis(threshold)) {
+                                record_warning(config, "slow_file", warning);
+                            }
+
+                        report_progress(|progress| progress.advance(indexes.len() as u64, unit_size));
+                        indexes.iter().for_each(|&index| {
+                            let _ = sender.send((index, checksums.clone()));
+                        });
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+
+        let mut results: Vec<(usize, Option<BTreeMap<String, String>>)> = Vec::with_capacity(files.len());
+        let mut last_checkpoint: Instant = clock();
+        for result in receiver.iter() {
+            results.push(result);
+            if let Some(interval) = checkpoint_interval
+                && results.len() < files.len() {
+                    let now: Instant = clock();
+                    if now.duration_since(last_checkpoint) >= interval {
+                        // The threads keep hashing while the checkpoint is written, their results wait in the channel.
+                        write_checkpoint(files, &results, config)?;
+                        last_checkpoint = now;
+                    }
+                }
+        }
+
+        threads.into_iter().try_for_each(|thread| thread.join().map_err(|_| DeepFinderError::SystemError(SystemError::ThreadError)))?;
+        Ok::<_, DeepFinderError>(results)
+    })?;
+
+    for (index, checksums) in results {
+        virtual_files[index].checksums = checksums;
+    }
+    Ok(budget_exceeded.into_inner())
+}
+
+/// This function is responsible for hashing the files with a fast algorithm, then confirming the candidates with the strong algorithms.
+/// The candidates are the files sharing their fast checksum (and their size) with another file,
+/// only them are hashed with the strong algorithms, and only the strong checksums are kept.
+///
+/// # Arguments
+///
+/// * `prefilter` - The fast hash algorithm applied on every file (e.g. xxh3).
+/// * `hash_algorithms` - The strong hash algorithms applied on the candidates (e.g. sha256).
+/// * `virtual_files` - The files to hash.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// The files (the candidates first, the unique ones without any checksum) and true if the byte budget has been exceeded.
+/// DeepFinderError if a thread has failed or a file can't be read.
+///
+fn prefilter_handler(prefilter: &str, hash_algorithms: &[String], mut virtual_files: Vec<VirtualFile>, config: &FindingConfig) -> Result<(Vec<VirtualFile>, bool), DeepFinderError> {
+    let prefilter_exceeded: bool = hash_handler(&[prefilter.to_string()], &mut virtual_files, config)?;
+
+    let key = |file: &VirtualFile| file.checksums.as_ref().map(|checksums| format!("{}|size:{}", checksums_key(checksums), file.size));
+    let mut key_counts: HashMap<String, usize> = HashMap::new();
+    virtual_files.iter().filter_map(key).for_each(|k| *key_counts.entry(k).or_default() += 1);
+    let (mut candidates, mut unique_files): (Vec<VirtualFile>, Vec<VirtualFile>) =
+        virtual_files.into_iter().partition(|f| key(f).is_some_and(|k| key_counts[&k] > 1));
+
+    // The fast checksums are only used to select the candidates, they are never reported.
+    candidates.iter_mut().chain(unique_files.iter_mut()).for_each(|f| f.checksums = None);
+    let confirm_exceeded: bool = hash_handler(hash_algorithms, &mut candidates, config)?;
+    candidates.append(&mut unique_files);
+    Ok((candidates, prefilter_exceeded || confirm_exceeded))
+}
+
+/// This function is responsible for searching eventual duplicates in the virtual files.
+/// The virtual files are split into chunks grouped by scoped threads, then the partial groups are merged in order.
+/// The duplicates are sorted by their key at the end, so the output is always the same.
+///
+/// # Arguments
+///
+/// * `virtual_files` - A slice of VirtualFile.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// A vector of DuplicateFile containing the duplicates found, DeepFinderError if a thread has failed.
+///
+fn search_eventual_duplicates(virtual_files: &[VirtualFile], config: &FindingConfig) -> Result<Vec<DuplicateFile>, DeepFinderError> {
+    let num_cores: usize = worker_count();
+    let chunk_size: usize = virtual_files.len().div_ceil(num_cores).max(1);
+
+    let partial_maps: Vec<HashMap<String, DuplicateFile>> = thread::scope(|scope| {
+        let threads: Vec<ScopedJoinHandle<HashMap<String, DuplicateFile>>> = virtual_files
+            .chunks(chunk_size)
+            .map(|chunk_files| scope.spawn(move || group_virtual_files(chunk_files, config)))
+            .collect();
+
+        threads
+            .into_iter()
+            .map(|thread| thread.join().map_err(|_| DeepFinderError::SystemError(SystemError::ThreadError)))
+            .collect::<Result<_, _>>()
+    })?;
+
+    // The chunks are merged in order into the map of the first one, so each group keeps the name of its first file
+    // and the entries of the first chunk are never hashed again.
+    let mut partial_maps = partial_maps.into_iter();
+    let mut map: HashMap<String, DuplicateFile> = partial_maps.next().unwrap_or_default();
+    for (key, duplicate) in partial_maps.flatten() {
+        match map.entry(key) {
+            Entry::Occupied(mut entry) => entry.get_mut().paths.extend(duplicate.paths),
+            Entry::Vacant(entry) => { entry.insert(duplicate); },
+        }
+    }
+
+    if !config.enable_search_by_name {
+        for checksums in size_mismatches(&map) {
+            record_warning(config, "size_mismatch", format!("Warning: files with the same checksums ({checksums}) have different sizes, they are reported in separate groups."));
+        }
+    }
+
+    Ok(sort_groups(map, config.all_files))
+}
+
+/// This function is responsible for finding the checksums shared by files of different sizes.
+/// Identical contents always have the same size, so such a mismatch means that a checksum is wrong
+/// (e.g. a file modified during the scan). The groups are already split by size, since the size is part of their key.
+///
+/// # Arguments
+///
+/// * `map` - A HashMap with the groups of files, indexed by their key ("<checksums>|size:<size>").
+///
+/// # Returns
+///
+/// The sorted checksums keys shared by several sizes.
+///
+fn size_mismatches(map: &HashMap<String, DuplicateFile>) -> Vec<&str> {
+    let mut size_counts: HashMap<&str, usize> = HashMap::new();
+    for key in map.keys() {
+        if let Some((checksums, _)) = key.rsplit_once("|size:") {
+            *size_counts.entry(checksums).or_default() += 1;
+        }
+    }
+
+    let mut mismatches: Vec<&str> = size_counts.into_iter().filter(|(_, count)| *count > 1).map(|(checksums, _)| checksums).collect();
+    mismatches.sort_unstable();
+    mismatches
+}
+
+/// This function is responsible for grouping the virtual files by their key (name or checksums).
+///
+/// # Arguments
+///
+/// * `virtual_files` - A slice of VirtualFile.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// A HashMap with the groups of files, indexed by their key. The groups with a single file are kept.
+///
+fn group_virtual_files(virtual_files: &[VirtualFile], config: &FindingConfig) -> HashMap<String, DuplicateFile> {
+    let mut map: HashMap<String, DuplicateFile> = HashMap::new();
+
+    for file in virtual_files {
+        let key: String = if config.enable_search_by_name {
+            name_key(file, config)
+        } else if file.checksums.is_some() && config.distinct_empty && file.size == 0 {
+            // Each empty file has its own key, so the empty files are never grouped together.
+            format!("empty|{}", file.full_path)
+        } else if let Some(checksums) = &file.checksums && config.ignore_metadata && image_hash::is_image_file(&file.name) {
+            // The metadata changes the size of an image, only its pixels are compared.
+            format!("{}|pixels", checksums_key(checksums))
+        } else if let Some(checksums) = &file.checksums && config.name_then_hash {
+            format!("{}|size:{}|name:{}", checksums_key(checksums), file.size, name_key(file, config))
+        } else if let Some(checksums) = &file.checksums {
+            // The size is part of the key, so a wrong checksum can't gather files of different sizes.
+            format!("{}|size:{}", checksums_key(checksums), file.size)
+        } else {
+            continue;
+        };
+
+        let entry: &mut DuplicateFile = map.entry(key).or_insert_with(|| DuplicateFile {
+            paths: HashSet::new(),
+            name: file.name.clone(),
+            checksums: file.checksums.clone(),
+            size: file.size,
+            sizes: BTreeMap::new(),
+        });
+
+        entry.insert_path(file.full_path.clone(), file.size);
+    }
+
+    map
+}
+
+/// This function is responsible for building the key of a file searched by name.
+/// The original name is kept for the display, only the key is normalized and/or built without the extension.
+///
+/// # Arguments
+///
+/// * `file` - The file to group.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// The key of the file, built from its name.
+///
+fn name_key(file: &VirtualFile, config: &FindingConfig) -> String {
+    normalize_name(&file.name, config, config.detect_confusables)
+}
+
+/// This function is responsible for normalizing a name according to the user's choices ('--normalize-unicode', '--ignore-extension').
+///
+/// # Arguments
+///
+/// * `name` - A string slice that holds the name.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+/// * `skeleton` - A boolean that indicates if the name is replaced by its confusable skeleton.
+///
+/// # Returns
+///
+/// The normalized name.
+///
+fn normalize_name(name: &str, config: &FindingConfig, skeleton: bool) -> String {
+    let name: String = if config.normalize_unicode { name.nfc().collect() } else { name.to_string() };
+    let name: String = if skeleton { confusable_skeleton(&name) } else { name };
+    if config.ignore_extension { Path::new(&name).file_stem().and_then(|n| n.to_str()).unwrap_or(&name).to_string() } else { name }
+}
+
+/// This function is responsible for finding the same-named files whose contents differ ('--name-then-hash').
+///
+/// # Arguments
+///
+/// * `virtual_files` - A slice of the hashed VirtualFile.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// The names having several contents with their sorted paths, in the alphabetical order of the names.
+///
+fn differing_names<'a>(virtual_files: &'a [VirtualFile], config: &FindingConfig) -> Vec<(String, Vec<&'a str>)> {
+    let mut names: BTreeMap<String, (HashSet<String>, Vec<&str>)> = BTreeMap::new();
+    for file in virtual_files {
+        if let Some(checksums) = &file.checksums {
+            let (contents, paths): &mut (HashSet<String>, Vec<&str>) = names.entry(name_key(file, config)).or_default();
+            contents.insert(format!("{}|size:{}", checksums_key(checksums), file.size));
+            paths.push(&file.full_path);
+        }
+    }
+
+    names
+        .into_iter()
+        .filter(|(_, (contents, _))| contents.len() > 1)
+        .map(|(name, (_, mut paths))| {
+            paths.sort_unstable();
+            (name, paths)
+        })
+        .collect()
+}
+
+/// This function is responsible for recording the same-named files whose contents differ ('--name-then-hash'), so they are reported in the outputs.
+///
+/// # Arguments
+///
+/// * `virtual_files` - A slice of the hashed VirtualFile.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+fn record_differing_names(virtual_files: &[VirtualFile], config: &FindingConfig) {
+    for (name, paths) in differing_names(virtual_files, config) {
+        record_warning(config, "differing_content", format!("Note: the files named '{name}' don't have the same content:\n  {}", paths.join("\n  ")));
+    }
+}
+
+/// This function is responsible for building the skeleton of a name, so the names that look identical have the same skeleton.
+/// The name is normalized (NFKC), its invisible characters are removed and its homoglyphs are replaced by the Latin letters.
+///
+/// # Arguments
+///
+/// * `name` - A string slice that holds the name.
+///
+/// # Returns
+///
+/// The skeleton of the name.
+///
+fn confusable_skeleton(name: &str) -> String {
+    name.nfkc()
+        .filter(|c| !INVISIBLE_CHARS.contains(c))
+        .map(|c| HOMOGLYPHS.iter().find(|(homoglyph, _)| *homoglyph == c).map_or(c, |(_, latin)| *latin))
+        .collect()
+}
+
+/// This function is responsible for running the command of '--on-group' once per group of duplicates.
+/// A failure of the command is reported as a warning, unless strict is set.
+///
+/// # Arguments
+///
+/// * `duplicates` - The groups of duplicates found.
+/// * `command` - A string slice that holds the command to run.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration ('--strict' aborts the search on a failure).
+///
+/// # Returns
+///
+/// Ok if all the commands have been run, SystemError::HookFailed if one of them has failed with strict set.
+///
+fn run_group_hooks(duplicates: &[DuplicateFile], command: &str, config: &FindingConfig) -> Result<(), DeepFinderError> {
+    for duplicate in duplicates {
+        let mut paths: Vec<&String> = duplicate.paths.iter().collect();
+        paths.sort();
+        let env_vars: [(&str, String); 3] = [
+            ("DF_GROUP_NAME", duplicate.name.clone()),
+            ("DF_GROUP_SIZE", duplicate.size.to_string()),
+            ("DF_GROUP_PATHS", paths.iter().map(|path| path.as_str()).collect::<Vec<&str>>().join("\n")),
+        ];
+        let input: Vec<u8> = serde_json::to_vec(duplicate).map_err(|e| DeepFinderError::SystemError(SystemError::UnableToSerialize("json".to_string(), e.to_string())))?;
+
+        if let Err(e) = system::run_group_hook(command, &env_vars, &input) {
+            if config.strict {
+                return Err(DeepFinderError::SystemError(SystemError::HookFailed(command.to_string(), duplicate.name.clone(), e)));
+            }
+            record_warning(config, "hook_failed", format!("Warning: the command '{command}' has failed for the group '{}': {e}", duplicate.name));
+        }
+    }
+
+    Ok(())
+}
+
+/// This function is responsible for building the warning about a group whose names look identical but differ (spoofing).
+/// The names are compared after the other normalizations ('--normalize-unicode', '--ignore-extension'), so only the names unified by their skeleton are confusable.
+/// The non-ASCII characters of the names are escaped, so the invisible characters and the homoglyphs can be seen.
+///
+/// # Arguments
+///
+/// * `duplicate` - The group of files found by their name.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// The warning with the distinct names of the group (sorted), None if its names are the same without their skeleton or have different skeletons.
+///
+fn confusable_warning(duplicate: &DuplicateFile, config: &FindingConfig) -> Option<String> {
+    let names: BTreeSet<&str> = duplicate.paths.iter().filter_map(|path| Path::new(path).file_name().and_then(|n| n.to_str())).collect();
+    let plain_names: BTreeSet<String> = names.iter().map(|name| normalize_name(name, config, false)).collect();
+    let skeletons: BTreeSet<String> = names.iter().map(|name| normalize_name(name, config, true)).collect();
+    (plain_names.len() > 1 && skeletons.len() == 1).then(|| {
+        let escaped_names: Vec<String> = names
+            .iter()
+            .map(|name| name.chars().map(|c| if c.is_ascii() { c.to_string() } else { c.escape_unicode().to_string() }).collect::<String>())
+            .map(|name| format!("'{name}'"))
+            .collect();
+        format!("Warning: confusable names, they look identical but differ: {}.", escaped_names.join(", "))
+    })
+}
+
+/// This function is responsible for searching the approximate duplicates, i.e. the files with the same name and close sizes (e.g. re-encoded videos).
+/// The files of each name are sorted by size (then by path), and each cluster gathers the files within the tolerance of its smallest file.
+/// A cluster never chains the close sizes, so its spread is bounded and the same files always give the same clusters.
+///
+/// # Arguments
+///
+/// * `virtual_files` - A slice of VirtualFile.
+/// * `tolerance` - The maximum size difference with the smallest file of a cluster, in hundredths of a percent of its size.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// A vector of DuplicateFile containing the clusters, sorted by name then by size. Each cluster has the size of its smallest file.
+///
+fn search_approximate_duplicates(virtual_files: &[VirtualFile], tolerance: u32, config: &FindingConfig) -> Vec<DuplicateFile> {
+    let mut files_by_name: BTreeMap<String, Vec<&VirtualFile>> = BTreeMap::new();
+    for file in virtual_files {
+        files_by_name.entry(name_key(file, config)).or_default().push(file);
+    }
+
+    let mut clusters: Vec<DuplicateFile> = Vec::new();
+    for mut files in files_by_name.into_values() {
+        files.sort_by(|a, b| a.size.cmp(&b.size).then_with(|| a.full_path.cmp(&b.full_path)));
+        let first_cluster: usize = clusters.len();
+        for file in files {
+            let within_tolerance: bool = clusters[first_cluster..]
+                .last()
+                .is_some_and(|cluster| u128::from(file.size - cluster.size) * 10_000 <= u128::from(cluster.size) * u128::from(tolerance));
+
+            if let (true, Some(cluster)) = (within_tolerance, clusters.last_mut()) {
+                cluster.paths.insert(file.full_path.clone());
+            } else {
+                clusters.push(DuplicateFile {
+                    paths: HashSet::from([file.full_path.clone()]),
+                    name: file.name.clone(),
+                    checksums: None,
+                    size: file.size,
+                    sizes: BTreeMap::new(),
+                });
+            }
+        }
+    }
+
+    clusters.retain(|cluster| config.all_files || cluster.paths.len() > 1);
+    clusters
+}
+
+/// This function is responsible for keeping the groups with several files, sorted by their key.
+///
+/// # Arguments
+///
+/// * `map` - A HashMap with the groups of files, indexed by their key.
+/// * `include_singletons` - A boolean that indicates if the groups with a single file are kept too (inventory of all the files).
+///
+/// # Returns
+///
+/// A vector of DuplicateFile containing the duplicates, sorted by their key.
+///
+fn sort_groups(map: HashMap<String, DuplicateFile>, include_singletons: bool) -> Vec<DuplicateFile> {
+    let mut groups: Vec<(String, DuplicateFile)> = map.into_iter().filter(|(_, d)| include_singletons || d.paths.len() > 1).collect();
+    groups.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    groups.into_iter().map(|(_, d)| d).collect()
+}
+
+/// This function is responsible for building a key from checksums, sorted by algorithm.
+///
+/// # Arguments
+///
+/// * `checksums` - A reference to the BTreeMap of checksums, indexed by algorithm.
+///
+/// # Returns
+///
+/// The key, as "algorithm:checksum" pairs joined by '|'.
+///
+fn checksums_key(checksums: &BTreeMap<String, String>) -> String {
+    checksums.iter().map(|(k, v)| format!("{k}:{v}")).collect::<Vec<_>>().join("|")
+}
+
+/// This function is responsible for merging the findings of several reports (e.g. generated on different machines).
+/// The groups sharing the same checksums are merged into one group with the union of their paths.
+/// A warning is displayed if the merged groups don't have the same size, the size of the first report is kept.
+/// The groups without any checksum (report written without -i) are kept as they are, with a warning.
+///
+/// # Arguments
+///
+/// * `reports` - A vector with the findings of each report.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// A vector of DuplicateFile containing the merged groups, in the order of their first appearance.
+///
+fn merge_duplicates(reports: Vec<Vec<DuplicateFile>>, config: &FindingConfig) -> Vec<DuplicateFile> {
+    let mut merged: Vec<DuplicateFile> = Vec::new();
+    let mut indexes: HashMap<String, usize> = HashMap::new();
+    let mut unmerged_groups: usize = 0;
+
+    for duplicate in reports.into_iter().flatten() {
+        let Some(key) = duplicate.group_key() else {
+            unmerged_groups += 1;
+            merged.push(duplicate);
+            continue;
+        };
+        let Some(&index) = indexes.get(&key) else {
+            indexes.insert(key, merged.len());
+            merged.push(duplicate);
+            continue;
+        };
+
+        let entry: &mut DuplicateFile = &mut merged[index];
+        if entry.size != duplicate.size {
+            record_warning(config, "report_conflict", format!("Warning: the group '{}' has conflicting sizes in the reports ({} and {} bytes).", entry.name, entry.size, duplicate.size));
+        }
+        entry.paths.extend(duplicate.paths);
+        entry.sizes.extend(duplicate.sizes);
+    }
+
+    if unmerged_groups > 0 {
+        record_warning(
+            config,
+            "report_conflict",
+            format!("Warning: {unmerged_groups} groups have no checksums (reports written without -i), they have not been merged with the other reports."),
+        );
+    }
+    merged
+}
+
+/// This function is responsible for searching similar images in the virtual files, with their average hash.
+/// Two images are similar if the Hamming distance between their hashes is lower or equal to the maximum distance.
+/// The similarity is transitive, so an image similar to any image of a group joins this group.
+///
+/// # Arguments
+///
+/// * `virtual_files` - A slice of VirtualFile, hashed with the "ahash" algorithm.
+/// * `max_distance` - The maximum Hamming distance between two similar images.
+///
+/// # Returns
+///
+/// A vector of DuplicateFile containing the groups of similar images.
+///
+fn search_similar_images(virtual_files: &[VirtualFile], max_distance: u32) -> Vec<DuplicateFile> {
+    let images: Vec<(&VirtualFile, u64)> = virtual_files
+        .iter()
+        .filter_map(|f| {
+            let checksum: &String = f.checksums.as_ref()?.get("ahash")?;
+            Some((f, u64::from_str_radix(checksum, 16).ok()?))
+        })
+        .collect();
+
+    // Union-find of the images, each image points to the first image of its group.
+    let mut parents: Vec<usize> = (0..images.len()).collect();
+    fn find_root(parents: &[usize], mut index: usize) -> usize {
+        while parents[index] != index {
+            index = parents[index];
+        }
+        index
+    }
+
+    for i in 0..images.len() {
+        for j in (i + 1)..images.len() {
+            if image_hash::hamming_distance(images[i].1, images[j].1) <= max_distance {
+                let (root_i, root_j) = (find_root(&parents, i), find_root(&parents, j));
+                parents[root_i.max(root_j)] = root_i.min(root_j);
+            }
+        }
+    }
+
+    let mut groups: Vec<DuplicateFile> = Vec::new();
+    let mut group_indexes: HashMap<usize, usize> = HashMap::new();
+    for (i, (file, _)) in images.iter().enumerate() {
+        let root: usize = find_root(&parents, i);
+        let group_index: usize = *group_indexes.entry(root).or_insert_with(|| {
+            groups.push(DuplicateFile {
+                paths: HashSet::new(),
+                name: file.name.clone(),
+                checksums: file.checksums.clone(),
+                size: file.size,
+                sizes: BTreeMap::new(),
+            });
+            groups.len() - 1
+        });
+        groups[group_index].insert_path(file.full_path.clone(), file.size);
+    }
+
+    groups.retain(|g| g.paths.len() > 1);
+    groups
+}
+
+/// This function is responsible for filtering the duplicates according to the user's filters (size, wasted space, occurrences, name, scope).
+///
+/// # Arguments
+///
+/// * `duplicates` - A vector of DuplicateFile to filter.
+/// * `config` - A reference to the FindingConfig struct with the user's configuration.
+///
+/// # Returns
+///
+/// A vector of DuplicateFile containing only the duplicates matching every filter.
+///
+fn filter_duplicates(mut duplicates: Vec<DuplicateFile>, config: &FindingConfig) -> Vec<DuplicateFile> {
+    duplicates.retain(|d| {
+        config.min_size.is_none_or(|min_size| d.size >= min_size)
+            && config.min_wasted.is_none_or(|min_wasted| d.reclaimable_size() >= min_wasted)
+            && config.min_occurrences.is_none_or(|min_occurrences| d.paths.len() >= min_occurrences)
+            && (config.only.is_empty() || config.only.iter().any(|pattern| matches_glob(pattern, &d.name)))
+            && (config.scope == Scope::Global || d.spans_several_folders())
+    });
+    duplicates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::PathRegex;
+
+    #[test]
+    fn test_search_eventual_duplicates_by_name() {
         let files = vec![
             VirtualFile {
                 name: "file1.txt".to_string(),
                 full_path: "/tmp/file1.txt".to_string(),
                 size: 123,
                 checksums: None,
+                ..Default::default()
+            },
+            VirtualFile {
+                name: "file1.txt".to_string(),
+                full_path: "/tmp/copy_file1.txt".to_string(),
+                size: 123,
+                checksums: None,
+                ..Default::default()
+            },
+            VirtualFile {
+                name: "file2.txt".to_string(),
+                full_path: "/tmp/file2.txt".to_string(),
+                size: 456,
+                checksums: None,
+                ..Default::default()
+            },
+        ];
+
+        let config: FindingConfig = FindingConfig {
+            search_path: "/tmp".to_string(),
+            enable_search_by_name: true,
+            output: CliOutput::Standard,
+            ..Default::default()
+        };
+
+        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&files, &config).unwrap();
+        assert_eq!(duplicates.len(), 1);
+        
+        let duplicate: &DuplicateFile = &duplicates[0];
+        assert_eq!(duplicate.name, "file1.txt");
+        assert_eq!(duplicate.paths.len(), 2);
+        assert!(duplicate.paths.contains("/tmp/file1.txt"));
+        assert!(duplicate.paths.contains("/tmp/copy_file1.txt"));
+    }
+
+    #[test]
+    fn test_search_eventual_duplicates_with_same_path() {
+        let file = |full_path: &str| VirtualFile {
+            name: "file.txt".to_string(),
+            full_path: full_path.to_string(),
+            size: 10,
+            checksums: None,
+            ..Default::default()
+        };
+        let config: FindingConfig = FindingConfig { enable_search_by_name: true, ..Default::default() };
+
+        // A path enumerated twice (e.g. through a symlink) is counted once, and alone it isn't a duplicate.
+        assert!(search_eventual_duplicates(&[file("/tmp/a/file.txt"), file("/tmp/a/file.txt")], &config).unwrap().is_empty());
+
+        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&[file("/tmp/a/file.txt"), file("/tmp/b/file.txt"), file("/tmp/a/file.txt")], &config).unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].paths.len(), 2);
+        assert_eq!(duplicates[0].reclaimable_size(), 10);
+    }
+
+    #[test]
+    fn test_search_eventual_duplicates_ignoring_extension() {
+        let file = |name: &str| VirtualFile {
+            name: name.to_string(),
+            full_path: format!("/tmp/{name}"),
+            size: 10,
+            checksums: None,
+            ..Default::default()
+        };
+        let files: Vec<VirtualFile> = vec![file("a.txt"), file("a.md"), file("b.txt")];
+
+        let mut config: FindingConfig = FindingConfig {
+            search_path: "/tmp".to_string(),
+            enable_search_by_name: true,
+            ..Default::default()
+        };
+        assert!(search_eventual_duplicates(&files, &config).unwrap().is_empty());
+
+        config.ignore_extension = true;
+        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&files, &config).unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].name, "a.txt");
+        assert!(duplicates[0].paths.contains("/tmp/a.txt"));
+        assert!(duplicates[0].paths.contains("/tmp/a.md"));
+    }
+
+    #[test]
+    fn test_slow_file_warning() {
+        // A reader stalling on each read, like a failing disk.
+        struct SlowReader(io::Cursor<Vec<u8>>);
+        impl Read for SlowReader {
+            fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+                thread::sleep(Duration::from_millis(20));
+                self.0.read(buffer)
+            }
+        }
+
+        let content: Vec<u8> = vec![0; 2048];
+        let start: Instant = Instant::now();
+        assert!(system::manage_hash_reader(SlowReader(io::Cursor::new(content)), "md5").unwrap().is_some());
+        let elapsed: Duration = start.elapsed();
+
+        let warning: Option<String> = slow_file_warning("/mnt/share/file.bin", 2048, elapsed, Duration::from_millis(40));
+        assert!(warning.as_deref().is_some_and(|w| w.contains("'/mnt/share/file.bin'") && w.contains("MB/s")));
+        assert_eq!(slow_file_warning("/mnt/share/file.bin", 2048, elapsed, Duration::from_secs(60)), None);
+    }
+
+    #[test]
+    fn test_record_warning_from_threads() {
+        let config: FindingConfig = FindingConfig { quiet: true, ..Default::default() };
+
+        // The warnings of the worker threads are collected with the others, the notes too.
+        thread::scope(|scope| {
+            for index in 0..4 {
+                let config: &FindingConfig = &config;
+                scope.spawn(move || record_warning(config, "slow_file", format!("Warning: slow file '/data/{index}.bin'.")));
+            }
+        });
+        record_warning(&config, "collapsed_paths", "Note: 2 paths lead to files already scanned.".to_string());
+
+        let warnings: Vec<Warning> = take_warnings(&config);
+        assert_eq!(warnings.iter().filter(|w| w.kind == "slow_file").count(), 4);
+        assert_eq!(warnings.last(), Some(&Warning { kind: "collapsed_paths", detail: "2 paths lead to files already scanned.".to_string() }));
+        assert!(take_warnings(&config).is_empty());
+    }
+
+    #[test]
+    fn test_warnings_in_text_output() {
+        // The warnings are displayed once: at the end of the text outputs, on stderr for the other ones.
+        assert!(warnings_in_text_output(&FindingConfig::default()));
+        assert!(warnings_in_text_output(&FindingConfig { summary_report: true, ..Default::default() }));
+        assert!(warnings_in_text_output(&FindingConfig { by_dir_pairs: true, output: CliOutput::JsonStdin, ..Default::default() }));
+        assert!(!warnings_in_text_output(&FindingConfig { output: CliOutput::JsonStdin, ..Default::default() }));
+        assert!(!warnings_in_text_output(&FindingConfig { json_tree: true, ..Default::default() }));
+        assert!(!warnings_in_text_output(&FindingConfig { list_hashes: true, ..Default::default() }));
+        assert!(!warnings_in_text_output(&FindingConfig { verify_sidecars: true, ..Default::default() }));
+    }
+
+    #[test]
+    fn test_search_eventual_duplicates_with_normalize_unicode() {
+        let file = |name: &str, folder: &str| VirtualFile {
+            name: name.to_string(),
+            full_path: format!("/tmp/{folder}/{name}"),
+            size: 10,
+            checksums: None,
+            ..Default::default()
+        };
+        // The same name, with a precomposed 'é' (NFC) and with an 'e' followed by a combining acute accent (NFD).
+        let files: Vec<VirtualFile> = vec![file("caf\u{e9}.txt", "linux"), file("cafe\u{301}.txt", "macos")];
+
+        let mut config: FindingConfig = FindingConfig { enable_search_by_name: true, ..Default::default() };
+        assert!(search_eventual_duplicates(&files, &config).unwrap().is_empty());
+
+        config.normalize_unicode = true;
+        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&files, &config).unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].paths.len(), 2);
+        assert_eq!(duplicates[0].name, "caf\u{e9}.txt");
+    }
+
+    #[test]
+    fn test_search_eventual_duplicates_by_checksum() {
+        let mut files: Vec<VirtualFile> = Vec::new();
+        let mut checksums: BTreeMap<String, String> = BTreeMap::new();
+        checksums.insert("md5".to_string(), "abc123".to_string());
+
+        files.push(VirtualFile {
+            name: "fileA.txt".to_string(),
+            full_path: "/tmp/fileA.txt".to_string(),
+            size: 100,
+            checksums: Some(checksums.clone()),
+            ..Default::default()
+        });
+        files.push(VirtualFile {
+            name: "fileB.txt".to_string(),
+            full_path: "/tmp/fileB.txt".to_string(),
+            size: 100,
+            checksums: Some(checksums.clone()),
+            ..Default::default()
+        });
+
+        let config: FindingConfig = FindingConfig {
+            search_path: "/tmp".to_string(),
+            enable_search_by_name: false,
+            output: CliOutput::Standard,
+            ..Default::default()
+        };
+
+        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&files, &config).unwrap();
+        assert_eq!(duplicates.len(), 1);
+        
+        let duplicate: &DuplicateFile = &duplicates[0];
+        assert_eq!(duplicate.paths.len(), 2);
+        assert!(duplicate.paths.contains("/tmp/fileA.txt"));
+        assert!(duplicate.paths.contains("/tmp/fileB.txt"));
+    }
+
+    #[test]
+    fn test_filter_duplicates_from_report() {
+        let duplicates: Vec<DuplicateFile> = vec![
+            DuplicateFile {
+                name: "small.txt".to_string(),
+                paths: ["/tmp/a/small.txt".to_string(), "/tmp/b/small.txt".to_string(), "/tmp/c/small.txt".to_string()].into_iter().collect(),
+                checksums: None,
+                size: 10,
+                sizes: BTreeMap::new(),
             },
-            VirtualFile {
+            DuplicateFile {
+                name: "big.iso".to_string(),
+                paths: ["/tmp/a/big.iso".to_string(), "/tmp/b/big.iso".to_string()].into_iter().collect(),
+                checksums: None,
+                size: 200 * 1024 * 1024,
+                sizes: BTreeMap::new(),
+            },
+        ];
+        let report_path: String = std::env::temp_dir().join("deepfinder_test_filter_report.json").to_string_lossy().to_string();
+        fs::write(&report_path, serde_json::to_string(&duplicates).unwrap()).unwrap();
+
+        let mut config: FindingConfig = FindingConfig {
+            filter_report: Some(report_path.clone()),
+            min_size: Some(100 * 1024 * 1024),
+            ..Default::default()
+        };
+        let filtered: Vec<DuplicateFile> = filter_duplicates(load_json_report(&report_path).unwrap(), &config);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].name, "big.iso");
+
+        config.min_size = None;
+        config.min_occurrences = Some(3);
+        let filtered: Vec<DuplicateFile> = filter_duplicates(load_json_report(&report_path).unwrap(), &config);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].name, "small.txt");
+
+        // A group is kept if its name matches any of the patterns.
+        config.min_occurrences = None;
+        config.only = vec!["*.iso".to_string()];
+        let filtered: Vec<DuplicateFile> = filter_duplicates(load_json_report(&report_path).unwrap(), &config);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].name, "big.iso");
+        config.only.push("small.*".to_string());
+        assert_eq!(filter_duplicates(load_json_report(&report_path).unwrap(), &config).len(), 2);
+        config.only = vec!["*.txt".to_string()];
+        config.min_size = Some(100 * 1024 * 1024);
+        assert!(filter_duplicates(load_json_report(&report_path).unwrap(), &config).is_empty());
+
+        let _ = fs::remove_file(report_path);
+    }
+
+    #[test]
+    fn test_search_eventual_duplicates_with_size_mismatch() {
+        let file = |path: &str, size: u64| VirtualFile {
+            name: "file.txt".to_string(),
+            full_path: path.to_string(),
+            size,
+            checksums: Some([("md5".to_string(), "abc".to_string())].into_iter().collect()),
+            ..Default::default()
+        };
+        let files: Vec<VirtualFile> = vec![file("/a/file.txt", 10), file("/b/file.txt", 10), file("/c/file.txt", 12), file("/d/file.txt", 12), file("/e/file.txt", 99)];
+        let config: FindingConfig = FindingConfig { hash: Some(vec!["md5".to_string()]), quiet: true, ..Default::default() };
+
+        // The files with a mismatched size are split out of the group, the single one is not a duplicate anymore.
+        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&files, &config).unwrap();
+        assert_eq!(duplicates.len(), 2);
+        assert!(duplicates.iter().all(|d| d.paths.len() == 2));
+        assert_eq!(duplicates.iter().map(|d| d.size).collect::<Vec<u64>>(), vec![10, 12]);
+
+        let map: HashMap<String, DuplicateFile> = group_virtual_files(&files, &config);
+        assert_eq!(size_mismatches(&map), vec!["md5:abc"]);
+    }
+
+    #[test]
+    fn test_search_eventual_duplicates_with_all_files() {
+        let file = |path: &str, checksum: &str| VirtualFile {
+            name: path.rsplit('/').next().unwrap_or_default().to_string(),
+            full_path: path.to_string(),
+            size: 10,
+            checksums: Some([("md5".to_string(), checksum.to_string())].into_iter().collect()),
+            ..Default::default()
+        };
+        let files: Vec<VirtualFile> = vec![file("/a/file.txt", "abc"), file("/b/copy.txt", "abc"), file("/c/unique.txt", "def")];
+        let mut config: FindingConfig = FindingConfig { hash: Some(vec!["md5".to_string()]), ..Default::default() };
+
+        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&files, &config).unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert!(duplicates.iter().all(|d| !d.paths.contains("/c/unique.txt")));
+
+        // The unique file is a group with a single occurrence.
+        config.all_files = true;
+        let inventory: Vec<DuplicateFile> = search_eventual_duplicates(&files, &config).unwrap();
+        assert_eq!(inventory.len(), 2);
+        assert_eq!(inventory[1].name, "unique.txt");
+        assert_eq!(inventory[1].paths.len(), 1);
+        assert_eq!(inventory[1].reclaimable_size(), 0);
+    }
+
+    #[test]
+    fn test_search_eventual_duplicates_in_parallel() {
+        let files: Vec<VirtualFile> = (0..20_000).map(|i| VirtualFile {
+            name: format!("file{}.txt", i % 3_000),
+            full_path: format!("/tmp/{i}/file{}.txt", i % 3_000),
+            size: 10,
+            checksums: Some([("md5".to_string(), format!("{:x}", i % 2_500)), ("sha1".to_string(), format!("{:x}", i % 2_500))].into_iter().collect()),
+            ..Default::default()
+        }).collect();
+
+        for enable_search_by_name in [true, false] {
+            let config: FindingConfig = FindingConfig { enable_search_by_name, ..Default::default() };
+            let sequential: Vec<DuplicateFile> = sort_groups(group_virtual_files(&files, &config), false);
+            let parallel: Vec<DuplicateFile> = search_eventual_duplicates(&files, &config).unwrap();
+            assert_eq!(parallel.len(), if enable_search_by_name { 3_000 } else { 2_500 });
+            assert!(parallel == sequential);
+        }
+    }
+
+    #[test]
+    fn test_compare_files() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_compare");
+        fs::create_dir_all(&dir).unwrap();
+        let paths: Vec<String> = ["a.bin", "b.bin", "c.bin"].iter().map(|name| dir.join(name).to_string_lossy().to_string()).collect();
+        let content: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&paths[0], &content).unwrap();
+        fs::write(&paths[1], &content).unwrap();
+        let mut different_content: Vec<u8> = content.clone();
+        different_content[15_000] ^= 1;
+        fs::write(&paths[2], &different_content).unwrap();
+
+        for hash_algorithms in [vec![], vec!["md5".to_string(), "sha256".to_string()]] {
+            assert!(compare_files(&paths[0], &paths[1], &hash_algorithms).unwrap());
+            assert!(!compare_files(&paths[0], &paths[2], &hash_algorithms).unwrap());
+        }
+
+        // A missing file is an error, not a difference.
+        let missing_path: String = dir.join("missing.bin").to_string_lossy().to_string();
+        assert!(matches!(compare_files(&paths[0], &missing_path, &[]), Err(SystemError::UnableToReadFile(path, _)) if path == missing_path));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    #[cfg(target_family = "windows")]
+    fn test_search_files_with_hidden_attribute() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_hidden_attribute");
+        fs::create_dir_all(&dir).unwrap();
+        let visible_file: std::path::PathBuf = dir.join("visible.txt");
+        let hidden_file: std::path::PathBuf = dir.join("hidden.txt");
+        fs::write(&visible_file, "content").unwrap();
+        fs::write(&hidden_file, "content").unwrap();
+        assert!(std::process::Command::new("attrib").arg("+h").arg(&hidden_file).status().unwrap().success());
+
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let files: Vec<String> = search_files(&dir_path, &FindingConfig::default()).unwrap();
+        assert_eq!(files, vec![visible_file.to_string_lossy().to_string()]);
+        let config: FindingConfig = FindingConfig { include_hidden_files: true, ..Default::default() };
+        assert_eq!(search_files(&dir_path, &config).unwrap().len(), 2);
+
+        let _ = std::process::Command::new("attrib").arg("-h").arg(&hidden_file).status();
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(target_family = "windows")]
+    #[test]
+    fn test_search_files_with_cloud_stubs() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_cloud_stubs");
+        fs::create_dir_all(&dir).unwrap();
+        let local_file: std::path::PathBuf = dir.join("local.txt");
+        let stub_file: std::path::PathBuf = dir.join("stub.txt");
+        fs::write(&local_file, "content").unwrap();
+        fs::write(&stub_file, "content").unwrap();
+
+        // The offline attribute is set by the cloud providers on the placeholders.
+        assert!(std::process::Command::new("attrib").arg("+o").arg(&stub_file).status().unwrap().success());
+
+        let dir_path: String = dir.to_string_lossy().to_string();
+        assert_eq!(search_files(&dir_path, &FindingConfig::default()).unwrap().len(), 2);
+        let config: FindingConfig = FindingConfig { exclude_stubs: true, ..Default::default() };
+        assert_eq!(search_files(&dir_path, &config).unwrap(), vec![local_file.to_string_lossy().to_string()]);
+
+        let _ = std::process::Command::new("attrib").arg("-o").arg(&stub_file).status();
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(target_family = "windows")]
+    #[test]
+    fn test_search_files_with_junctions() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_junctions");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("target")).unwrap();
+        fs::write(dir.join("target").join("file.txt"), "content").unwrap();
+        let mklink = |link: std::path::PathBuf, target: &std::path::PathBuf| {
+            assert!(std::process::Command::new("cmd").args(["/C", "mklink", "/J"]).arg(link).arg(target).status().unwrap().success());
+        };
+        mklink(dir.join("junction"), &dir.join("target"));
+        mklink(dir.join("target").join("loop"), &dir);
+
+        // By default, the junctions are skipped, so the file is found once.
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let files: Vec<String> = search_files(&dir_path, &FindingConfig::default()).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].contains("target"));
+
+        // When followed, the target is traversed once (through the junction, first in alphabetical order) and the loop ends.
+        let config: FindingConfig = FindingConfig { follow_reparse_points: true, ..Default::default() };
+        let files: Vec<String> = search_files(&dir_path, &config).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].contains("junction"));
+
+        // The junctions are removed first, so the removal doesn't go through them.
+        let _ = fs::remove_dir(dir.join("target").join("loop"));
+        let _ = fs::remove_dir(dir.join("junction"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_search_files_with_cloud_stubs() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_cloud_stubs");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let local_file: std::path::PathBuf = dir.join("local.txt");
+        let empty_file: std::path::PathBuf = dir.join("empty.txt");
+        fs::write(&local_file, "content").unwrap();
+        fs::write(&empty_file, "").unwrap();
+        // A sparse file has a size but no allocated block, like a placeholder.
+        fs::File::create(dir.join("stub.txt")).unwrap().set_len(1024 * 1024).unwrap();
+
+        let dir_path: String = dir.to_string_lossy().to_string();
+        assert_eq!(search_files(&dir_path, &FindingConfig::default()).unwrap().len(), 3);
+        let config: FindingConfig = FindingConfig { exclude_stubs: true, ..Default::default() };
+        let mut file_paths: Vec<String> = search_files(&dir_path, &config).unwrap();
+        file_paths.sort();
+        assert_eq!(file_paths, [empty_file.to_string_lossy().to_string(), local_file.to_string_lossy().to_string()]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_search_files_with_exclude_and_hidden_files() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_exclude");
+        let _ = fs::remove_dir_all(&dir);
+        for sub_dir in ["node_modules", ".hidden", "sub/node_modules", "sub/.cache"] {
+            fs::create_dir_all(dir.join(sub_dir)).unwrap();
+        }
+        for file in ["a.txt", "node_modules/b.txt", ".hidden/c.txt", ".hidden/d.tmp", "sub/e.tmp", "sub/.f.txt", "sub/node_modules/g.txt", "sub/.cache/h.txt"] {
+            fs::write(dir.join(file), "content").unwrap();
+        }
+
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let relative_files = |config: &FindingConfig| {
+            let mut files: Vec<String> = search_files(&dir_path, config).unwrap().iter().map(|f| f[dir_path.len() + 1..].to_string()).collect();
+            files.sort();
+            files
+        };
+
+        let mut config: FindingConfig = FindingConfig { exclude: vec!["node_modules".to_string(), "*.tmp".to_string()], ..Default::default() };
+        assert_eq!(relative_files(&config), vec!["a.txt"]);
+
+        // The exclude patterns still apply inside the hidden folders.
+        config.include_hidden_files = true;
+        assert_eq!(relative_files(&config), vec![".hidden/c.txt", "a.txt", "sub/.cache/h.txt", "sub/.f.txt"]);
+
+        config.exclude = vec![".*".to_string()];
+        assert_eq!(relative_files(&config), vec!["a.txt", "node_modules/b.txt", "sub/e.tmp", "sub/node_modules/g.txt"]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_search_engine_scheduler_with_largest_first() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_largest_first");
+        let _ = fs::remove_dir_all(&dir);
+        for sub_dir in ["a", "b"] {
+            fs::create_dir_all(dir.join(sub_dir)).unwrap();
+            for (file, size) in [("small.bin", 10), ("large.bin", 3000), ("medium.bin", 200), ("unique.bin", 5000)] {
+                let content: Vec<u8> = if file == "unique.bin" { vec![sub_dir.as_bytes()[0]; size] } else { vec![b'x'; size] };
+                fs::write(dir.join(sub_dir).join(file), content).unwrap();
+            }
+        }
+        let report_path: String = std::env::temp_dir().join("deepfinder_test_largest_first.json").to_string_lossy().to_string();
+        let config: FindingConfig = FindingConfig {
+            search_path: dir.to_string_lossy().to_string(),
+            hash: Some(vec!["md5".to_string()]),
+            output: CliOutput::JsonFile(report_path.clone()),
+            largest_first: true,
+            quiet: true,
+            ..Default::default()
+        };
+
+        // The files are hashed from the largest to the smallest.
+        let sizes: Vec<u64> = scan_virtual_files(&config, &mut Vec::new()).unwrap().iter().map(|f| f.size).collect();
+        assert!(sizes.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        // The groups are reported in the descending order of their size.
+        assert_eq!(search_engine_scheduler(&config), Ok(true));
+        let names: Vec<String> = load_json_report(&report_path).unwrap().into_iter().map(|d| d.name).collect();
+        assert_eq!(names, ["large.bin", "medium.bin", "small.bin"]);
+
+        let _ = fs::remove_file(report_path);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_distinct_empty() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_distinct_empty");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, content) in [("empty1.txt", ""), ("empty2.txt", ""), ("empty3.txt", ""), ("full1.txt", "content"), ("full2.txt", "content")] {
+            fs::write(dir.join(file), content).unwrap();
+        }
+
+        let mut config: FindingConfig = FindingConfig {
+            search_path: dir.to_string_lossy().to_string(),
+            hash: Some(vec!["md5".to_string()]),
+            ..Default::default()
+        };
+        let group_sizes = |config: &FindingConfig| {
+            let virtual_files: Vec<VirtualFile> = scan_virtual_files(config, &mut Vec::new()).unwrap();
+            let mut sizes: Vec<(u64, usize)> = filter_duplicates(search_eventual_duplicates(&virtual_files, config).unwrap(), config)
+                .iter()
+                .map(|d| (d.size, d.paths.len()))
+                .collect();
+            sizes.sort_unstable();
+            sizes
+        };
+
+        // The empty files share the same content by default.
+        assert_eq!(group_sizes(&config), [(0, 3), (7, 2)]);
+
+        config.distinct_empty = true;
+        assert_eq!(group_sizes(&config), [(7, 2)]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn test_run_group_hooks() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_run_group_hooks");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let log_path: std::path::PathBuf = dir.join("calls.log");
+        let hook_path: std::path::PathBuf = dir.join("hook.sh");
+        fs::write(&hook_path, format!("printf '%s|%s|%s|%s\\n' \"$DF_GROUP_NAME\" \"$DF_GROUP_SIZE\" \"$(echo \"$DF_GROUP_PATHS\" | wc -l | tr -d ' ')\" \"$(head -c 1)\" >> '{}'\n", log_path.display())).unwrap();
+
+        let duplicates: Vec<DuplicateFile> = vec![
+            DuplicateFile {
+                paths: HashSet::from(["/a/file1.txt".to_string(), "/b/file1.txt".to_string()]),
                 name: "file1.txt".to_string(),
-                full_path: "/tmp/copy_file1.txt".to_string(),
-                size: 123,
                 checksums: None,
+                size: 10,
+                sizes: BTreeMap::new(),
             },
-            VirtualFile {
+            DuplicateFile {
+                paths: HashSet::from(["/a/file2.txt".to_string(), "/b/file2.txt".to_string(), "/c/file2.txt".to_string()]),
                 name: "file2.txt".to_string(),
-                full_path: "/tmp/file2.txt".to_string(),
-                size: 456,
                 checksums: None,
+                size: 20,
+                sizes: BTreeMap::new(),
             },
         ];
 
+        // The hook is called once per group, with its details in the environment and its JSON on stdin.
+        let command: String = format!("sh '{}'", hook_path.display());
+        let mut config: FindingConfig = FindingConfig::default();
+        assert!(run_group_hooks(&duplicates, &command, &config).is_ok());
+        assert_eq!(fs::read_to_string(&log_path).unwrap(), "file1.txt|10|2|{\nfile2.txt|20|3|{\n");
+
+        // A failing hook is only reported, unless strict is set.
+        assert!(run_group_hooks(&duplicates, "exit 3", &config).is_ok());
+        assert!(take_warnings(&config).iter().filter(|w| w.kind == "hook_failed").count() == 2);
+        config.strict = true;
+        assert!(matches!(
+            run_group_hooks(&duplicates, "exit 3", &config),
+            Err(DeepFinderError::SystemError(SystemError::HookFailed(_, group, _))) if group == "file1.txt"
+        ));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn test_unique_content() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_unique_content");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "content").unwrap();
+        fs::hard_link(dir.join("a.txt"), dir.join("b.txt")).unwrap();
+        fs::write(dir.join("c.txt"), "content").unwrap();
+
+        let mut config: FindingConfig = FindingConfig {
+            search_path: dir.to_string_lossy().to_string(),
+            hash: Some(vec!["md5".to_string()]),
+            quiet: true,
+            ..Default::default()
+        };
+        let group_paths = |config: &FindingConfig| {
+            let virtual_files: Vec<VirtualFile> = scan_virtual_files(config, &mut Vec::new()).unwrap();
+            filter_duplicates(search_eventual_duplicates(&virtual_files, config).unwrap(), config).iter().map(|d| d.paths.len()).collect::<Vec<usize>>()
+        };
+
+        // The hard link is a path of the group, but not another copy of the bytes.
+        assert_eq!(group_paths(&config), [3]);
+        config.unique_content = true;
+        assert_eq!(group_paths(&config), [2]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_content_case_insensitive() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_content_case_insensitive");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("lower.conf"), "key = value\n").unwrap();
+        fs::write(dir.join("upper.conf"), "KEY = Value\n").unwrap();
+        fs::write(dir.join("lower.bin"), b"key\0value").unwrap();
+        fs::write(dir.join("upper.bin"), b"KEY\0VALUE").unwrap();
+
+        let mut config: FindingConfig = FindingConfig {
+            search_path: dir.to_string_lossy().to_string(),
+            hash: Some(vec!["md5".to_string()]),
+            ..Default::default()
+        };
+        let group_names = |config: &FindingConfig| {
+            let virtual_files: Vec<VirtualFile> = scan_virtual_files(config, &mut Vec::new()).unwrap();
+            let mut names: Vec<Vec<String>> = filter_duplicates(search_eventual_duplicates(&virtual_files, config).unwrap(), config)
+                .iter()
+                .map(|d| {
+                    let mut names: Vec<String> = d.paths.iter().filter_map(|p| Path::new(p).file_name()).map(|n| n.to_string_lossy().to_string()).collect();
+                    names.sort();
+                    names
+                })
+                .collect();
+            names.sort();
+            names
+        };
+
+        // The texts differing only by their case are grouped in this mode only, the binary files never.
+        assert!(group_names(&config).is_empty());
+        config.content_case_insensitive = true;
+        assert_eq!(group_names(&config), [["lower.conf", "upper.conf"]]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_name_then_hash() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_name_then_hash");
+        let _ = fs::remove_dir_all(&dir);
+        for sub_dir in ["first", "second"] {
+            fs::create_dir_all(dir.join(sub_dir)).unwrap();
+        }
+        for (file, content) in [("first/same.txt", "same"), ("second/same.txt", "same"), ("first/diff.txt", "a"), ("second/diff.txt", "b"), ("first/unique.txt", "same")] {
+            fs::write(dir.join(file), content).unwrap();
+        }
+
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let config: FindingConfig = FindingConfig {
+            search_path: dir_path.clone(),
+            hash: Some(vec!["md5".to_string()]),
+            name_then_hash: true,
+            ..Default::default()
+        };
+
+        // The file without any name collision is never hashed, even if its content is the same.
+        let virtual_files: Vec<VirtualFile> = scan_virtual_files(&config, &mut Vec::new()).unwrap();
+        assert_eq!(virtual_files.len(), 4);
+        assert!(virtual_files.iter().all(|f| f.checksums.is_some()));
+
+        let duplicates: Vec<DuplicateFile> = filter_duplicates(search_eventual_duplicates(&virtual_files, &config).unwrap(), &config);
+        assert_eq!(duplicates.len(), 1);
+        assert!(duplicates[0].paths == HashSet::from([format!("{dir_path}/first/same.txt"), format!("{dir_path}/second/same.txt")]));
+
+        let (first_diff, second_diff): (String, String) = (format!("{dir_path}/first/diff.txt"), format!("{dir_path}/second/diff.txt"));
+        assert_eq!(differing_names(&virtual_files, &config), [("diff.txt".to_string(), vec![first_diff.as_str(), second_diff.as_str()])]);
+
+        // The differing contents are recorded with the other warnings, so they reach the outputs.
+        record_differing_names(&virtual_files, &config);
+        let warnings: Vec<Warning> = take_warnings(&config);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, "differing_content");
+        assert_eq!(warnings[0].detail, format!("the files named 'diff.txt' don't have the same content:\n  {first_diff}\n  {second_diff}"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_search_files_with_ignore_file() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_search_ignore_file");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("cache")).unwrap();
+        for file in ["a.txt", "b.log", "cache/c.txt", "d.tmp", "old copy.txt", "old"] {
+            fs::write(dir.join(file), "content").unwrap();
+        }
+        fs::write(dir.join("ignore"), "# Generated files\ncache\n*.log, *.tmp\n  old copy.txt  \n").unwrap();
+
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let config: FindingConfig = FindingConfig { exclude: system::read_ignore_file(&format!("{dir_path}/ignore")).unwrap(), ..Default::default() };
+        let mut files: Vec<String> = search_files(&dir_path, &config).unwrap();
+        files.sort();
+        assert_eq!(files, [format!("{dir_path}/a.txt"), format!("{dir_path}/ignore"), format!("{dir_path}/old")]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_search_files_with_min_dir_size() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_search_min_dir_size");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("small")).unwrap();
+        fs::create_dir_all(dir.join("large").join("nested")).unwrap();
+        for (file, size) in [("root.txt", 10), ("small/a.txt", 500), ("large/b.bin", 800), ("large/nested/c.bin", 400)] {
+            fs::write(dir.join(file), vec![0u8; size]).unwrap();
+        }
+
+        // The large folder (1200 bytes with its nested folder) is kept, the small one and the nested one are pruned.
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let mut config: FindingConfig = FindingConfig { min_dir_size: Some(1000), ..Default::default() };
+        let mut files: Vec<String> = search_files(&dir_path, &config).unwrap();
+        files.sort();
+        assert_eq!(files, [format!("{dir_path}/large/b.bin"), format!("{dir_path}/root.txt")]);
+
+        // The large folder is pruned with its whole subtree.
+        config.min_dir_size = Some(1100);
+        assert!(search_files(&dir_path, &config).unwrap().contains(&format!("{dir_path}/large/b.bin")));
+        config.min_dir_size = Some(1500);
+        assert_eq!(search_files(&dir_path, &config).unwrap(), [format!("{dir_path}/root.txt")]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_search_files_with_empty_dirs() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_search_empty_dirs");
+        let _ = fs::remove_dir_all(&dir);
+        for folder in ["a", "b/c", "b/d", "e/f", "g"] {
+            fs::create_dir_all(dir.join(folder)).unwrap();
+        }
+        fs::write(dir.join("e").join("file.txt"), "content").unwrap();
+        fs::write(dir.join("g").join(".hidden"), "content").unwrap();
+
+        // The hidden file isn't scanned, but its folder isn't empty.
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let config: FindingConfig = FindingConfig { report_empty_dirs: true, ..Default::default() };
+        assert_eq!(search_files(&dir_path, &config).unwrap(), [format!("{dir_path}/e/file.txt")]);
+        let empty_dirs: Vec<(String, bool)> = take_empty_dirs(&config).into_iter().map(|d| (d.path, d.recursive)).collect();
+        assert_eq!(
+            empty_dirs,
+            [
+                (format!("{dir_path}/a"), false),
+                (format!("{dir_path}/b"), true),
+                (format!("{dir_path}/b/c"), false),
+                (format!("{dir_path}/b/d"), false),
+                (format!("{dir_path}/e/f"), false),
+            ]
+        );
+
+        // The empty directories belong to the search that found them.
+        let other_config: FindingConfig = FindingConfig { report_empty_dirs: true, ..Default::default() };
+        search_files(&dir_path, &config).unwrap();
+        assert!(take_empty_dirs(&other_config).is_empty());
+        assert_eq!(take_empty_dirs(&config).len(), 5);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_search_engine_scheduler_with_fail_on_duplicates() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_fail_on_duplicates");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "content").unwrap();
+        fs::write(dir.join("sub").join("a.txt"), "content").unwrap();
+        let report_path: String = std::env::temp_dir().join("deepfinder_test_fail_on_duplicates.json").to_string_lossy().to_string();
+
+        // The report is still written, and the duplicates found make the process fail.
+        let mut config: FindingConfig = FindingConfig {
+            search_path: dir.to_string_lossy().to_string(),
+            enable_search_by_name: true,
+            output: CliOutput::JsonFile(report_path.clone()),
+            fail_on_duplicates: true,
+            quiet: true,
+            ..Default::default()
+        };
+        assert_eq!(search_engine_scheduler(&config), Ok(true));
+        assert!(fs::read_to_string(&report_path).unwrap().contains("a.txt"));
+
+        // The filters apply before the check, so the duplicates too small don't make the process fail.
+        config.min_size = Some(1024);
+        assert_eq!(search_engine_scheduler(&config), Ok(false));
+
+        let _ = fs::remove_file(report_path);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_search_engine_scheduler_with_hash_truncate() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_hash_truncate");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        // Two groups of files with the same size, still grouped apart with their full checksums.
+        for (name, content) in [("a1.txt", "first"), ("a2.txt", "first"), ("b1.txt", "other"), ("b2.txt", "other"), ("c.txt", "third")] {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        let report_path: String = std::env::temp_dir().join("deepfinder_test_hash_truncate.json").to_string_lossy().to_string();
+
+        let config: FindingConfig = FindingConfig {
+            search_path: dir.to_string_lossy().to_string(),
+            hash: Some(vec!["md5".to_string()]),
+            include_hashes_in_output: true,
+            hash_truncate: Some(12),
+            output: CliOutput::JsonFile(report_path.clone()),
+            quiet: true,
+            ..Default::default()
+        };
+        assert_eq!(search_engine_scheduler(&config), Ok(true));
+
+        let report: Vec<DuplicateFile> = load_json_report(&report_path).unwrap();
+        assert_eq!(report.len(), 2);
+        assert!(report.iter().all(|d| d.paths.len() == 2 && d.checksums.as_ref().unwrap()["md5"].len() == 12));
+        let mut checksums: Vec<&str> = report.iter().map(|d| d.checksums.as_ref().unwrap()["md5"].as_str()).collect();
+        checksums.sort_unstable();
+        assert_eq!(checksums, vec!["795f3202b17c", "8b04d5e3775d"]);
+
+        let _ = fs::remove_file(report_path);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_search_approximate_duplicates() {
+        let file = |folder: &str, name: &str, size: u64| VirtualFile {
+            name: name.to_string(),
+            size,
+            full_path: format!("/{folder}/{name}"),
+            checksums: None,
+            ..Default::default()
+        };
+        let files: Vec<VirtualFile> = vec![
+            file("e", "movie.mkv", 1_019),
+            file("a", "movie.mkv", 1_000),
+            file("b", "movie.mkv", 1_010),
+            file("c", "movie.mkv", 1_005),
+            file("d", "movie.mkv", 1_025),
+            file("f", "movie.mkv", 2_000),
+            file("a", "other.mkv", 1_001),
+            file("g", "clip.mp4", 5_000),
+            file("h", "clip.mp4", 5_050),
+        ];
+        let config: FindingConfig = FindingConfig { enable_search_by_name: true, ..Default::default() };
+
+        // Within 1%, the clusters start at their smallest file: 1 000 to 1 010, then 1 019 to 1 025. The other name is never mixed.
+        let clusters: Vec<DuplicateFile> = search_approximate_duplicates(&files, 100, &config);
+        let summary: Vec<(&str, u64, Vec<&str>)> = clusters
+            .iter()
+            .map(|c| {
+                let mut paths: Vec<&str> = c.paths.iter().map(String::as_str).collect();
+                paths.sort_unstable();
+                (c.name.as_str(), c.size, paths)
+            })
+            .collect();
+        assert_eq!(summary, vec![
+            ("clip.mp4", 5_000, vec!["/g/clip.mp4", "/h/clip.mp4"]),
+            ("movie.mkv", 1_000, vec!["/a/movie.mkv", "/b/movie.mkv", "/c/movie.mkv"]),
+            ("movie.mkv", 1_019, vec!["/d/movie.mkv", "/e/movie.mkv"]),
+        ]);
+
+        // The same files in another order give the same clusters, and a zero tolerance only keeps the exact sizes.
+        let reversed: Vec<VirtualFile> = files.iter().rev().cloned().collect();
+        assert!(search_approximate_duplicates(&reversed, 100, &config) == clusters);
+        assert!(search_approximate_duplicates(&files, 0, &config).is_empty());
+    }
+
+    #[test]
+    fn test_read_nul_delimited_paths() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_nul_delimited_paths");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let simple_path: String = format!("{dir_path}/a.txt");
+        fs::write(&simple_path, "content").unwrap();
+
+        // A name with a newline is a single path, as long as the platform accepts it.
+        let newline_path: String = format!("{dir_path}/b\nc.txt");
+        let expected: Vec<String> = if fs::write(&newline_path, "content").is_ok() { vec![simple_path.clone(), newline_path.clone()] } else { vec![simple_path.clone()] };
+
+        let input: String = format!("{simple_path}\0{newline_path}\0{dir_path}\0{dir_path}/missing.txt\0");
+        let config: FindingConfig = FindingConfig::default();
+        assert_eq!(read_nul_delimited_paths(input.as_bytes(), &config).unwrap(), expected);
+        let warnings: Vec<Warning> = take_warnings(&config);
+        assert_eq!(warnings.iter().filter(|w| w.kind == "skipped_file").count(), 4 - expected.len());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_record_and_replay_filelist() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_replay_filelist");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for (name, content) in [("a.txt", "same"), ("sub/a.txt", "same"), ("sub/b.txt", "other"), ("c\nd.txt", "other")] {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        let filelist_path: String = std::env::temp_dir().join("deepfinder_test_replay_filelist.json").to_string_lossy().to_string();
+
         let config: FindingConfig = FindingConfig {
-            search_path: "/tmp".to_string(),
-            enable_search_by_name: true,
-            include_hidden_files: false,
-            include_hashes_in_output: false,
-            hash: None,
-            output: CliOutput::Standard,
+            search_path: dir.to_string_lossy().to_string(),
+            hash: Some(vec!["md5".to_string()]),
+            record_filelist: Some(filelist_path.clone()),
+            ..Default::default()
+        };
+        let recorded: Vec<VirtualFile> = scan_virtual_files(&config, &mut Vec::new()).unwrap();
+        let recorded_duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&recorded, &config).unwrap();
+        assert_eq!(recorded_duplicates.len(), 2);
+
+        // The replay doesn't traverse the folder, so a new file isn't found, but the same duplicates are.
+        fs::write(dir.join("sub/new.txt"), "same").unwrap();
+        let config: FindingConfig = FindingConfig {
+            search_path: String::new(),
+            hash: Some(vec!["md5".to_string()]),
+            replay_filelist: Some(filelist_path.clone()),
+            ..Default::default()
+        };
+        let replayed: Vec<VirtualFile> = scan_virtual_files(&config, &mut Vec::new()).unwrap();
+        assert_eq!(replayed.iter().map(|f| &f.full_path).collect::<Vec<_>>(), recorded.iter().map(|f| &f.full_path).collect::<Vec<_>>());
+        assert!(search_eventual_duplicates(&replayed, &config).unwrap() == recorded_duplicates);
+
+        let _ = fs::remove_file(filelist_path);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_detect_confusables() {
+        let file = |folder: &str, name: &str| VirtualFile {
+            name: name.to_string(),
+            size: 10,
+            full_path: format!("/{folder}/{name}"),
+            checksums: None,
+            ..Default::default()
+        };
+        // A Cyrillic 'а' (U+0430) and a zero-width space, then a true duplicate.
+        let files: Vec<VirtualFile> = vec![
+            file("a", "data.csv"),
+            file("b", "d\u{0430}ta.csv"),
+            file("c", "da\u{200B}ta.csv"),
+            file("a", "notes.txt"),
+            file("b", "notes.txt"),
+        ];
+
+        let config: FindingConfig = FindingConfig { enable_search_by_name: true, ..Default::default() };
+        assert_eq!(search_eventual_duplicates(&files, &config).unwrap().len(), 1);
+
+        let config: FindingConfig = FindingConfig { enable_search_by_name: true, detect_confusables: true, ..Default::default() };
+        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&files, &config).unwrap();
+        assert_eq!(duplicates.len(), 2);
+        let warnings: Vec<String> = duplicates.iter().filter_map(|d| confusable_warning(d, &config)).collect();
+        assert_eq!(warnings, vec![r"Warning: confusable names, they look identical but differ: 'data.csv', 'da\u{200b}ta.csv', 'd\u{430}ta.csv'.".to_string()]);
+
+        // The names unified by another normalization (extension, NFC/NFD) aren't confusable.
+        let files: Vec<VirtualFile> = vec![file("a", "song.mp3"), file("b", "song.flac"), file("a", "caf\u{e9}.txt"), file("b", "cafe\u{301}.txt")];
+        let config: FindingConfig =
+            FindingConfig { enable_search_by_name: true, detect_confusables: true, ignore_extension: true, normalize_unicode: true, ..Default::default() };
+        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&files, &config).unwrap();
+        assert_eq!(duplicates.len(), 2);
+        assert!(duplicates.iter().all(|d| confusable_warning(d, &config).is_none()));
+
+        // Neither are the files grouped by their content under different names.
+        let config: FindingConfig = FindingConfig { detect_confusables: true, ..Default::default() };
+        let duplicate: DuplicateFile =
+            DuplicateFile { name: "a.txt".to_string(), paths: HashSet::from(["/a/a.txt".to_string(), "/b/b.txt".to_string()]), checksums: None, size: 10, sizes: BTreeMap::new() };
+        assert!(confusable_warning(&duplicate, &config).is_none());
+    }
+
+    #[test]
+    fn test_check_read_only_paths() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_read_only_paths");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "content").unwrap();
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let file_paths: Vec<String> = vec![format!("{dir_path}/a.txt")];
+
+        // The listed files have no search path, the output is checked against their folders.
+        let output_path: String = format!("{dir_path}/report.json");
+        let config: FindingConfig = FindingConfig { read_only: true, output: CliOutput::JsonFile(output_path.clone()), ..Default::default() };
+        assert_eq!(check_read_only_paths(&file_paths, &config), Err(DeepFinderError::SystemError(SystemError::WriteOnScannedFilesystem(output_path))));
+        let config: FindingConfig = FindingConfig { read_only: true, output: CliOutput::JsonStdin, ..Default::default() };
+        assert!(check_read_only_paths(&file_paths, &config).is_ok());
+        if Path::new("/dev/shm").is_dir() && !system::is_same_filesystem("/dev/shm", &dir_path) {
+            let config: FindingConfig = FindingConfig { read_only: true, output: CliOutput::JsonFile("/dev/shm/report.json".to_string()), ..Default::default() };
+            assert!(check_read_only_paths(&file_paths, &config).is_ok());
+        }
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_output_collision_warning() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_output_collision");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("photos")).unwrap();
+        for file in ["Report.JSON", "photos/a.jpg"] {
+            fs::write(dir.join(file), "content").unwrap();
+        }
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let file_paths: Vec<String> = vec![format!("{dir_path}/photos/a.jpg"), format!("{dir_path}/Report.JSON")];
+
+        // The paths are canonicalized, so a detour through another folder still leads to the scanned file.
+        let warning: Option<String> = output_collision_warning(&format!("{dir_path}/photos/../Report.JSON"), &file_paths, false);
+        assert!(warning.is_some_and(|w| w.contains(&format!("'{dir_path}/Report.JSON'")) && !w.contains("case-insensitive")));
+
+        // Only a case-insensitive filesystem (macOS, Windows) would write the report over the scanned file.
+        let output_path: String = format!("{dir_path}/report.json");
+        assert!(output_collision_warning(&output_path, &file_paths, false).is_none());
+        let warning: Option<String> = output_collision_warning(&output_path, &file_paths, true);
+        assert!(warning.is_some_and(|w| w.contains(&format!("'{output_path}'")) && w.contains("case-insensitive filesystem")));
+        assert!(output_collision_warning(&format!("{dir_path}/photos/A.JPG"), &file_paths, true).is_some());
+        assert!(output_collision_warning(&format!("{dir_path}/report.csv"), &file_paths, true).is_none());
+        assert!(output_collision_warning("/other/report.json", &file_paths, true).is_none());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_search_files_with_exclude_regex() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_exclude_regex");
+        let _ = fs::remove_dir_all(&dir);
+        for sub_dir in ["repo/.git/objects", "repo/src", "build"] {
+            fs::create_dir_all(dir.join(sub_dir)).unwrap();
+        }
+        for file in ["a.txt", "repo/.git/HEAD", "repo/.git/objects/b", "repo/src/main.rs", "repo/src/main.rs.bak", "build/c.o"] {
+            fs::write(dir.join(file), "content").unwrap();
+        }
+
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let regexes: Vec<PathRegex> = [r".*/\.git/.*", r"\.bak$", r"/build$"].iter().map(|p| PathRegex(regex::Regex::new(p).unwrap())).collect();
+        let config: FindingConfig = FindingConfig { include_hidden_files: true, exclude_regex: regexes, ..Default::default() };
+        let mut files: Vec<String> = search_files(&dir_path, &config).unwrap().iter().map(|f| f[dir_path.len() + 1..].to_string()).collect();
+        files.sort();
+        assert_eq!(files, vec!["a.txt", "repo/src/main.rs"]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_scan_virtual_files_with_symlinks_as_entries() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_symlink_entries");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("folder")).unwrap();
+        fs::write(dir.join("file.txt"), "content").unwrap();
+        std::os::unix::fs::symlink(dir.join("file.txt"), dir.join("link.txt")).unwrap();
+        std::os::unix::fs::symlink(dir.join("folder"), dir.join("link_folder")).unwrap();
+
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let config: FindingConfig = FindingConfig {
+            search_path: dir_path.clone(),
+            hash: Some(vec!["md5".to_string()]),
+            list_hashes: true,
+            include_symlinks_as_entries: true,
+            ..Default::default()
+        };
+        let mut virtual_files: Vec<VirtualFile> = scan_virtual_files(&config, &mut Vec::new()).unwrap();
+        virtual_files.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // The links are listed (the folder one is not descended), with their target and without any checksum.
+        assert_eq!(virtual_files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["file.txt", "link.txt", "link_folder"]);
+        assert!(virtual_files[0].checksums.is_some() && virtual_files[0].kind.is_none());
+        assert_eq!(virtual_files[1].kind, Some("symlink"));
+        assert_eq!(virtual_files[1].target, Some(format!("{dir_path}/file.txt")));
+        assert!(virtual_files[1].checksums.is_none());
+
+        let json: String = serde_json::to_string(&virtual_files[2]).unwrap();
+        assert!(json.contains(r#""kind":"symlink""#) && json.contains(&format!(r#""target":"{dir_path}/folder""#)));
+
+        // Without the mode, the links are followed as before.
+        let config: FindingConfig = FindingConfig { include_symlinks_as_entries: false, ..config };
+        assert!(scan_virtual_files(&config, &mut Vec::new()).unwrap().iter().all(|f| f.kind.is_none() && f.checksums.is_some()));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_filter_duplicates_with_min_wasted() {
+        let group = |name: &str, size: u64, occurrences: usize| DuplicateFile {
+            name: name.to_string(),
+            paths: (0..occurrences).map(|i| format!("/dir{i}/{name}")).collect(),
+            size,
+            checksums: None,
+            sizes: BTreeMap::new(),
+        };
+        // 1 KiB copied 101 times wastes 100 KiB, 60 KiB copied twice wastes 60 KiB.
+        let duplicates: Vec<DuplicateFile> = vec![group("small.txt", 1024, 101), group("large.bin", 60 * 1024, 2)];
+
+        let config: FindingConfig = FindingConfig { min_wasted: Some(80 * 1024), ..Default::default() };
+        let filtered: Vec<DuplicateFile> = filter_duplicates(duplicates.clone(), &config);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].name, "small.txt");
+
+        // The threshold is inclusive.
+        let config: FindingConfig = FindingConfig { min_wasted: Some(60 * 1024), ..Default::default() };
+        assert_eq!(filter_duplicates(duplicates.clone(), &config).len(), 2);
+
+        // The large file passes a size filter, but not the small file with more waste.
+        let config: FindingConfig = FindingConfig { min_size: Some(2048), ..Default::default() };
+        assert_eq!(filter_duplicates(duplicates, &config)[0].name, "large.bin");
+    }
+
+    #[test]
+    fn test_filter_duplicates_with_cross_dir_scope() {
+        let group = |paths: &[&str]| DuplicateFile {
+            name: "song".to_string(),
+            paths: paths.iter().map(ToString::to_string).collect(),
+            size: 10,
+            checksums: None,
+            sizes: BTreeMap::new(),
+        };
+        let duplicates: Vec<DuplicateFile> = vec![
+            group(&["/music/song.mp3", "/music/song.flac"]),
+            group(&["/music/song.mp3", "/backup/music/song.mp3", "/music/song.flac"]),
+        ];
+
+        assert_eq!(filter_duplicates(duplicates.clone(), &FindingConfig::default()).len(), 2);
+
+        // The duplicates in the same folder are filtered out, the scattered ones are kept.
+        let config: FindingConfig = FindingConfig { scope: Scope::CrossDir, ..Default::default() };
+        let filtered: Vec<DuplicateFile> = filter_duplicates(duplicates, &config);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].paths.len(), 3);
+    }
+
+    #[test]
+    fn test_hash_reader_with_algorithms() {
+        let sha256: (String, String) = ("sha256".to_string(), "ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73".to_string());
+        let md5: (String, String) = ("md5".to_string(), "9a0364b9e99bb480dd25e1f0284c8555".to_string());
+
+        let checksums: Vec<(String, String)> = hash_reader_with_algorithms(b"content".as_slice(), &["sha256".to_string()]).unwrap();
+        assert_eq!(checksums, vec![sha256.clone()]);
+
+        // Several algorithms are computed on the same content, in the given order.
+        let checksums: Vec<(String, String)> = hash_reader_with_algorithms(b"content".as_slice(), &["sha256".to_string(), "md5".to_string()]).unwrap();
+        assert_eq!(checksums, vec![sha256, md5]);
+
+        // A source of many chunks is streamed to every algorithm, the unsupported ones are skipped.
+        let content: Vec<u8> = (0..1_000_000u32).flat_map(u32::to_le_bytes).collect();
+        let algorithms: Vec<String> = ["blake3", "unknown", "sha1", "xxh3"].iter().map(|a| a.to_string()).collect();
+        let checksums: Vec<(String, String)> = hash_reader_with_algorithms(content.as_slice(), &algorithms).unwrap();
+        let expected: Vec<(String, String)> = ["blake3", "sha1", "xxh3"]
+            .iter()
+            .map(|a| (a.to_string(), system::manage_hash_reader(content.as_slice(), a).unwrap().unwrap()))
+            .collect();
+        assert_eq!(checksums, expected);
+    }
+
+    #[test]
+    fn test_verify_sidecars() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_sidecars");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, content) in [("correct.iso", "content"), ("tampered.iso", "tampered content"), ("alone.iso", "content"), ("both.iso", "content"), ("garbled.iso", "content")] {
+            fs::write(dir.join(file), content).unwrap();
+        }
+
+        // The sidecars hold the checksums of "content".
+        let sha256: &str = "ED7002B439E9AC845F22357D822BAC1444730FBDB6016D3EC9432297B9EC9F73";
+        let md5: &str = "9a0364b9e99bb480dd25e1f0284c8555";
+        fs::write(dir.join("correct.iso.sha256"), format!("{sha256}  correct.iso\n")).unwrap();
+        fs::write(dir.join("tampered.iso.sha256"), format!("{sha256}  tampered.iso\n")).unwrap();
+        fs::write(dir.join("both.iso.sha256"), sha256).unwrap();
+        fs::write(dir.join("both.iso.md5"), md5).unwrap();
+        fs::write(dir.join("garbled.iso.sha256"), [0xFF, 0xFE, 0x00]).unwrap();
+
+        let file_paths: Vec<String> = search_files(&dir.to_string_lossy(), &FindingConfig::default()).unwrap();
+        let report: SidecarReport = verify_sidecars(&file_paths).unwrap();
+        assert_eq!(report.verified, 2);
+        assert_eq!(report.failed, vec![dir.join("tampered.iso").to_string_lossy().to_string()]);
+        assert_eq!(report.missing, 1);
+        assert_eq!(report.unreadable, vec![dir.join("garbled.iso").to_string_lossy().to_string()]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_search_files_with_skip_dirs() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_skip_dir");
+        let _ = fs::remove_dir_all(&dir);
+        for sub_dir in ["backups/daily", "photos/backups", ".snapshots"] {
+            fs::create_dir_all(dir.join(sub_dir)).unwrap();
+        }
+        for file in ["a.txt", "backups/b.txt", "backups/daily/c.txt", "photos/backups/d.txt", ".snapshots/e.txt"] {
+            fs::write(dir.join(file), "content").unwrap();
+        }
+
+        // Only the named directory is skipped, not the other folders with the same name.
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let skip_dirs: Vec<String> = ["backups", ".snapshots"].iter().map(|d| fs::canonicalize(dir.join(d)).unwrap().to_string_lossy().to_string()).collect();
+        let config: FindingConfig = FindingConfig { include_hidden_files: true, skip_dirs, ..Default::default() };
+        let mut files: Vec<String> = search_files(&dir_path, &config).unwrap().iter().map(|f| f[dir_path.len() + 1..].to_string()).collect();
+        files.sort();
+        assert_eq!(files, vec!["a.txt", "photos/backups/d.txt"]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_search_files_with_skip_top() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_skip_top");
+        let _ = fs::remove_dir_all(&dir);
+        for sub_dir in ["vendor", "archive", "app/vendor", "app/archive"] {
+            fs::create_dir_all(dir.join(sub_dir)).unwrap();
+        }
+        for file in ["a.txt", "vendor/b.txt", "archive/c.txt", "app/vendor/d.txt", "app/archive/e.txt"] {
+            fs::write(dir.join(file), "content").unwrap();
+        }
+
+        let dir_path: String = dir.to_string_lossy().to_string();
+        let config: FindingConfig = FindingConfig { skip_top: vec!["vendor".to_string(), "archive".to_string()], ..Default::default() };
+        let mut files: Vec<String> = search_files(&dir_path, &config).unwrap().iter().map(|f| f[dir_path.len() + 1..].to_string()).collect();
+        files.sort();
+        assert_eq!(files, vec!["a.txt", "app/archive/e.txt", "app/vendor/d.txt"]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_search_files_in_deep_tree() {
+        let root: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_deep_tree");
+        let _ = fs::remove_dir_all(&root);
+        let mut deepest: std::path::PathBuf = root.clone();
+        for _ in 0..1_500 {
+            deepest.push("d");
+        }
+        fs::create_dir_all(&deepest).unwrap();
+        fs::write(deepest.join("deep.txt"), "content").unwrap();
+        fs::write(root.join("top.txt"), "content").unwrap();
+
+        // A small stack would overflow with a recursive traversal.
+        let root_path: String = root.to_string_lossy().to_string();
+        let files: Vec<String> = thread::Builder::new()
+            .stack_size(128 * 1024)
+            .spawn(move || search_files(&root_path, &FindingConfig::default()).unwrap())
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].ends_with("/d/deep.txt"));
+        assert!(files[1].ends_with("top.txt"));
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_matches_glob() {
+        assert!(matches_glob("node_modules", "node_modules"));
+        assert!(!matches_glob("node_modules", "node_modules2"));
+        assert!(matches_glob("*.tmp", "file.tmp"));
+        assert!(matches_glob("*.tmp", ".tmp"));
+        assert!(!matches_glob("*.tmp", "file.tmp.txt"));
+        assert!(matches_glob("file?.*", "file1.txt"));
+        assert!(matches_glob("*a*b*", "xxaxxbxx"));
+        assert!(!matches_glob("*a*b", "xxbxxa"));
+        assert!(matches_glob("*", ""));
+    }
+
+    #[test]
+    fn test_hash_handler_with_ignore_metadata() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_ignore_metadata");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // The same pixels, saved without and with an EXIF chunk (inserted right after the IHDR chunk).
+        let image: image::GrayImage = image::GrayImage::from_fn(16, 16, |x, y| image::Luma([(x * 16 + y) as u8]));
+        image.save(dir.join("original.png")).unwrap();
+        let png: Vec<u8> = fs::read(dir.join("original.png")).unwrap();
+        let exif: &[u8] = b"MM\x00\x2a\x00\x00\x00\x08\x00\x00";
+        let mut chunk_crc: flate2::Crc = flate2::Crc::new();
+        chunk_crc.update(b"eXIf");
+        chunk_crc.update(exif);
+        let mut with_exif: Vec<u8> = png[..33].to_vec();
+        with_exif.extend_from_slice(&(exif.len() as u32).to_be_bytes());
+        with_exif.extend_from_slice(b"eXIf");
+        with_exif.extend_from_slice(exif);
+        with_exif.extend_from_slice(&chunk_crc.sum().to_be_bytes());
+        with_exif.extend_from_slice(&png[33..]);
+        fs::write(dir.join("edited.png"), &with_exif).unwrap();
+
+        let file_paths: Vec<String> = search_files(&dir.to_string_lossy(), &FindingConfig::default()).unwrap();
+        let hash_algorithms: Vec<String> = vec!["sha256".to_string()];
+        let duplicates = |ignore_metadata: bool| {
+            let config: FindingConfig = FindingConfig { hash: Some(hash_algorithms.clone()), ignore_metadata, ..Default::default() };
+            let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, None).unwrap();
+            hash_handler(&hash_algorithms, &mut virtual_files, &config).unwrap();
+            search_eventual_duplicates(&virtual_files, &config).unwrap()
+        };
+
+        assert!(duplicates(false).is_empty());
+        let pixel_duplicates: Vec<DuplicateFile> = duplicates(true);
+        assert_eq!(pixel_duplicates.len(), 1);
+        assert_eq!(pixel_duplicates[0].paths.len(), 2);
+
+        // The group has the size of its largest file, only the smaller one can be reclaimed.
+        assert_eq!(pixel_duplicates[0].size, with_exif.len() as u64);
+        assert_eq!(pixel_duplicates[0].total_size(), (png.len() + with_exif.len()) as u64);
+        assert_eq!(pixel_duplicates[0].reclaimable_size(), png.len() as u64);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_hash_handler_with_byteswap_transform() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_byteswap");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // The samples are written in little endian and big endian, with an odd size to keep an incomplete last word.
+        let samples: Vec<u16> = (0..3_000).map(|i| i * 7).collect();
+        let little_endian: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).chain([42]).collect();
+        let big_endian: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).chain([42]).collect();
+        fs::write(dir.join("samples_le.raw"), &little_endian).unwrap();
+        fs::write(dir.join("samples_be.raw"), &big_endian).unwrap();
+
+        let file_paths: Vec<String> = search_files(&dir.to_string_lossy(), &FindingConfig::default()).unwrap();
+        let hash_algorithms: Vec<String> = vec!["sha256".to_string()];
+        let duplicates = |transform: Transform| {
+            let config: FindingConfig = FindingConfig { hash: Some(hash_algorithms.clone()), transform, ..Default::default() };
+            let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, None).unwrap();
+            hash_handler(&hash_algorithms, &mut virtual_files, &config).unwrap();
+            search_eventual_duplicates(&virtual_files, &config).unwrap()
         };
 
-        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&files, &config);
+        assert!(duplicates(Transform::None).is_empty());
+        assert!(duplicates(Transform::ByteSwap32).is_empty());
+        let byteswap16_duplicates: Vec<DuplicateFile> = duplicates(Transform::ByteSwap16);
+        assert_eq!(byteswap16_duplicates.len(), 1);
+        assert_eq!(byteswap16_duplicates[0].paths.len(), 2);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_prefilter_handler() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_prefilter");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in [("a1.txt", "same"), ("a2.txt", "same"), ("a3.txt", "same"), ("b.txt", "diff"), ("c.txt", "other content")] {
+            fs::write(dir.join(name), content).unwrap();
+        }
+
+        let file_paths: Vec<String> = search_files(&dir.to_string_lossy(), &FindingConfig::default()).unwrap();
+        let hash_algorithms: Vec<String> = vec!["sha256".to_string()];
+        let config: FindingConfig = FindingConfig { hash: Some(hash_algorithms.clone()), prefilter: Some("xxh3".to_string()), ..Default::default() };
+        let virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, None).unwrap();
+        let (virtual_files, budget_exceeded): (Vec<VirtualFile>, bool) = prefilter_handler("xxh3", &hash_algorithms, virtual_files, &config).unwrap();
+        assert!(!budget_exceeded);
+
+        // Only the files sharing their xxh3 checksum have a sha256 checksum, the xxh3 checksums are dropped.
+        let hashed: Vec<&str> = virtual_files.iter().filter(|f| f.checksums.is_some()).map(|f| f.name.as_str()).collect();
+        assert_eq!(hashed, vec!["a1.txt", "a2.txt", "a3.txt"]);
+        assert!(virtual_files.iter().filter_map(|f| f.checksums.as_ref()).all(|c| c.keys().eq(["sha256"])));
+
+        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&virtual_files, &config).unwrap();
         assert_eq!(duplicates.len(), 1);
-        
-        let duplicate: &DuplicateFile = &duplicates[0];
-        assert_eq!(duplicate.name, "file1.txt");
-        assert_eq!(duplicate.paths.len(), 2);
-        assert!(duplicate.paths.contains("/tmp/file1.txt"));
-        assert!(duplicate.paths.contains("/tmp/copy_file1.txt"));
+        assert_eq!(duplicates[0].paths.len(), 3);
+
+        let _ = fs::remove_dir_all(dir);
     }
 
     #[test]
-    fn test_search_eventual_duplicates_by_checksum() {
-        let mut files: Vec<VirtualFile> = Vec::new();
-        let mut checksums: HashMap<String, String> = HashMap::new();
-        checksums.insert("md5".to_string(), "abc123".to_string());
+    fn test_hash_handler_with_size_pairs() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_size_pairs");
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in [("a1.txt", "same"), ("a2.txt", "same"), ("b1.txt", "diff1"), ("b2.txt", "diff2")] {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        let file_paths: Vec<String> = ["a1.txt", "b1.txt", "a2.txt", "b2.txt"].iter().map(|n| dir.join(n).to_string_lossy().to_string()).collect();
 
-        files.push(VirtualFile {
-            name: "fileA.txt".to_string(),
-            full_path: "/tmp/fileA.txt".to_string(),
-            size: 100,
-            checksums: Some(checksums.clone()),
-        });
-        files.push(VirtualFile {
-            name: "fileB.txt".to_string(),
-            full_path: "/tmp/fileB.txt".to_string(),
+        // Each size is shared by two files, so the files are compared by pairs: an identical pair has its real checksum, a different one has none.
+        let config: FindingConfig = FindingConfig { hash: Some(vec!["md5".to_string(), "sha1".to_string()]), ..Default::default() };
+        let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, None).unwrap();
+        assert!(matches!(hash_units(&virtual_files, compares_size_pairs(&config)).as_slice(), [HashUnit::Pair(0, 2), HashUnit::Pair(1, 3)]));
+        assert!(!hash_handler(config.hash.as_ref().unwrap(), &mut virtual_files, &config).unwrap());
+        for file in &virtual_files[..] {
+            if file.name.starts_with('a') {
+                let checksums: &BTreeMap<String, String> = file.checksums.as_ref().unwrap();
+                assert_eq!(checksums.get("md5"), system::manage_hash(&file.full_path, "md5").unwrap().as_ref());
+                assert_eq!(checksums.get("sha1"), system::manage_hash(&file.full_path, "sha1").unwrap().as_ref());
+            } else {
+                assert!(file.checksums.is_none());
+            }
+        }
+        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&virtual_files, &config).unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].paths, [&file_paths[0], &file_paths[2]].into_iter().cloned().collect::<HashSet<String>>());
+
+        // A pair counts twice in the byte budget, like two files.
+        let config: FindingConfig = FindingConfig { hash: Some(vec!["md5".to_string()]), byte_budget: Some(9), ..Default::default() };
+        let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, None).unwrap();
+        assert!(hash_with_workers(&["md5".to_string()], &mut virtual_files, &config, 1, None, Instant::now).unwrap());
+        assert_eq!(virtual_files.iter().filter(|f| f.checksums.is_some()).count(), 2);
+
+        // The inventory of all the files needs every checksum, so the files are hashed on their own.
+        let config: FindingConfig = FindingConfig { all_files: true, ..config };
+        assert!(hash_units(&virtual_files, compares_size_pairs(&config)).iter().all(|unit| matches!(unit, HashUnit::File(_))));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_combine_checksums() {
+        let duplicate = |checksums: &[(&str, &str)]| DuplicateFile {
+            paths: HashSet::new(),
+            name: "file.txt".to_string(),
+            checksums: Some(checksums.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()),
+            size: 10,
+            sizes: BTreeMap::new(),
+        };
+
+        let mut first: DuplicateFile = duplicate(&[("sha256", "bbb"), ("md5", "aaa"), ("blake3", "ccc")]);
+        let mut second: DuplicateFile = duplicate(&[("md5", "aaa"), ("blake3", "ccc"), ("sha256", "bbb")]);
+        assert_eq!(first.checksums.as_ref().unwrap().keys().collect::<Vec<_>>(), vec!["blake3", "md5", "sha256"]);
+        let json: String = serde_json::to_string(&first).unwrap();
+        assert!(json.find("blake3").unwrap() < json.find("md5").unwrap() && json.find("md5").unwrap() < json.find("sha256").unwrap());
+
+        first.combine_checksums();
+        second.combine_checksums();
+        assert_eq!(first.checksums, Some(BTreeMap::from([("blake3+md5+sha256".to_string(), "cccaaabbb".to_string())])));
+        assert_eq!(first.checksums, second.checksums);
+    }
+
+    #[test]
+    fn test_benchmark_timings() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_benchmark");
+        fs::create_dir_all(&dir).unwrap();
+        for i in 0..50 {
+            fs::write(dir.join(format!("file{i}.bin")), vec![(i % 5) as u8; 10_000]).unwrap();
+        }
+
+        let config: FindingConfig = FindingConfig {
+            search_path: dir.to_string_lossy().to_string(),
+            hash: Some(vec!["sha256".to_string()]),
+            benchmark: true,
+            ..Default::default()
+        };
+        let mut timings: Vec<PhaseTiming> = Vec::new();
+        let virtual_files: Vec<VirtualFile> = scan_virtual_files(&config, &mut timings).unwrap();
+        let start: Instant = Instant::now();
+        assert_eq!(search_eventual_duplicates(&virtual_files, &config).unwrap().len(), 5);
+        timings.push(PhaseTiming { name: "grouping", elapsed: start.elapsed(), files: virtual_files.len(), bytes: 0 });
+
+        assert_eq!(timings.iter().map(|t| t.name).collect::<Vec<_>>(), vec!["traversal", "hashing", "grouping"]);
+        assert!(timings.iter().all(|t| t.elapsed > Duration::ZERO && t.files == 50));
+        assert_eq!(timings[1].bytes, 500_000);
+
+        let output: String = format_benchmark(&timings);
+        assert_eq!(output.lines().count(), 4);
+        assert!(output.lines().nth(1).unwrap().starts_with("Benchmark: hashing") && output.contains("MB/s"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_merge_duplicates() {
+        let duplicate = |paths: &[&str], md5: &str| DuplicateFile {
+            paths: paths.iter().map(ToString::to_string).collect(),
+            name: "file.txt".to_string(),
+            checksums: Some([("md5".to_string(), md5.to_string()), ("sha1".to_string(), format!("sha1-{md5}"))].into_iter().collect()),
+            size: 10,
+            sizes: BTreeMap::new(),
+        };
+        let first_report: Vec<DuplicateFile> = vec![duplicate(&["/a/1", "/a/2"], "aaa"), duplicate(&["/a/3", "/a/4"], "bbb")];
+        let second_report: Vec<DuplicateFile> = vec![duplicate(&["/b/1", "/a/2"], "aaa"), duplicate(&["/b/2", "/b/3"], "ccc")];
+
+        let merged: Vec<DuplicateFile> = merge_duplicates(vec![first_report, second_report], &FindingConfig::default());
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0].paths, ["/a/1", "/a/2", "/b/1"].iter().map(ToString::to_string).collect::<HashSet<String>>());
+        assert_eq!(merged[1].paths.len(), 2);
+        assert_eq!(merged[2].group_key(), Some("md5:ccc|sha1:sha1-ccc".to_string()));
+
+        // The groups without checksums are never merged by their name.
+        let config: FindingConfig = FindingConfig { quiet: true, ..Default::default() };
+        let unhashed = |path: &str| DuplicateFile { paths: HashSet::from([path.to_string()]), name: "file.txt".to_string(), checksums: None, size: 10, sizes: BTreeMap::new() };
+        let merged: Vec<DuplicateFile> = merge_duplicates(vec![vec![unhashed("/a/file.txt")], vec![unhashed("/b/file.txt")]], &config);
+        assert_eq!(merged.len(), 2);
+        assert!(take_warnings(&config).iter().any(|w| w.kind == "report_conflict" && w.detail.starts_with("2 groups have no checksums")));
+    }
+
+    #[test]
+    fn test_search_similar_images() {
+        let image = |name: &str, ahash: &str| VirtualFile {
+            name: name.to_string(),
+            full_path: format!("/tmp/{name}"),
             size: 100,
-            checksums: Some(checksums.clone()),
-        });
+            checksums: Some([("ahash".to_string(), ahash.to_string())].into_iter().collect()),
+            ..Default::default()
+        };
+        let files: Vec<VirtualFile> = vec![
+            image("photo.jpg", "ff00ff00ff00ff00"),
+            image("photo_small.png", "ff00ff00ff00ff01"),
+            image("other.png", "00ff00ff00ff00ff"),
+        ];
+
+        let similar: Vec<DuplicateFile> = search_similar_images(&files, 5);
+        assert_eq!(similar.len(), 1);
+        assert!(similar[0].paths.contains("/tmp/photo.jpg"));
+        assert!(similar[0].paths.contains("/tmp/photo_small.png"));
+        assert!(search_similar_images(&files, 0).is_empty());
+    }
+
+    #[test]
+    fn test_progress_reporter() {
+        let progress: ProgressReporter<Vec<u8>> = ProgressReporter::new(Vec::new());
+        progress.start("traversal", None);
+        progress.advance(3, 0);
+        progress.finish();
+        progress.start("hashing", Some(1_000));
+        (0..1_000).for_each(|_| progress.advance(1, 10));
+        progress.finish();
+
+        // Each line is a JSON event, the events between the start and the end of a phase are throttled.
+        let output: String = String::from_utf8(progress.state.into_inner().unwrap().writer).unwrap();
+        let events: Vec<serde_json::Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert!(events.len() < 20);
+        assert_eq!(events[0], serde_json::json!({"phase": "traversal", "done": 0, "total": null, "bytes": 0}));
+        assert!(events.iter().any(|e| *e == serde_json::json!({"phase": "traversal", "done": 3, "total": 3, "bytes": 0})));
+        assert_eq!(events.last(), Some(&serde_json::json!({"phase": "hashing", "done": 1_000, "total": 1_000, "bytes": 10_000})));
+    }
 
+    #[test]
+    fn test_hash_with_a_single_worker() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_single_worker");
+        fs::create_dir_all(&dir).unwrap();
+        let file_paths: Vec<String> = (0..7).map(|i| {
+            let path: std::path::PathBuf = dir.join(format!("file{i}.txt"));
+            fs::write(&path, format!("content {i}")).unwrap();
+            path.to_string_lossy().to_string()
+        }).collect();
+
+        // A single thread (or none reported) hashes every file, the same way as several threads.
+        let mut expected: Vec<VirtualFile> = build_virtual_files(&file_paths, None).unwrap();
+        hash_with_workers(&["md5".to_string()], &mut expected, &FindingConfig::default(), 4, None, Instant::now).unwrap();
+        for workers in [0, 1] {
+            let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, None).unwrap();
+            assert!(!hash_with_workers(&["md5".to_string()], &mut virtual_files, &FindingConfig::default(), workers, None, Instant::now).unwrap());
+            for (file, expected_file) in virtual_files.iter().zip(&expected) {
+                assert_eq!(file.checksums.as_ref().unwrap().get("md5"), system::manage_hash(&file.full_path, "md5").unwrap().as_ref());
+                assert_eq!(file.checksums, expected_file.checksums);
+            }
+        }
+        assert!(worker_count() >= 1);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_hash_with_checkpoints() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_checkpoints");
+        fs::create_dir_all(&dir).unwrap();
+        let file_paths: Vec<String> = (0..8).map(|i| {
+            let path: std::path::PathBuf = dir.join(format!("file{i}.txt"));
+            let content: String = if i < 4 { format!("early {}", "x".repeat(i % 2 + 1)) } else { format!("late {}", "x".repeat(i % 2 + 1)) };
+            fs::write(&path, content).unwrap();
+            path.to_string_lossy().to_string()
+        }).collect();
+        let checkpoint_path: String = dir.join("checkpoint.json").to_string_lossy().to_string();
         let config: FindingConfig = FindingConfig {
-            search_path: "/tmp".to_string(),
-            enable_search_by_name: false,
-            include_hidden_files: false,
-            include_hashes_in_output: false,
-            hash: None,
-            output: CliOutput::Standard,
+            hash: Some(vec!["md5".to_string()]),
+            output: crate::cli::CliOutput::JsonFile(checkpoint_path.clone()),
+            ..Default::default()
         };
 
-        let duplicates: Vec<DuplicateFile> = search_eventual_duplicates(&files, &config);
-        assert_eq!(duplicates.len(), 1);
-        
-        let duplicate: &DuplicateFile = &duplicates[0];
-        assert_eq!(duplicate.paths.len(), 2);
-        assert!(duplicate.paths.contains("/tmp/fileA.txt"));
-        assert!(duplicate.paths.contains("/tmp/fileB.txt"));
+        // The clock moves 10 seconds forward at each hashed file, so a checkpoint is written after the 4th one, and none after the last one.
+        let base: Instant = Instant::now();
+        let mut ticks: u64 = 0;
+        let clock = || {
+            ticks += 1;
+            base + Duration::from_secs(10 * ticks)
+        };
+        let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, None).unwrap();
+        hash_with_workers(&["md5".to_string()], &mut virtual_files, &config, 1, Some(Duration::from_secs(35)), clock).unwrap();
+        assert!(virtual_files.iter().all(|f| f.checksums.is_some()));
+
+        // The checkpoint only holds the groups of the files hashed before it.
+        let checkpoint: Vec<DuplicateFile> = load_json_report(&checkpoint_path).unwrap();
+        assert_eq!(checkpoint.len(), 2);
+        for duplicate in &checkpoint {
+            assert_eq!(duplicate.paths.len(), 2);
+            assert!(duplicate.paths.iter().all(|path| file_paths[..4].contains(path)));
+        }
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_hash_handler_with_algo_for_large() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_algo_for_large");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file_paths: Vec<String> = [("small.txt", 10), ("large.bin", 100), ("larger.bin", 200)].iter().map(|(name, size)| {
+            let path: std::path::PathBuf = dir.join(name);
+            fs::write(&path, vec![b'a'; *size]).unwrap();
+            path.to_string_lossy().to_string()
+        }).collect();
+
+        let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, None).unwrap();
+        let config: FindingConfig = FindingConfig { algo_for_large: Some(("blake3".to_string(), 100)), ..Default::default() };
+        assert!(!hash_handler(&["sha256".to_string()], &mut virtual_files, &config).unwrap());
+
+        // The files under the threshold are hashed with the primary algorithm, the other ones with the large one only.
+        let algorithms: Vec<Vec<&str>> = virtual_files.iter().map(|f| f.checksums.as_ref().unwrap().keys().map(String::as_str).collect()).collect();
+        assert_eq!(algorithms, [vec!["sha256"], vec!["blake3"], vec!["blake3"]]);
+        assert_eq!(virtual_files[1].checksums.as_ref().unwrap().get("blake3"), system::manage_hash(&file_paths[1], "blake3").unwrap().as_ref());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_hash_handler_in_place() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_hash_handler");
+        fs::create_dir_all(&dir).unwrap();
+        let file_paths: Vec<String> = (0..5).map(|i| {
+            let path: std::path::PathBuf = dir.join(format!("file{i}.txt"));
+            fs::write(&path, format!("content {}", i % 2)).unwrap();
+            path.to_string_lossy().to_string()
+        }).collect();
+
+        let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, None).unwrap();
+        let hash_algorithms: Vec<String> = vec!["md5".to_string(), "sha1".to_string()];
+        assert!(!hash_handler(&hash_algorithms, &mut virtual_files, &FindingConfig::default()).unwrap());
+
+        // The files keep their order and each of them has every checksum.
+        assert_eq!(virtual_files.iter().map(|f| f.full_path.clone()).collect::<Vec<String>>(), file_paths);
+        for file in &virtual_files {
+            let checksums: &BTreeMap<String, String> = file.checksums.as_ref().unwrap();
+            assert_eq!(checksums.len(), 2);
+            assert_eq!(checksums.get("md5"), system::manage_hash(&file.full_path, "md5").unwrap().as_ref());
+        }
+        assert_eq!(virtual_files[0].checksums, virtual_files[2].checksums);
+        assert_ne!(virtual_files[0].checksums, virtual_files[1].checksums);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_hash_handler_with_byte_budget() {
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_byte_budget");
+        fs::create_dir_all(&dir).unwrap();
+        let file_paths: Vec<String> = (0..5).map(|i| {
+            let path: std::path::PathBuf = dir.join(format!("file{i}.bin"));
+            fs::write(&path, [i; 100]).unwrap();
+            path.to_string_lossy().to_string()
+        }).collect();
+
+        let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, None).unwrap();
+        let config: FindingConfig = FindingConfig { byte_budget: Some(250), ..Default::default() };
+        assert!(hash_handler(&["md5".to_string()], &mut virtual_files, &config).unwrap());
+
+        // Only two files of 100 bytes fit in the budget, the others are not hashed.
+        assert_eq!(virtual_files.iter().filter(|f| f.checksums.is_some()).count(), 2);
+
+        let _ = fs::remove_dir_all(dir);
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn test_access_denied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir: std::path::PathBuf = std::env::temp_dir().join("deepfinder_test_access_denied");
+        let locked_dir: std::path::PathBuf = dir.join("locked");
+        let locked_file: std::path::PathBuf = dir.join("locked.txt");
+        fs::create_dir_all(&locked_dir).unwrap();
+        fs::write(&locked_file, "content").unwrap();
+        fs::set_permissions(&locked_dir, fs::Permissions::from_mode(0o000)).unwrap();
+        fs::set_permissions(&locked_file, fs::Permissions::from_mode(0o000)).unwrap();
+
+        // The permissions are bypassed by a privileged user (e.g. root), nothing can be denied.
+        if fs::File::open(&locked_file).is_err() {
+            let locked_dir_path: String = locked_dir.to_string_lossy().to_string();
+            assert_eq!(search_files(&locked_dir_path, &FindingConfig::default()), Err(SystemError::AccessDenied(locked_dir_path)));
+
+            let locked_file_path: String = locked_file.to_string_lossy().to_string();
+            // A file that can't be read is skipped, the search goes on.
+            let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&vec![locked_file_path.clone()], None).unwrap();
+            let config: FindingConfig = FindingConfig { quiet: true, ..Default::default() };
+            assert_eq!(hash_handler(&["md5".to_string()], &mut virtual_files, &config), Ok(false));
+            assert!(virtual_files[0].checksums.is_none());
+            assert_eq!(
+                take_warnings(&config),
+                [Warning { kind: "skipped_file", detail: format!("{}, it has been skipped.", SystemError::AccessDenied(locked_file_path).to_string().trim_start_matches("Error: ")) }]
+            );
+        }
+
+        // Neither does a file deleted since the traversal.
+        let vanished_path: String = dir.join("vanished.txt").to_string_lossy().to_string();
+        fs::write(&vanished_path, "content").unwrap();
+        let mut virtual_files: Vec<VirtualFile> = build_virtual_files(&vec![vanished_path.clone()], None).unwrap();
+        fs::remove_file(&vanished_path).unwrap();
+        let config: FindingConfig = FindingConfig { quiet: true, ..Default::default() };
+        assert_eq!(hash_handler(&["md5".to_string()], &mut virtual_files, &config), Ok(false));
+        assert!(virtual_files[0].checksums.is_none());
+        assert!(take_warnings(&config).iter().any(|w| w.kind == "skipped_file" && w.detail.contains(&vanished_path)));
+
+        fs::set_permissions(&locked_dir, fs::Permissions::from_mode(0o755)).unwrap();
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_kept_path() {
+        let duplicate: DuplicateFile = DuplicateFile {
+            paths: ["/tmp/b/file.txt".to_string(), "/tmp/a/long/file.txt".to_string(), "/tmp/c/file.txt".to_string()].into_iter().collect(),
+            name: "file.txt".to_string(),
+            checksums: None,
+            size: 10,
+            sizes: BTreeMap::new(),
+        };
+
+        assert_eq!(duplicate.kept_path(KeepPolicy::First, &[]).unwrap(), "/tmp/a/long/file.txt");
+        assert_eq!(duplicate.kept_path(KeepPolicy::Last, &[]).unwrap(), "/tmp/c/file.txt");
+        assert_eq!(duplicate.kept_path(KeepPolicy::Shortest, &[]).unwrap(), "/tmp/b/file.txt");
+        assert_eq!(duplicate.kept_path(KeepPolicy::Longest, &[]).unwrap(), "/tmp/a/long/file.txt");
+        assert_eq!(duplicate.kept_path(KeepPolicy::Oldest, &[]).unwrap(), "/tmp/a/long/file.txt"); // No modification time, alphabetical order.
+
+        // The copy in the preferred directory survives, whatever the policy, the policy only breaks the ties.
+        let prefer_dirs: Vec<String> = vec!["/tmp/c".to_string(), "/tmp/b".to_string()];
+        assert_eq!(duplicate.kept_path(KeepPolicy::First, &prefer_dirs).unwrap(), "/tmp/c/file.txt");
+        assert_eq!(duplicate.kept_path(KeepPolicy::Longest, &["/tmp/b".to_string(), "/tmp/c".to_string()]).unwrap(), "/tmp/b/file.txt");
+        assert_eq!(duplicate.kept_path(KeepPolicy::Last, &["/tmp/a".to_string()]).unwrap(), "/tmp/a/long/file.txt");
+        assert_eq!(duplicate.kept_path(KeepPolicy::Last, &["/tmp".to_string()]).unwrap(), "/tmp/c/file.txt");
+        // A directory is matched by its components, not as a prefix of the paths.
+        assert_eq!(duplicate.kept_path(KeepPolicy::Last, &["/tmp/a/lo".to_string()]).unwrap(), "/tmp/c/file.txt");
+    }
+}
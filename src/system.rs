@@ -1,20 +1,30 @@
 // Internal crates.
-use crate::error::SystemError;
+use crate::{error::SystemError, image_hash};
 
 // External crates.
 use blake2::{Blake2b512, Blake2s256};
 use digest::Digest;
+use infer::MatcherType;
 use md5::Md5;
+use serde::Serialize;
 use sha1::Sha1;
 use sha2::{Sha224, Sha256, Sha384, Sha512};
 use sha3::{Sha3_224, Sha3_256, Sha3_384, Sha3_512};
 use std::{
-    collections::HashMap,
+    collections::BTreeMap,
     fs::{self, File},
-    io::{BufReader, Read},
-    path::Path,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
 };
 use whirlpool::Whirlpool;
+use xxhash_rust::xxh3::Xxh3;
 
 #[cfg(target_family = "unix")]
 use std::os::unix::fs::MetadataExt;
@@ -22,20 +32,38 @@ use std::os::unix::fs::MetadataExt;
 #[cfg(target_family = "windows")]
 use std::os::windows::fs::MetadataExt;
 
+/// The size of the buffer used to read the files when they are hashed.
+const BUFFER_SIZE: usize = 1024;
+
+/// The size of the chunks read at once when a single file is hashed with several threads.
+const PARALLEL_BUFFER_SIZE: usize = 16 * 1024 * 1024;
+
+/// The maximum size of a text file hashed case-insensitively, since its whole content is lowercased in memory.
+const CASE_FOLD_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Number of bytes read at the start of a file to decide whether it holds text.
+const TEXT_SNIFF_SIZE: u64 = 8 * 1024;
+
 /// This struct represents a virtual file on the system.
 /// It permits the program to store the file's name, size, full path and checksum properly.
 ///
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct VirtualFile {
     pub name: String,
     pub size: u64,
     pub full_path: String,
-    pub checksums: Option<HashMap<String, String>>,
+    pub checksums: Option<BTreeMap<String, String>>,
+    /// The kind of the entry, "symlink" for a symbolic link listed as an entry ('--include-symlinks-as-entries'), None for a file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<&'static str>,
+    /// The path the symbolic link points to, None for a file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
 }
 
 impl VirtualFile {
     /// This function is responsible for updating the checksum of the file.
-    /// It creates a HashMap if it doesn't exist and adds the hash and checksum to it.
+    /// It creates a BTreeMap (sorted by algorithm) if it doesn't exist and adds the hash and checksum to it.
     ///
     /// # Arguments
     ///
@@ -46,13 +74,29 @@ impl VirtualFile {
         if let Some(ref mut map) = self.checksums {
             map.insert(hash.to_string(), checksum);
         } else {
-            let mut map: HashMap<String, String> = HashMap::new();
+            let mut map: BTreeMap<String, String> = BTreeMap::new();
             map.insert(hash.to_string(), checksum);
             self.checksums = Some(map);
         }
     }
 }
 
+/// This function is responsible for truncating the checksums to their first characters, to get a compact output ('--hash-truncate').
+/// The truncated checksums are only displayed, the files are always grouped with the full ones.
+///
+/// # Arguments
+///
+/// * `checksums` - The checksums to truncate, by algorithm.
+/// * `length` - The number of characters kept in each checksum.
+///
+pub fn truncate_checksums(checksums: &mut BTreeMap<String, String>, length: usize) {
+    for checksum in checksums.values_mut() {
+        if let Some((index, _)) = checksum.char_indices().nth(length) {
+            checksum.truncate(index);
+        }
+    }
+}
+
 /// This function is responsible for checking a path/filename.
 ///
 /// # Arguments
@@ -114,6 +158,7 @@ pub fn check_if_parent_folder_exists(file_path: &str) -> bool {
 /// # Returns
 ///
 /// Ok(String) if the path is valid, containing the full path, SystemError otherwise.
+/// SystemError::NotADirectory if the path exists but isn't a directory.
 ///
 pub fn is_valid_folder_path(path: &str) -> Result<String, SystemError> {
     let full_path: String = build_full_path(path)?;
@@ -121,69 +166,800 @@ pub fn is_valid_folder_path(path: &str) -> Result<String, SystemError> {
         return Err(SystemError::InvalidFolder(full_path));
     }
 
+    if !Path::new(&full_path).is_dir() {
+        return Err(SystemError::NotADirectory(full_path));
+    }
+
     Ok(full_path)
 }
 
+/// This function is responsible for checking that files can be created in a folder, by creating and removing a probe file.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path of the folder.
+///
+/// # Returns
+///
+/// Ok if a file can be created in the folder, SystemError::TempDirNotWritable otherwise.
+///
+pub fn check_writable_folder(path: &str) -> Result<(), SystemError> {
+    let probe_path: PathBuf = Path::new(path).join(format!(".deepfinder-{}.probe", process::id()));
+    File::create_new(&probe_path)
+        .and_then(|_| fs::remove_file(&probe_path))
+        .map_err(|e| SystemError::TempDirNotWritable(path.to_string(), e))
+}
+
+/// This function is responsible for running the command of '--on-group' for a group of duplicates.
+/// The command is run by the shell (cmd on Windows), with the group details in its environment and its JSON on the standard input.
+/// Its standard output is redirected to the standard error, so it never mixes with the results written on the standard output.
+///
+/// # Arguments
+///
+/// * `command` - A string slice that holds the command to run.
+/// * `env_vars` - The environment variables describing the group (DF_GROUP_NAME, DF_GROUP_SIZE, DF_GROUP_PATHS).
+/// * `input` - The group serialized in JSON, written on the standard input of the command.
+///
+/// # Returns
+///
+/// Ok if the command has succeeded, the reason of its failure otherwise.
+///
+pub fn run_group_hook(command: &str, env_vars: &[(&str, String)], input: &[u8]) -> Result<(), String> {
+    #[cfg(target_family = "windows")]
+    let mut shell: process::Command = process::Command::new("cmd");
+    #[cfg(target_family = "windows")]
+    shell.args(["/C", command]);
+    #[cfg(not(target_family = "windows"))]
+    let mut shell: process::Command = process::Command::new("sh");
+    #[cfg(not(target_family = "windows"))]
+    shell.args(["-c", command]);
+
+    let mut child: process::Child = shell
+        .envs(env_vars.iter().map(|(key, value)| (*key, value)))
+        .stdin(process::Stdio::piped())
+        .stdout(io::stderr())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // The command may not read its input, so a closed pipe isn't a failure.
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(input) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e.to_string()),
+            _ => {}
+        }
+    }
+
+    let status: process::ExitStatus = child.wait().map_err(|e| e.to_string())?;
+    if status.success() { Ok(()) } else { Err(status.to_string()) }
+}
+
+/// This function is responsible for checking if two paths are on the same filesystem ('--read-only').
+/// A path that doesn't exist yet (e.g. an output file) is checked through its nearest existing ancestor.
+///
+/// # Arguments
+///
+/// * `first` - A string slice that holds the first path.
+/// * `second` - A string slice that holds the second path.
+///
+/// # Returns
+///
+/// True if both paths are on the same filesystem (same device on Unix, same volume on Windows) or if it can't be determined, false otherwise.
+///
+pub fn is_same_filesystem(first: &str, second: &str) -> bool {
+    let existing_ancestor = |path: &str| Path::new(path).ancestors().find(|ancestor| ancestor.exists()).and_then(|ancestor| fs::canonicalize(ancestor).ok());
+    let (Some(first), Some(second)) = (existing_ancestor(first), existing_ancestor(second)) else {
+        return true;
+    };
+
+    #[cfg(target_family = "unix")]
+    let same_filesystem: bool = fs::metadata(&first).map(|m| m.dev()).ok() == fs::metadata(&second).map(|m| m.dev()).ok();
+    #[cfg(target_family = "windows")]
+    let same_filesystem: bool = first.components().next() == second.components().next();
+    same_filesystem
+}
+
+/// This function is responsible for checking if the filesystem holding a path ignores the case of the names (e.g. APFS, NTFS).
+/// The nearest existing ancestor whose name has letters is looked up again with its case swapped, both names lead to it if the case is ignored.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path, which may not exist yet (e.g. an output file).
+///
+/// # Returns
+///
+/// True if the filesystem is case-insensitive, false otherwise. The default of the platform is used if it can't be determined.
+///
+pub fn is_case_insensitive(path: &str) -> bool {
+    let existing_dir: Option<PathBuf> = Path::new(path).ancestors().find(|ancestor| ancestor.is_dir()).and_then(|ancestor| fs::canonicalize(ancestor).ok());
+    for ancestor in existing_dir.iter().flat_map(|dir| dir.ancestors()) {
+        let Some(name) = ancestor.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let swapped_name: String = name.chars().map(|c| if c.is_lowercase() { c.to_uppercase().to_string() } else { c.to_lowercase().to_string() }).collect();
+        if swapped_name == name {
+            continue;
+        }
+
+        let swapped_path: PathBuf = ancestor.with_file_name(swapped_name);
+        #[cfg(target_family = "unix")]
+        let same_dir: bool = fs::metadata(&swapped_path).is_ok_and(|m| fs::metadata(ancestor).is_ok_and(|a| (a.dev(), a.ino()) == (m.dev(), m.ino())));
+        #[cfg(not(target_family = "unix"))]
+        let same_dir: bool = swapped_path.is_dir();
+        return same_dir;
+    }
+
+    cfg!(any(target_family = "windows", target_os = "macos"))
+}
+
+/// This function is responsible for identifying the physical file behind a path ('--unique-content').
+/// The hard links and the files reached through several bind mounts share the same device and inode.
+/// The file index of Windows isn't exposed by the stable standard library, so the files are never identified there.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path of the file.
+///
+/// # Returns
+///
+/// The device and inode of the file, None if they can't be read.
+///
+pub fn physical_file_id(path: &str) -> Option<(u64, u64)> {
+    #[cfg(target_family = "unix")]
+    {
+        fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+    }
+
+    #[cfg(target_family = "windows")]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// This function is responsible for reading the exclude patterns of an ignore file ('.deepfinderignore' or '--ignore-file').
+/// The lines starting with '#' are comments, the other ones hold glob patterns separated by commas.
+/// The patterns are trimmed, so they can hold inner spaces (e.g. "My Documents").
+///
+/// # Arguments
+///
+/// * `path` - The path of the ignore file.
+///
+/// # Returns
+///
+/// The glob patterns of the ignore file, SystemError::UnableToReadFile if it can't be read.
+///
+pub fn read_ignore_file(path: &str) -> Result<Vec<String>, SystemError> {
+    let content: String = fs::read_to_string(path).map_err(|e| SystemError::UnableToReadFile(path.to_string(), e))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .flat_map(|line| line.split(',').map(str::trim))
+        .filter(|pattern| !pattern.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// This function is responsible for getting the free space available to the user in the filesystem of a folder.
+/// Only the unix platforms can query it, the free space is unknown on the other ones.
+///
+/// # Arguments
+///
+/// * `path` - The path of the folder.
+///
+/// # Returns
+///
+/// The number of bytes available, None if it is unknown.
+///
+pub fn available_space(path: &Path) -> Option<u64> {
+    #[cfg(target_family = "unix")]
+    {
+        rustix::fs::statvfs(path).ok().map(|stats| stats.f_bavail.saturating_mul(stats.f_frsize))
+    }
+
+    #[cfg(not(target_family = "unix"))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// This function is responsible for checking the free space before writing a file, so it fails early instead of leaving a truncated file.
+/// The folder of the file is checked, and the temporary folder too if the file is written through it.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path of the file to write.
+/// * `temp_dir` - The directory of the temporary file, None if it is written next to the file.
+/// * `needed` - The estimated size of the file, in bytes.
+/// * `available_space` - The function querying the free space of a folder (None if it is unknown).
+///
+/// # Returns
+///
+/// Ok if the free space is sufficient (or unknown), SystemError::InsufficientSpace otherwise.
+///
+pub fn check_free_space<F>(file_path: &str, temp_dir: Option<&str>, needed: u64, available_space: F) -> Result<(), SystemError>
+where
+    F: Fn(&Path) -> Option<u64>,
+{
+    let file_folder: &Path = Path::new(file_path).parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    for folder in [Some(file_folder), temp_dir.map(Path::new)].into_iter().flatten() {
+        if let Some(available) = available_space(folder)
+            && available < needed
+        {
+            return Err(SystemError::InsufficientSpace(folder.to_string_lossy().to_string(), needed, available));
+        }
+    }
+
+    Ok(())
+}
+
+/// This function is responsible for writing a file atomically: the content is written in a temporary file, which then replaces the file.
+/// The temporary file is created in `temp_dir` if specified, next to the file otherwise.
+/// If the rename fails (e.g. across filesystems), the temporary file is first copied next to the file, so the replacement stays atomic.
+/// The permissions of the replaced file are kept, and a symbolic link is written through: its target is replaced, not the link.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path of the file to write.
+/// * `temp_dir` - The directory of the temporary file, None to write it next to the file.
+/// * `write` - The closure writing the content into the buffered temporary file.
+///
+/// # Returns
+///
+/// Ok if the file has been written, an I/O error otherwise (the temporary file is removed in any case).
+///
+pub fn write_atomically<F>(file_path: &str, temp_dir: Option<&str>, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+{
+    let resolved_path: PathBuf = resolve_symlinks(Path::new(file_path))?;
+    let path: &Path = &resolved_path;
+    let file_name: String = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let temp_folder: PathBuf = match temp_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    let temp_path: PathBuf = temp_folder.join(format!(".{file_name}.{}.tmp", process::id()));
+
+    let result: io::Result<()> = File::create(&temp_path).and_then(|file| {
+        let mut writer: BufWriter<File> = BufWriter::new(file);
+        write(&mut writer)?;
+        let file: File = writer.into_inner().map_err(|e| e.into_error())?;
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()
+    });
+
+    // The rename fails across filesystems, the content is then copied to the folder of the file, and renamed from there.
+    let local_temp_path: PathBuf = path.parent().map(Path::to_path_buf).unwrap_or_default().join(format!(".{file_name}.{}.copy.tmp", process::id()));
+    let result: io::Result<()> = result.and_then(|()| {
+        fs::rename(&temp_path, path).or_else(|_| {
+            fs::copy(&temp_path, &local_temp_path)?;
+            File::open(&local_temp_path)?.sync_all()?;
+            fs::rename(&local_temp_path, path)
+        })
+    });
+    for leftover_path in [&temp_path, &local_temp_path] {
+        if leftover_path.exists() {
+            let _ = fs::remove_file(leftover_path);
+        }
+    }
+
+    result
+}
+
+/// This function is responsible for resolving the symbolic links of a path, even if its final target doesn't exist yet.
+/// A relative target is resolved from the folder of the link.
+///
+/// # Arguments
+///
+/// * `path` - The path to resolve.
+///
+/// # Returns
+///
+/// The path of the final target (the path itself if it isn't a symbolic link), an I/O error if there are too many links.
+///
+fn resolve_symlinks(path: &Path) -> io::Result<PathBuf> {
+    const MAX_LINKS: usize = 40;
+    let mut resolved_path: PathBuf = path.to_path_buf();
+    for _ in 0..MAX_LINKS {
+        if !fs::symlink_metadata(&resolved_path).is_ok_and(|m| m.file_type().is_symlink()) {
+            return Ok(resolved_path);
+        }
+        let target: PathBuf = fs::read_link(&resolved_path)?;
+        resolved_path = resolved_path.parent().map(|parent| parent.join(&target)).unwrap_or(target);
+    }
+
+    Err(io::Error::other(format!("too many levels of symbolic links: {}", path.display())))
+}
+
+/// The names of the transformations that can be applied on the content of the files before hashing them.
+pub const TRANSFORMS: [&str; 3] = ["none", "byteswap16", "byteswap32"];
+
+/// This enum is the registry of the transformations applied on the content of the files before hashing them.
+/// A transformation must be an involution (applied twice, it gives back the original content),
+/// so a file and its transformed copy share the same canonical checksum.
+/// A new transformation only needs its name in TRANSFORMS, in `from_name` and its implementation in `apply`.
+///
+#[derive(Eq, PartialEq, Debug, Default, Clone, Copy)]
+pub enum Transform {
+    #[default]
+    None,
+    ByteSwap16,
+    ByteSwap32,
+}
+
+impl Transform {
+    /// This function is responsible for finding a transformation from its name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - A string slice that holds the name of the transformation (see TRANSFORMS).
+    ///
+    /// # Returns
+    ///
+    /// The transformation, None if the name is unknown.
+    ///
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "byteswap16" => Some(Self::ByteSwap16),
+            "byteswap32" => Some(Self::ByteSwap32),
+            _ => None,
+        }
+    }
+
+    /// This function is responsible for applying the transformation on a buffer, in place.
+    /// The buffer must hold complete words, except at the end of the content: an incomplete last word is kept as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The bytes to transform.
+    ///
+    fn apply(self, buffer: &mut [u8]) {
+        match self {
+            Self::None => {}
+            Self::ByteSwap16 => buffer.chunks_exact_mut(2).for_each(<[u8]>::reverse),
+            Self::ByteSwap32 => buffer.chunks_exact_mut(4).for_each(<[u8]>::reverse),
+        }
+    }
+}
+
+/// This struct is a reader applying a transformation on the content of another reader.
+/// The content is read by blocks of BUFFER_SIZE bytes, a multiple of every word size, so no word is split between two blocks.
+///
+struct TransformReader<R: Read> {
+    reader: R,
+    transform: Transform,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> Read for TransformReader<R> {
+    fn read(&mut self, output: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.buffer.len() {
+            self.buffer.clear();
+            self.position = 0;
+            self.reader.by_ref().take(BUFFER_SIZE as u64).read_to_end(&mut self.buffer)?;
+            self.transform.apply(&mut self.buffer);
+        }
+
+        let count: usize = output.len().min(self.buffer.len() - self.position);
+        output[..count].copy_from_slice(&self.buffer[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+/// This struct limits the aggregate read bandwidth of the threads hashing the files (token bucket).
+/// The threads share the number of bytes read, each read is delayed until this number fits in the bandwidth since the first read.
+///
+pub struct IoThrottle {
+    bytes_per_second: u64,
+    start: OnceLock<Instant>,
+    consumed: AtomicU64,
+}
+
+impl IoThrottle {
+    pub fn new(bytes_per_second: u64) -> Self {
+        Self { bytes_per_second: bytes_per_second.max(1), start: OnceLock::new(), consumed: AtomicU64::new(0) }
+    }
+
+    /// This method is responsible for consuming the bytes just read, it sleeps until they fit in the bandwidth.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The number of bytes read.
+    ///
+    pub fn consume(&self, count: usize) {
+        let start: Instant = *self.start.get_or_init(Instant::now);
+        let consumed: u64 = self.consumed.fetch_add(count as u64, Ordering::Relaxed) + count as u64;
+        let expected: Duration = Duration::from_secs_f64(consumed as f64 / self.bytes_per_second as f64);
+        if let Some(delay) = expected.checked_sub(start.elapsed()) {
+            thread::sleep(delay);
+        }
+    }
+}
+
+/// The throttle of the reads done while hashing, shared by all the threads. The reads aren't throttled if it isn't set.
+static IO_THROTTLE: OnceLock<IoThrottle> = OnceLock::new();
+
+/// This function is responsible for limiting the read bandwidth while hashing, for the whole process.
+/// It should be called once at startup, the later calls are ignored.
+///
+/// # Arguments
+///
+/// * `bytes_per_second` - The aggregate read bandwidth allowed.
+///
+pub fn set_io_throttle(bytes_per_second: u64) {
+    let _ = IO_THROTTLE.set(IoThrottle::new(bytes_per_second));
+}
+
+/// This function is responsible for throttling a read done while hashing, if the throttle has been set.
+///
+/// # Arguments
+///
+/// * `count` - The number of bytes read.
+///
+fn throttle_read(count: usize) {
+    if let Some(throttle) = IO_THROTTLE.get() {
+        throttle.consume(count);
+    }
+}
+
 /// This function is responsible for managing file hashing.
 /// It returns the checksum of the file, using the hash algorithm provided.
+/// The "ahash" algorithm is the perceptual average hash of an image, it needs the whole file to decode it.
 ///
 /// # Arguments
 ///
-/// * `file` - The password to hash.
+/// * `file` - The path of the file to hash.
 /// * `hash` - The hash algorithm to use.
 ///
 /// # Returns
 ///
 /// The hashed file, or None if the hash algorithm is not supported.
+/// SystemError if the file cannot be read, AccessDenied if the permission is missing.
 ///
-pub fn manage_hash(file: &str, hash: &str) -> Option<String> {
-    match hash {
-        "md5" => hash_with_digest(Md5::new(), file),
-        "sha1" => hash_with_digest(Sha1::new(), file),
-        "sha224" => hash_with_digest(Sha224::new(), file),
-        "sha256" => hash_with_digest(Sha256::new(), file),
-        "sha384" => hash_with_digest(Sha384::new(), file),
-        "sha512" => hash_with_digest(Sha512::new(), file),
-        "sha3-224" => hash_with_digest(Sha3_224::new(), file),
-        "sha3-256" => hash_with_digest(Sha3_256::new(), file),
-        "sha3-384" => hash_with_digest(Sha3_384::new(), file),
-        "sha3-512" => hash_with_digest(Sha3_512::new(), file),
-        "blake2b-512" => hash_with_digest(Blake2b512::new(), file),
-        "blake2s-256" => hash_with_digest(Blake2s256::new(), file),
-        "whirlpool" => hash_with_digest(Whirlpool::new(), file),
-        _ => None,
+pub fn manage_hash(file: &str, hash: &str) -> Result<Option<String>, SystemError> {
+    if hash == "ahash" {
+        // Check the permission first, an image that can't be decoded is simply ignored.
+        File::open(file).map_err(|e| read_file_error(file, e))?;
+        return Ok(image_hash::average_hash(file).map(|h| format!("{h:016x}")));
     }
+
+    manage_transformed_hash(file, hash, Transform::None)
 }
 
-/// This function is responsible for calculating file hash with a specified algorithm.
-/// It returns the file hash.
+/// This function is responsible for managing the hashing of a file with a transformation applied on its content.
+/// The canonical checksum is the smallest one between the checksums of the content and of the transformed content,
+/// so a file and its transformed copy (e.g. byte-swapped) have the same checksum.
 ///
 /// # Arguments
 ///
-/// * `hasher` - The hasher to use, it must implement the Digest trait.
-/// * `file` - The file to hash.
+/// * `file` - The path of the file to hash.
+/// * `hash` - The hash algorithm to use.
+/// * `transform` - The transformation applied on the content, Transform::None to hash the content as is.
+///
+/// # Returns
+///
+/// The hashed file, or None if the hash algorithm is not supported.
+/// SystemError if the file cannot be read, AccessDenied if the permission is missing.
+///
+pub fn manage_transformed_hash(file: &str, hash: &str, transform: Transform) -> Result<Option<String>, SystemError> {
+    let input: File = File::open(file).map_err(|e| read_file_error(file, e))?;
+    let checksum: Option<String> = manage_hash_reader(BufReader::new(input), hash).map_err(|e| read_file_error(file, e))?;
+    if transform == Transform::None {
+        return Ok(checksum);
+    }
+
+    let input: File = File::open(file).map_err(|e| read_file_error(file, e))?;
+    let reader: TransformReader<BufReader<File>> = TransformReader { reader: BufReader::new(input), transform, buffer: Vec::new(), position: 0 };
+    let transformed_checksum: Option<String> = manage_hash_reader(reader, hash).map_err(|e| read_file_error(file, e))?;
+    Ok(checksum.min(transformed_checksum))
+}
+
+/// This function is responsible for managing the hashing of a file ignoring the case of its content ('--content-case-insensitive').
+/// A text file (valid UTF-8 without any NUL byte) is hashed lowercased, so two texts differing only by their case have the same checksum.
+/// The binary files and the files larger than CASE_FOLD_MAX_SIZE are hashed as is.
+///
+/// # Arguments
+///
+/// * `file` - The path of the file to hash.
+/// * `hash` - The hash algorithm to use.
+///
+/// # Returns
+///
+/// The hashed file, or None if the hash algorithm is not supported.
+/// SystemError if the file cannot be read, AccessDenied if the permission is missing.
+///
+pub fn manage_case_folded_hash(file: &str, hash: &str) -> Result<Option<String>, SystemError> {
+    if get_file_size(file)? > CASE_FOLD_MAX_SIZE {
+        return manage_hash(file, hash);
+    }
+
+    let content: Vec<u8> = fs::read(file).map_err(|e| read_file_error(file, e))?;
+    let folded: Option<String> = if content.contains(&0) { None } else { std::str::from_utf8(&content).ok().map(str::to_lowercase) };
+    manage_hash_reader(folded.as_ref().map_or(content.as_slice(), String::as_bytes), hash).map_err(|e| read_file_error(file, e))
+}
+
+/// This function is responsible for managing the hashing of any readable source (file, archive entry, ...).
+/// It returns the checksum of the content, using the hash algorithm provided.
+///
+/// # Arguments
+///
+/// * `reader` - The source to hash, it must implement the Read trait.
+/// * `hash` - The hash algorithm to use.
+///
+/// # Returns
+///
+/// The checksum of the content, or None if the hash algorithm is not supported. An I/O error if the source cannot be read.
+///
+pub fn manage_hash_reader<R: Read>(reader: R, hash: &str) -> io::Result<Option<String>> {
+    let checksum: String = match hash {
+        "md5" => hash_reader(reader, Md5::new())?,
+        "sha1" => hash_reader(reader, Sha1::new())?,
+        "sha224" => hash_reader(reader, Sha224::new())?,
+        "sha256" => hash_reader(reader, Sha256::new())?,
+        "sha384" => hash_reader(reader, Sha384::new())?,
+        "sha512" => hash_reader(reader, Sha512::new())?,
+        "sha3-224" => hash_reader(reader, Sha3_224::new())?,
+        "sha3-256" => hash_reader(reader, Sha3_256::new())?,
+        "sha3-384" => hash_reader(reader, Sha3_384::new())?,
+        "sha3-512" => hash_reader(reader, Sha3_512::new())?,
+        "blake2b-512" => hash_reader(reader, Blake2b512::new())?,
+        "blake2s-256" => hash_reader(reader, Blake2s256::new())?,
+        "whirlpool" => hash_reader(reader, Whirlpool::new())?,
+        "blake3" => hash_with_blake3(reader, BUFFER_SIZE, false)?,
+        "xxh3" => hash_with_xxh3(reader)?,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(checksum))
+}
+
+/// This function is responsible for hashing a single file with several threads.
+/// Only blake3 is a tree hash that can be computed in parallel, the other algorithms are computed sequentially.
+///
+/// # Arguments
+///
+/// * `file` - The path of the file to hash.
+/// * `hash` - The hash algorithm to use.
+///
+/// # Returns
+///
+/// The hashed file, or None if the hash algorithm is not supported.
+/// SystemError if the file cannot be read, AccessDenied if the permission is missing.
+///
+pub fn manage_parallel_hash(file: &str, hash: &str) -> Result<Option<String>, SystemError> {
+    if hash != "blake3" {
+        return manage_hash(file, hash);
+    }
+
+    let input: File = File::open(file).map_err(|e| read_file_error(file, e))?;
+    hash_with_blake3(input, PARALLEL_BUFFER_SIZE, true).map(Some).map_err(|e| read_file_error(file, e))
+}
+
+/// This function is responsible for calculating the blake3 hash of a readable source.
+/// In parallel mode, each chunk of the source is split between the threads of the rayon pool.
+///
+/// # Arguments
+///
+/// * `reader` - The source to hash, it must implement the Read trait.
+/// * `chunk_size` - The number of bytes read (and hashed) at once.
+/// * `parallel` - A boolean that indicates if the chunks should be hashed with several threads or not.
 ///
 /// # Returns
 ///
-/// The file hash as a hexadecimal string, or None if the file cannot be read.
+/// The hash as a hexadecimal string, an I/O error if the source cannot be read.
 ///
-fn hash_with_digest<D: Digest>(mut hasher: D, path: &str) -> Option<String> {
-    let input: File = File::open(path).ok()?;
-    let mut reader: BufReader<File> = BufReader::new(input);
+fn hash_with_blake3<R: Read>(reader: R, chunk_size: usize, parallel: bool) -> io::Result<String> {
+    let mut hasher: blake3::Hasher = blake3::Hasher::new();
+    let mut reader: BufReader<R> = BufReader::with_capacity(chunk_size, reader);
+    let mut buffer: Vec<u8> = Vec::with_capacity(chunk_size);
+
+    loop {
+        buffer.clear();
+        let count: usize = reader.by_ref().take(chunk_size as u64).read_to_end(&mut buffer)?;
+        if count == 0 {
+            break;
+        }
+
+        throttle_read(count);
+        if parallel {
+            hasher.update_rayon(&buffer);
+        } else {
+            hasher.update(&buffer);
+        }
+    }
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
 
+/// This function is responsible for calculating the xxh3 hash (64 bits) of a readable source.
+/// The xxh3 hash isn't cryptographic, it is a fast hash used to find the candidate duplicates.
+///
+/// # Arguments
+///
+/// * `reader` - The source to hash, it must implement the Read trait.
+///
+/// # Returns
+///
+/// The hash as a hexadecimal string (16 characters), an I/O error if the source cannot be read.
+///
+fn hash_with_xxh3<R: Read>(mut reader: R) -> io::Result<String> {
+    let mut hasher: Xxh3 = Xxh3::new();
+    let mut buffer: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
+    loop {
+        let count: usize = reader.read(&mut buffer)?;
+        if count == 0 {
+            break;
+        }
+        throttle_read(count);
+        hasher.update(&buffer[..count]);
+    }
+
+    Ok(format!("{:016x}", hasher.digest()))
+}
+
+/// This function is responsible for calculating the hash of a readable source with a specified algorithm.
+/// The source is streamed through a fixed-size buffer, so it is never fully loaded in memory.
+///
+/// # Arguments
+///
+/// * `reader` - The source to hash (file, stdin, archive entry, in-memory buffer...), it must implement the Read trait.
+/// * `hasher` - The hasher to use, it must implement the Digest trait.
+///
+/// # Returns
+///
+/// The hash as a hexadecimal string, an I/O error if the source cannot be read.
+///
+fn hash_reader<R: Read, D: Digest>(mut reader: R, mut hasher: D) -> io::Result<String> {
     let digest = {
-        let mut buffer: [u8; 1024] = [0; 1024];
+        let mut buffer: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
         loop {
-            let count: usize = reader.read(&mut buffer).unwrap();
+            let count: usize = reader.read(&mut buffer)?;
             if count == 0 {
                 break;
             }
+            throttle_read(count);
             hasher.update(&buffer[..count]);
         }
         hasher.finalize()
     };
 
-    Some(hex::encode(digest))
+    Ok(hex::encode(digest))
+}
+
+/// This function is responsible for comparing the content of two files, byte by byte.
+/// The files are streamed, so the comparison stops at the first difference (or at the end of the shortest file).
+///
+/// # Arguments
+///
+/// * `file_a` - The path of the first file.
+/// * `file_b` - The path of the second file.
+///
+/// # Returns
+///
+/// True if the files have the same content, false otherwise. SystemError if a file cannot be read.
+///
+pub fn files_equal(file_a: &str, file_b: &str) -> Result<bool, SystemError> {
+    let mut reader_a: BufReader<File> = BufReader::new(File::open(file_a).map_err(|e| read_file_error(file_a, e))?);
+    let mut reader_b: BufReader<File> = BufReader::new(File::open(file_b).map_err(|e| read_file_error(file_b, e))?);
+
+    loop {
+        let buffer_a: &[u8] = reader_a.fill_buf().map_err(|e| read_file_error(file_a, e))?;
+        let buffer_b: &[u8] = reader_b.fill_buf().map_err(|e| read_file_error(file_b, e))?;
+        if buffer_a.is_empty() || buffer_b.is_empty() {
+            return Ok(buffer_a.is_empty() && buffer_b.is_empty());
+        }
+
+        let length: usize = buffer_a.len().min(buffer_b.len());
+        if buffer_a[..length] != buffer_b[..length] {
+            return Ok(false);
+        }
+        reader_a.consume(length);
+        reader_b.consume(length);
+    }
+}
+
+/// This struct reads a file while checking that another file has the same content.
+/// It reaches its end at the first difference, so the hashing of a pair of files stops there.
+///
+struct EqualReader {
+    reader: File,
+    other: File,
+    other_buffer: Vec<u8>,
+    equal: bool,
+    other_error: Option<io::Error>,
+}
+
+impl Read for EqualReader {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if !self.equal {
+            return Ok(0);
+        }
+
+        let count: usize = self.reader.read(buffer)?;
+        self.other_buffer.resize(count.max(1), 0);
+        // The other file must have the same bytes, and end with this one.
+        let other_equal: io::Result<bool> = if count == 0 {
+            self.other.read(&mut self.other_buffer[..1]).map(|other_count| other_count == 0)
+        } else {
+            match self.other.read_exact(&mut self.other_buffer[..count]) {
+                Ok(()) => Ok(self.other_buffer[..count] == buffer[..count]),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+                Err(e) => Err(e),
+            }
+        };
+
+        match other_equal {
+            Ok(true) => {
+                throttle_read(count);
+                Ok(count)
+            }
+            Ok(false) => {
+                self.equal = false;
+                Ok(0)
+            }
+            Err(e) => {
+                self.equal = false;
+                self.other_error = Some(e);
+                Ok(0)
+            }
+        }
+    }
+}
+
+/// This function is responsible for hashing two files while comparing them, byte by byte.
+/// Both files are read once, and the reads stop at the first difference, so two different files are never read entirely.
+///
+/// # Arguments
+///
+/// * `file_a` - The path of the first file, the one hashed.
+/// * `file_b` - The path of the second file.
+///
+/// # Returns
+///
+/// The checksum of both files if they have the same content, None if they differ (or if the hash algorithm is not supported).
+/// SystemError if a file cannot be read, AccessDenied if the permission is missing.
+///
+pub fn hash_if_equal(file_a: &str, file_b: &str, hash: &str) -> Result<Option<String>, SystemError> {
+    let mut reader: EqualReader = EqualReader {
+        reader: File::open(file_a).map_err(|e| read_file_error(file_a, e))?,
+        other: File::open(file_b).map_err(|e| read_file_error(file_b, e))?,
+        other_buffer: Vec::new(),
+        equal: true,
+        other_error: None,
+    };
+
+    let checksum: Option<String> = manage_hash_reader(&mut reader, hash).map_err(|e| read_file_error(file_a, e))?;
+    if let Some(e) = reader.other_error {
+        return Err(read_file_error(file_b, e));
+    }
+
+    Ok(checksum.filter(|_| reader.equal))
+}
+
+/// This function is responsible for converting an I/O error raised while reading a file into a SystemError.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path of the file.
+/// * `error` - The I/O error raised.
+///
+/// # Returns
+///
+/// AccessDenied if the permission is missing, UnableToReadFile otherwise.
+///
+fn read_file_error(path: &str, error: io::Error) -> SystemError {
+    if error.kind() == io::ErrorKind::PermissionDenied {
+        SystemError::AccessDenied(path.to_string())
+    } else {
+        SystemError::UnableToReadFile(path.to_string(), error)
+    }
 }
 
 /// This function sends the invalid chars for windows platforms.
@@ -219,22 +995,67 @@ const fn get_invalid_chars() -> &'static [char] {
 /// A string containing the full path. DeepFinderError if the path is invalid or it can't get the current directory.
 ///
 fn build_full_path(path: &str) -> Result<String, SystemError> {
-    let full_path: String = if Path::new(path).is_absolute() {
-        path.to_string()
-    } else {
-        let current_dir: String = match std::env::current_dir() {
-            Ok(c) => match c.to_str() {
-                Some(s) => s.to_string(),
-                None => return Err(SystemError::InvalidPath(path.to_string())),
-            },
-            Err(e) => {
-                return Err(SystemError::UnableToGetCurrentDir(e.to_string()));
-            }
-        };
-        current_dir + "/" + path.trim_start_matches("./")
+    build_full_path_with(path, std::env::current_dir, std::env::var("PWD").ok())
+}
+
+/// This function is reponsible for building the entire path of a file/folder, with an injectable current directory resolver.
+/// If the current directory can't be determined (e.g. deleted or unmounted underneath the process),
+/// a relative path is resolved from the directory known by the shell (PWD), as long as it still exists.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path to build.
+/// * `current_dir` - The resolver of the current directory.
+/// * `known_dir` - The directory known by the shell, used if the current directory can't be determined.
+///
+/// # Returns
+///
+/// A string containing the full path. DeepFinderError if the path is invalid or it can't get the current directory.
+///
+fn build_full_path_with<F>(path: &str, current_dir: F, known_dir: Option<String>) -> Result<String, SystemError>
+where
+    F: FnOnce() -> io::Result<PathBuf>,
+{
+    let path: &str = &expand_tilde(path);
+    if Path::new(path).is_absolute() {
+        return Ok(path.to_string());
+    }
+
+    let current_dir: String = match current_dir() {
+        Ok(c) => match c.to_str() {
+            Some(s) => s.to_string(),
+            None => return Err(SystemError::InvalidPath(path.to_string())),
+        },
+        Err(e) => match known_dir.filter(|dir| Path::new(dir).is_absolute() && Path::new(dir).is_dir()) {
+            Some(dir) => dir,
+            None => return Err(SystemError::UnableToGetCurrentDir(path.to_string(), e)),
+        },
     };
 
-    Ok(full_path)
+    Ok(current_dir + "/" + path.trim_start_matches("./"))
+}
+
+/// This function is responsible for expanding a leading tilde to the home directory of the user.
+/// Only the '~' and '~/...' forms are expanded, a tilde in the middle of the path (or '~user') is kept as is.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path to expand.
+///
+/// # Returns
+///
+/// A string containing the expanded path, or the original path if it doesn't start with a tilde or the home directory is unknown.
+///
+fn expand_tilde(path: &str) -> String {
+    let Some(rest) = path.strip_prefix('~') else { return path.to_string() };
+    if !(rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\')) {
+        return path.to_string();
+    }
+
+    match std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")) {
+        Ok(home) if !home.is_empty() => home.trim_end_matches(['/', '\\']).to_string() + rest,
+        _ => path.to_string(),
+    }
 }
 
 /// This function is responsible for building virtual files from a list of file paths.
@@ -242,26 +1063,127 @@ fn build_full_path(path: &str) -> Result<String, SystemError> {
 /// # Arguments
 ///
 /// * `file_paths` - A vector of strings that holds the file paths.
+/// * `content_type` - The content type (e.g. "image") the files must have, None to keep all the files.
 ///
 /// # Returns
 ///
 /// A vector of VirtualFile structs. Empty if no paths are provided.
+/// SystemError::AccessDenied if the metadata of a file can't be read because of the permissions.
 ///
-pub fn build_virtual_files(file_paths: &Vec<String>) -> Vec<VirtualFile> {
+pub fn build_virtual_files(file_paths: &Vec<String>, content_type: Option<&str>) -> Result<Vec<VirtualFile>, SystemError> {
     let mut virtual_files: Vec<VirtualFile> = Vec::new();
     for path in file_paths {
+        if let Some(content_type) = content_type
+            && !has_content_type(path, content_type)?
+        {
+            continue;
+        }
+
         let file: VirtualFile = VirtualFile {
             name: match Path::new(path).file_name().and_then(|n| n.to_str()) {
                 Some(n) => n.to_string(),
                 None => continue,
             },
-            size: get_file_size(path),
+            size: get_file_size(path)?,
             full_path: path.to_string(),
             checksums: None,
+            ..Default::default()
         };
         virtual_files.push(file);
     }
-    virtual_files
+    Ok(virtual_files)
+}
+
+/// This function is responsible for building the entries of the symbolic links listed as such ('--include-symlinks-as-entries').
+/// A link is never followed, its size is the size of the link itself and its target is kept as written (even a dangling one).
+///
+/// # Arguments
+///
+/// * `link_paths` - The paths of the symbolic links.
+///
+/// # Returns
+///
+/// A vector of VirtualFile structs of kind "symlink", without any checksum.
+///
+pub fn build_symlink_entries(link_paths: &[String]) -> Vec<VirtualFile> {
+    link_paths
+        .iter()
+        .filter_map(|path| {
+            let name: &str = Path::new(path).file_name().and_then(|n| n.to_str())?;
+            Some(VirtualFile {
+                name: name.to_string(),
+                size: fs::symlink_metadata(path).map(|m| m.len()).unwrap_or(0),
+                full_path: path.to_string(),
+                checksums: None,
+                kind: Some("symlink"),
+                target: fs::read_link(path).ok().map(|target| target.to_string_lossy().to_string()),
+            })
+        })
+        .collect()
+}
+
+/// This function is responsible for checking the content type of a file, sniffed from its magic bytes.
+/// The extension of the file is ignored, so a mislabeled file is still recognized.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the file path.
+/// * `content_type` - A string slice that holds the expected content type (app, archive, audio, book, doc, font, image, text or video).
+///
+/// # Returns
+///
+/// True if the file has the expected content type, false otherwise (including when its type can't be determined).
+/// SystemError::AccessDenied if the file can't be read because of the permissions.
+///
+/// The "text" type isn't detected by a signature: a file is a text file if it starts with UTF-8 text.
+///
+fn has_content_type(file_path: &str, content_type: &str) -> Result<bool, SystemError> {
+    let expected: MatcherType = match content_type {
+        "app" => MatcherType::App,
+        "archive" => MatcherType::Archive,
+        "audio" => MatcherType::Audio,
+        "book" => MatcherType::Book,
+        "doc" => MatcherType::Doc,
+        "font" => MatcherType::Font,
+        "image" => MatcherType::Image,
+        "text" => return is_text_file(file_path),
+        "video" => MatcherType::Video,
+        _ => return Ok(false),
+    };
+
+    match infer::get_from_path(file_path) {
+        Ok(kind) => Ok(kind.is_some_and(|k| k.matcher_type() == expected)),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Err(SystemError::AccessDenied(file_path.to_string())),
+        Err(_) => Ok(false),
+    }
+}
+
+/// This function is responsible for checking whether a file holds UTF-8 text.
+/// Only the first TEXT_SNIFF_SIZE bytes are read: they must not be empty, hold no NUL byte and be valid UTF-8,
+/// except for a character cut at the end of the read bytes.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the file path.
+///
+/// # Returns
+///
+/// True if the file starts with UTF-8 text, false otherwise (including when it can't be read).
+/// SystemError::AccessDenied if the file can't be read because of the permissions.
+///
+fn is_text_file(file_path: &str) -> Result<bool, SystemError> {
+    let mut head: Vec<u8> = Vec::new();
+    let result: io::Result<usize> = File::open(file_path).and_then(|file| file.take(TEXT_SNIFF_SIZE).read_to_end(&mut head));
+    match result {
+        Ok(_) => Ok(!head.is_empty()
+            && !head.contains(&0)
+            && match std::str::from_utf8(&head) {
+                Ok(_) => true,
+                Err(e) => e.error_len().is_none(),
+            }),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Err(SystemError::AccessDenied(file_path.to_string())),
+        Err(_) => Ok(false),
+    }
 }
 
 /// This function is responsible for getting the size of a file.
@@ -274,21 +1196,26 @@ pub fn build_virtual_files(file_paths: &Vec<String>) -> Vec<VirtualFile> {
 /// # Returns
 ///
 /// The size of the file in bytes. 0 if the file doesn't exist or can't get the metadata.
+/// SystemError::AccessDenied if the metadata can't be read because of the permissions.
 ///
-fn get_file_size(file_path: &str) -> u64 {
-    let Ok(metadata) = fs::metadata(file_path) else { return 0 };
+fn get_file_size(file_path: &str) -> Result<u64, SystemError> {
+    let metadata: fs::Metadata = match fs::metadata(file_path) {
+        Ok(m) => m,
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => return Err(SystemError::AccessDenied(file_path.to_string())),
+        Err(_) => return Ok(0),
+    };
 
     #[cfg(target_family = "windows")]
-    return metadata.file_size();
+    return Ok(metadata.file_size());
 
     #[cfg(target_family = "unix")]
-    return metadata.size();
+    return Ok(metadata.size());
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{env, path::PathBuf};
+    use std::{env, io::Write, path::PathBuf};
 
     #[test]
     fn test_is_valid_file_path() {
@@ -322,6 +1249,16 @@ mod tests {
         assert!(is_valid_folder_path(invalid_folder_path).is_err());
     }
 
+    #[test]
+    fn test_is_valid_folder_with_file() {
+        let file_path: PathBuf = env::temp_dir().join("deepfinder_test_not_a_directory.txt");
+        std::fs::write(&file_path, "content").unwrap();
+
+        let file: &str = file_path.to_str().unwrap();
+        assert_eq!(is_valid_folder_path(file), Err(SystemError::NotADirectory(file.to_string())));
+        let _ = std::fs::remove_file(file_path);
+    }
+
     #[test]
     fn test_check_if_folder_exists() {
         let valid_relative_file_path: &str = "./test.txt";
@@ -347,13 +1284,259 @@ mod tests {
         assert_eq!(build_full_path("./test.txt"), Ok(current_path.to_string() + "/test.txt"));
     }
 
+    #[test]
+    fn test_build_full_path_without_current_dir() {
+        let missing_dir = || Err(io::Error::new(io::ErrorKind::NotFound, "No such file or directory"));
+        let known_dir: String = env::temp_dir().to_string_lossy().trim_end_matches(['/', '\\']).to_string();
+
+        // The relative paths are resolved from the known directory, the absolute paths don't need any directory.
+        assert_eq!(build_full_path_with("./test.txt", missing_dir, Some(known_dir.clone())), Ok(format!("{known_dir}/test.txt")));
+        assert_eq!(build_full_path_with(&format!("{known_dir}/test.txt"), missing_dir, None), Ok(format!("{known_dir}/test.txt")));
+
+        // Without any usable directory, the error names the path to resolve.
+        let deleted_dir: String = env::temp_dir().join("deepfinder_test_deleted_dir").to_string_lossy().to_string();
+        for known_dir in [None, Some(deleted_dir), Some("relative/dir".to_string())] {
+            assert_eq!(
+                build_full_path_with("test.txt", missing_dir, known_dir),
+                Err(SystemError::UnableToGetCurrentDir("test.txt".to_string(), io::Error::new(io::ErrorKind::NotFound, "No such file or directory")))
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_atomically_with_temp_dir() {
+        let target_dir: PathBuf = env::temp_dir().join("deepfinder_test_atomic_target");
+        let custom_temp_dir: PathBuf = env::temp_dir().join("deepfinder_test_atomic_temp");
+        fs::create_dir_all(&target_dir).unwrap();
+        fs::create_dir_all(&custom_temp_dir).unwrap();
+        let file_path: String = target_dir.join("report.json").to_string_lossy().to_string();
+        let temp_dir: String = custom_temp_dir.to_string_lossy().to_string();
+
+        // The temporary file is written in the custom directory, then moved to the target.
+        let result: io::Result<()> = write_atomically(&file_path, Some(&temp_dir), |writer| {
+            assert_eq!(fs::read_dir(&target_dir).unwrap().count(), 0);
+            assert_eq!(fs::read_dir(&custom_temp_dir).unwrap().count(), 1);
+            writer.write_all(b"[]")
+        });
+        assert!(result.is_ok());
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "[]");
+        assert_eq!(fs::read_dir(&target_dir).unwrap().count(), 1);
+        assert_eq!(fs::read_dir(&custom_temp_dir).unwrap().count(), 0);
+
+        // A failed write keeps the previous file and leaves no temporary file behind.
+        let result: io::Result<()> = write_atomically(&file_path, Some(&temp_dir), |_| Err(io::Error::other("interrupted")));
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "[]");
+        assert_eq!(fs::read_dir(&custom_temp_dir).unwrap().count(), 0);
+
+        assert!(check_writable_folder(&temp_dir).is_ok());
+        assert_eq!(fs::read_dir(&custom_temp_dir).unwrap().count(), 0);
+
+        fs::remove_dir_all(target_dir).unwrap();
+        fs::remove_dir_all(custom_temp_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn test_write_atomically_keeps_mode_and_symlink() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let dir: PathBuf = env::temp_dir().join("deepfinder_test_atomic_mode");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("reports")).unwrap();
+        let file_path: PathBuf = dir.join("reports").join("report.json");
+        fs::write(&file_path, "old").unwrap();
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        // The mode of the replaced file is kept.
+        assert!(write_atomically(&file_path.to_string_lossy(), None, |writer| writer.write_all(b"[]")).is_ok());
+        assert_eq!(fs::metadata(&file_path).unwrap().permissions().mode() & 0o777, 0o640);
+
+        // The link is kept, its (relative) target is replaced.
+        let link_path: PathBuf = dir.join("latest.json");
+        symlink("reports/report.json", &link_path).unwrap();
+        assert!(write_atomically(&link_path.to_string_lossy(), None, |writer| writer.write_all(b"[1]")).is_ok());
+        assert!(fs::symlink_metadata(&link_path).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "[1]");
+        assert_eq!(fs::metadata(&file_path).unwrap().permissions().mode() & 0o777, 0o640);
+
+        // A dangling link creates its target.
+        let dangling_path: PathBuf = dir.join("dangling.json");
+        symlink("reports/new.json", &dangling_path).unwrap();
+        assert!(write_atomically(&dangling_path.to_string_lossy(), None, |writer| writer.write_all(b"[]")).is_ok());
+        assert_eq!(fs::read_to_string(dir.join("reports").join("new.json")).unwrap(), "[]");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn test_write_atomically_across_filesystems() {
+        // A temporary directory on another filesystem (e.g. a tmpfs), when there is one.
+        let target_dir: PathBuf = env::temp_dir().join("deepfinder_test_atomic_cross_target");
+        fs::create_dir_all(&target_dir).unwrap();
+        let target_path: String = target_dir.to_string_lossy().to_string();
+        if Path::new("/dev/shm").is_dir() && !is_same_filesystem("/dev/shm", &target_path) {
+            let file_path: String = target_dir.join("report.json").to_string_lossy().to_string();
+            fs::write(&file_path, "old").unwrap();
+
+            // The content is copied next to the file then renamed, so a failed copy never truncates the file.
+            assert!(write_atomically(&file_path, Some("/dev/shm"), |writer| writer.write_all(b"[]")).is_ok());
+            assert_eq!(fs::read_to_string(&file_path).unwrap(), "[]");
+            assert_eq!(fs::read_dir(&target_dir).unwrap().count(), 1);
+            assert!(!fs::read_dir("/dev/shm").unwrap().any(|entry| entry.unwrap().file_name().to_string_lossy().starts_with(".report.json.")));
+        }
+
+        fs::remove_dir_all(target_dir).unwrap();
+    }
+
+    #[test]
+    fn test_is_case_insensitive() {
+        let dir: PathBuf = env::temp_dir().join("deepfinder_test_case_insensitive");
+        fs::create_dir_all(&dir).unwrap();
+        let output_path: String = dir.join("missing").join("report.json").to_string_lossy().to_string();
+
+        // The missing output file is checked through its folder, whose name is looked up with its case swapped.
+        let swapped_dir_exists: bool = env::temp_dir().join("DEEPFINDER_TEST_CASE_INSENSITIVE").is_dir();
+        assert_eq!(is_case_insensitive(&output_path), swapped_dir_exists);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_check_free_space() {
+        let file_path: String = env::temp_dir().join("deepfinder_test_free_space.json").to_string_lossy().to_string();
+        let temp_folder: String = env::temp_dir().to_string_lossy().to_string();
+        let nearly_full = |_: &Path| Some(1024);
+
+        assert!(check_free_space(&file_path, None, 512, nearly_full).is_ok());
+        assert_eq!(
+            check_free_space(&file_path, None, 4096, nearly_full),
+            Err(SystemError::InsufficientSpace(temp_folder, 4096, 1024))
+        );
+
+        // The temporary folder is checked too, and an unknown free space never blocks the output.
+        let full_temp_dir = |folder: &Path| if folder == Path::new("/full") { Some(0) } else { None };
+        assert!(matches!(check_free_space(&file_path, Some("/full"), 1, full_temp_dir), Err(SystemError::InsufficientSpace(folder, 1, 0)) if folder == "/full"));
+        assert!(check_free_space(&file_path, None, u64::MAX, |_| None).is_ok());
+
+        #[cfg(target_family = "unix")]
+        assert!(available_space(&env::temp_dir()).is_some());
+    }
+
+    #[test]
+    fn test_expand_tilde() {
+        let Ok(home) = env::var("HOME").or_else(|_| env::var("USERPROFILE")) else { return };
+        let home: &str = home.trim_end_matches(['/', '\\']);
+
+        assert_eq!(expand_tilde("~"), home);
+        assert_eq!(expand_tilde("~/sub"), format!("{home}/sub"));
+        assert_eq!(expand_tilde("./~file"), "./~file");
+        assert_eq!(expand_tilde("~file"), "~file");
+        assert_eq!(expand_tilde("/tmp/~/sub"), "/tmp/~/sub");
+        assert_eq!(build_full_path("~/sub"), Ok(format!("{home}/sub")));
+    }
+
+    #[test]
+    fn test_hash_reader() {
+        let file_path: PathBuf = env::temp_dir().join("deepfinder_test_hash_reader.txt");
+        let content: Vec<u8> = (0..5_000).map(|i| (i % 251) as u8).collect();
+        fs::write(&file_path, &content).unwrap();
+
+        // An in-memory buffer has the same checksum as the file holding the same bytes.
+        let checksum: String = hash_reader(io::Cursor::new(content), Sha256::new()).unwrap();
+        assert_eq!(Some(checksum), manage_hash(file_path.to_str().unwrap(), "sha256").unwrap());
+
+        let _ = fs::remove_file(file_path);
+    }
+
+    #[test]
+    fn test_io_throttle() {
+        // 4 threads reading 100 KB in total at 200 KB/s take at least 500 ms.
+        let throttle: IoThrottle = IoThrottle::new(200_000);
+        let start: Instant = Instant::now();
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| (0..25).for_each(|_| throttle.consume(1000)));
+            }
+        });
+
+        assert!(start.elapsed() >= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_manage_parallel_hash() {
+        let data: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+        let file_path: PathBuf = env::temp_dir().join("deepfinder_test_parallel_hash.bin");
+        std::fs::write(&file_path, &data).unwrap();
+        let file: &str = file_path.to_str().unwrap();
+
+        let expected: String = blake3::hash(&data).to_hex().to_string();
+        assert_eq!(manage_hash(file, "blake3"), Ok(Some(expected.clone())));
+        assert_eq!(manage_parallel_hash(file, "blake3"), Ok(Some(expected.clone())));
+        assert_eq!(hash_with_blake3(data.as_slice(), 64 * 1024, true).unwrap(), expected);
+        assert_eq!(manage_parallel_hash(file, "sha256"), manage_hash(file, "sha256"));
+
+        let _ = std::fs::remove_file(file_path);
+    }
+
+    #[test]
+    fn test_files_equal() {
+        let dir: PathBuf = env::temp_dir().join("deepfinder_test_files_equal");
+        std::fs::create_dir_all(&dir).unwrap();
+        let content: Vec<u8> = (0..50_000u32).map(|i| (i % 253) as u8).collect();
+        let mut same_length: Vec<u8> = content.clone();
+        same_length[40_000] ^= 1;
+
+        let paths: Vec<String> = ["a.bin", "b.bin", "c.bin", "d.bin"].iter().map(|name| dir.join(name).to_string_lossy().to_string()).collect();
+        std::fs::write(&paths[0], &content).unwrap();
+        std::fs::write(&paths[1], &content).unwrap();
+        std::fs::write(&paths[2], &same_length).unwrap();
+        std::fs::write(&paths[3], &content[..30_000]).unwrap();
+
+        assert_eq!(files_equal(&paths[0], &paths[1]), Ok(true));
+        assert_eq!(files_equal(&paths[0], &paths[2]), Ok(false));
+        assert_eq!(files_equal(&paths[0], &paths[3]), Ok(false));
+        assert_eq!(files_equal(&paths[3], &paths[0]), Ok(false));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_hash_if_equal() {
+        let dir: PathBuf = env::temp_dir().join("deepfinder_test_hash_if_equal");
+        std::fs::create_dir_all(&dir).unwrap();
+        let content: Vec<u8> = (0..50_000u32).map(|i| (i % 251) as u8).collect();
+        let mut same_length: Vec<u8> = content.clone();
+        same_length[40_000] ^= 1;
+
+        let paths: Vec<String> = ["a.bin", "b.bin", "c.bin", "d.bin"].iter().map(|name| dir.join(name).to_string_lossy().to_string()).collect();
+        std::fs::write(&paths[0], &content).unwrap();
+        std::fs::write(&paths[1], &content).unwrap();
+        std::fs::write(&paths[2], &same_length).unwrap();
+        std::fs::write(&paths[3], &content[..30_000]).unwrap();
+
+        // An identical pair has the real checksum of its content, a different one has none.
+        for hash in ["md5", "blake3", "xxh3"] {
+            assert_eq!(hash_if_equal(&paths[0], &paths[1], hash), manage_hash(&paths[0], hash));
+            assert_eq!(hash_if_equal(&paths[0], &paths[2], hash), Ok(None));
+            assert_eq!(hash_if_equal(&paths[0], &paths[3], hash), Ok(None));
+            assert_eq!(hash_if_equal(&paths[3], &paths[0], hash), Ok(None));
+        }
+
+        let missing_path: String = dir.join("missing.bin").to_string_lossy().to_string();
+        assert!(matches!(hash_if_equal(&paths[0], &missing_path, "md5"), Err(SystemError::UnableToReadFile(path, _)) if path == missing_path));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn test_build_virtual_files() {
         let file_paths: Vec<String> = vec![
             "/test1/test1.txt".to_string(),
             "/test2/test2.txt".to_string(),
         ];
-        let virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths);
+        let virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, None).unwrap();
 
         assert_eq!(virtual_files.len(), 2);
         assert_eq!(virtual_files[0].name, "test1.txt");
@@ -365,4 +1548,33 @@ mod tests {
         assert_eq!(virtual_files[0].full_path, "/test1/test1.txt");
         assert_eq!(virtual_files[1].full_path, "/test2/test2.txt");
     }
+
+    #[test]
+    fn test_build_virtual_files_with_content_type() {
+        let dir: PathBuf = env::temp_dir().join("deepfinder_test_content_type");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // A PNG renamed with an unrelated extension, a text file and an empty file.
+        let png_signature: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0x0D, b'I', b'H', b'D', b'R'];
+        let file_paths: Vec<String> = [("picture.dat", png_signature), ("notes.png", b"just some notes".as_slice()), ("empty.png", b"".as_slice())]
+            .iter()
+            .map(|(name, content)| {
+                let path: PathBuf = dir.join(name);
+                fs::write(&path, content).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+
+        let virtual_files: Vec<VirtualFile> = build_virtual_files(&file_paths, Some("image")).unwrap();
+        assert_eq!(virtual_files.len(), 1);
+        assert_eq!(virtual_files[0].name, "picture.dat");
+        assert!(build_virtual_files(&file_paths, Some("video")).unwrap().is_empty());
+        let text_files: Vec<VirtualFile> = build_virtual_files(&file_paths, Some("text")).unwrap();
+        assert_eq!(text_files.len(), 1);
+        assert_eq!(text_files[0].name, "notes.png");
+        assert_eq!(build_virtual_files(&file_paths, None).unwrap().len(), 3);
+
+        let _ = fs::remove_dir_all(dir);
+    }
 }